@@ -12,16 +12,15 @@ use crate::{
     databases::custom_connection::CustomDBConfig,
     env::Env,
     settings::{ConfigEntry, FtlConfEntry},
-    util::Error,
+    util::{Error, ErrorKind},
 };
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use failure::Fail;
 use shaku::Interface;
 
 #[cfg(test)]
 use {
-    crate::{
-        databases::custom_connection::{CustomSqliteConnection, CustomSqliteConnectionManager},
-        util::ErrorKind,
-    },
+    crate::databases::custom_connection::{CustomSqliteConnection, CustomSqliteConnectionManager},
     diesel::{
         connection::{Connection, TransactionManager},
         r2d2::Pool,
@@ -49,6 +48,17 @@ pub fn load_ftl_db_config(env: &Env) -> Result<CustomDBConfig, Error> {
     })
 }
 
+/// Convert an error from inserting into the Gravity database, reporting a
+/// unique constraint violation as `AlreadyExists`
+pub fn map_gravity_insert_error(error: DieselError) -> Error {
+    match error {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            Error::from(ErrorKind::AlreadyExists)
+        }
+        error => Error::from(error.context(ErrorKind::GravityDatabase)),
+    }
+}
+
 /// Start a test transaction so the database does not get modified. If a
 /// transaction is already running, it is rolled back.
 #[cfg(test)]
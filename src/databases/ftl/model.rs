@@ -110,6 +110,7 @@ impl From<FtlDbQuery> for QueryReply {
             dnssec: FtlDnssecType::Unknown as u8,
             reply: FtlQueryReplyType::Unknown as u8,
             response_time: 0,
+            details: None,
         }
     }
 }
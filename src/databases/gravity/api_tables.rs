@@ -0,0 +1,80 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Gravity Database Tables Added By The API
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::util::{Error, ErrorKind};
+use diesel::{connection::SimpleConnection, SqliteConnection};
+use failure::ResultExt;
+
+/// The tables which the API adds to the gravity database. Pi-hole does not
+/// create them, so they are created when the API starts.
+///
+/// - `adlist_domain`: the domains of each adlist from the last gravity update,
+///   including whitelisted domains
+/// - `regex_wildcard`: the regex entries which were added as wildcards, such
+///   as `*.example.com`
+const API_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS adlist_domain
+(
+    domain    TEXT    NOT NULL,
+    adlist_id INTEGER NOT NULL,
+    PRIMARY KEY (domain, adlist_id)
+);
+
+CREATE INDEX IF NOT EXISTS adlist_domain_adlist_id ON adlist_domain (adlist_id);
+
+CREATE TABLE IF NOT EXISTS regex_wildcard
+(
+    regex_id INTEGER PRIMARY KEY REFERENCES regex (id)
+);
+";
+
+/// Create the tables which the API adds to the gravity database, if they do
+/// not exist yet
+pub fn create_api_tables(db: &SqliteConnection) -> Result<(), Error> {
+    db.batch_execute(API_TABLES)
+        .context(ErrorKind::GravityDatabase)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::create_api_tables;
+    use crate::databases::gravity::{adlist_domain, connect_to_gravity_test_db};
+    use diesel::{insert_into, prelude::*};
+
+    /// The tables are created in a new database
+    #[test]
+    fn new_database() {
+        let db = SqliteConnection::establish(":memory:").unwrap();
+
+        create_api_tables(&db).unwrap();
+        insert_into(adlist_domain::table)
+            .values((
+                adlist_domain::domain.eq("ads.example.com"),
+                adlist_domain::adlist_id.eq(1),
+            ))
+            .execute(&db)
+            .unwrap();
+    }
+
+    /// Existing tables are kept
+    #[test]
+    fn existing_tables() {
+        let db = connect_to_gravity_test_db();
+        let db = &db as &SqliteConnection;
+        let count_before: i64 = adlist_domain::table.count().get_result(db).unwrap();
+
+        create_api_tables(db).unwrap();
+
+        let count_after: i64 = adlist_domain::table.count().get_result(db).unwrap();
+        assert_eq!(count_before, count_after);
+    }
+}
@@ -8,6 +8,7 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+mod api_tables;
 mod model;
 mod schema;
 #[cfg(test)]
@@ -15,4 +16,4 @@ mod testing;
 
 #[cfg(test)]
 pub use self::testing::*;
-pub use self::{model::*, schema::*};
+pub use self::{api_tables::create_api_tables, model::*, schema::*};
@@ -19,6 +19,13 @@ table! {
     }
 }
 
+table! {
+    adlist_domain (domain, adlist_id) {
+        domain -> Text,
+        adlist_id -> Integer,
+    }
+}
+
 table! {
     adlist_by_group (adlist_id, group_id) {
         adlist_id -> Integer,
@@ -92,6 +99,12 @@ table! {
     }
 }
 
+table! {
+    regex_wildcard (regex_id) {
+        regex_id -> Integer,
+    }
+}
+
 table! {
     whitelist (id) {
         id -> Integer,
@@ -112,16 +125,19 @@ table! {
 
 joinable!(adlist_by_group -> adlist (adlist_id));
 joinable!(adlist_by_group -> group (group_id));
+joinable!(adlist_domain -> adlist (adlist_id));
 joinable!(blacklist_by_group -> blacklist (blacklist_id));
 joinable!(blacklist_by_group -> group (group_id));
 joinable!(regex_by_group -> group (group_id));
 joinable!(regex_by_group -> regex (regex_id));
+joinable!(regex_wildcard -> regex (regex_id));
 joinable!(whitelist_by_group -> group (group_id));
 joinable!(whitelist_by_group -> whitelist (whitelist_id));
 
 allow_tables_to_appear_in_same_query!(
     adlist,
     adlist_by_group,
+    adlist_domain,
     blacklist,
     blacklist_by_group,
     domain_audit,
@@ -130,6 +146,7 @@ allow_tables_to_appear_in_same_query!(
     info,
     regex,
     regex_by_group,
+    regex_wildcard,
     whitelist,
     whitelist_by_group,
 );
@@ -15,4 +15,6 @@ pub mod gravity;
 
 #[cfg(test)]
 pub use self::common::{create_memory_db, FakeDatabaseService};
-pub use self::common::{load_ftl_db_config, load_gravity_db_config, DatabaseService};
+pub use self::common::{
+    load_ftl_db_config, load_gravity_db_config, map_gravity_insert_error, DatabaseService,
+};
@@ -0,0 +1,137 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Authentication Config
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// Configuration settings for authentication and sessions
+#[derive(Deserialize, Clone, Debug)]
+pub struct AuthConfig {
+    /// The number of seconds a session can go unused before it expires
+    #[serde(default = "default_session_idle_timeout")]
+    pub session_idle_timeout: u64,
+
+    /// The maximum number of seconds a session can exist, regardless of use
+    #[serde(default = "default_session_max_age")]
+    pub session_max_age: u64,
+
+    /// The number of failed login attempts from an IP before it is locked out
+    #[serde(default = "default_max_failed_attempts")]
+    pub max_failed_attempts: u32,
+
+    /// The number of seconds an IP must wait after its first failed login
+    /// attempt. The wait doubles with each additional failure.
+    #[serde(default = "default_login_backoff")]
+    pub login_backoff: u64,
+
+    /// The number of seconds an IP is locked out for after too many failed
+    /// login attempts
+    #[serde(default = "default_lockout_duration")]
+    pub lockout_duration: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            session_idle_timeout: default_session_idle_timeout(),
+            session_max_age: default_session_max_age(),
+            max_failed_attempts: default_max_failed_attempts(),
+            login_backoff: default_login_backoff(),
+            lockout_duration: default_lockout_duration(),
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn is_valid(&self) -> bool {
+        self.session_idle_timeout > 0
+            && self.session_max_age >= self.session_idle_timeout
+            && self.max_failed_attempts > 0
+            && self.lockout_duration > 0
+    }
+}
+
+fn default_session_idle_timeout() -> u64 {
+    // 30 minutes
+    30 * 60
+}
+
+fn default_session_max_age() -> u64 {
+    // 24 hours
+    24 * 60 * 60
+}
+
+fn default_max_failed_attempts() -> u32 {
+    10
+}
+
+fn default_login_backoff() -> u64 {
+    1
+}
+
+fn default_lockout_duration() -> u64 {
+    // 15 minutes
+    15 * 60
+}
+
+#[cfg(test)]
+mod test {
+    use super::AuthConfig;
+
+    /// The default config is valid
+    #[test]
+    fn valid_auth() {
+        let auth_config = AuthConfig::default();
+
+        assert!(auth_config.is_valid());
+    }
+
+    /// A zero idle timeout makes the config invalid
+    #[test]
+    fn invalid_idle_timeout() {
+        let auth_config = AuthConfig {
+            session_idle_timeout: 0,
+            ..AuthConfig::default()
+        };
+
+        assert!(!auth_config.is_valid());
+    }
+
+    /// A max age shorter than the idle timeout makes the config invalid
+    #[test]
+    fn invalid_max_age() {
+        let auth_config = AuthConfig {
+            session_idle_timeout: 60,
+            session_max_age: 30,
+            ..AuthConfig::default()
+        };
+
+        assert!(!auth_config.is_valid());
+    }
+
+    /// Allowing zero failed attempts makes the config invalid
+    #[test]
+    fn invalid_max_failed_attempts() {
+        let auth_config = AuthConfig {
+            max_failed_attempts: 0,
+            ..AuthConfig::default()
+        };
+
+        assert!(!auth_config.is_valid());
+    }
+
+    /// A zero lockout duration makes the config invalid
+    #[test]
+    fn invalid_lockout_duration() {
+        let auth_config = AuthConfig {
+            lockout_duration: 0,
+            ..AuthConfig::default()
+        };
+
+        assert!(!auth_config.is_valid());
+    }
+}
@@ -39,6 +39,12 @@ pub struct Files {
     black_list: String,
     #[serde(default = "default_black_list_backup")]
     black_list_backup: String,
+    #[serde(default = "default_api_tokens")]
+    api_tokens: String,
+    #[serde(default = "default_accounts")]
+    accounts: String,
+    #[serde(default = "default_adlist_status")]
+    adlist_status: String,
 }
 
 impl Default for Files {
@@ -56,6 +62,9 @@ impl Default for Files {
             gravity_backup: default_gravity_backup(),
             black_list: default_black_list(),
             black_list_backup: default_black_list_backup(),
+            api_tokens: default_api_tokens(),
+            accounts: default_accounts(),
+            adlist_status: default_adlist_status(),
         }
     }
 }
@@ -75,6 +84,9 @@ impl Files {
             &self.gravity_backup,
             &self.black_list,
             &self.black_list_backup,
+            &self.api_tokens,
+            &self.accounts,
+            &self.adlist_status,
         ]
         .iter()
         .all(|file| Path::new(file).is_absolute())
@@ -95,6 +107,9 @@ impl Files {
             PiholeFile::GravityBackup => &self.gravity_backup,
             PiholeFile::BlackList => &self.black_list,
             PiholeFile::BlackListBackup => &self.black_list_backup,
+            PiholeFile::ApiTokens => &self.api_tokens,
+            PiholeFile::Accounts => &self.accounts,
+            PiholeFile::AdlistStatus => &self.adlist_status,
         }
     }
 }
@@ -120,6 +135,9 @@ default!(default_gravity, Gravity);
 default!(default_gravity_backup, GravityBackup);
 default!(default_black_list, BlackList);
 default!(default_black_list_backup, BlackListBackup);
+default!(default_api_tokens, ApiTokens);
+default!(default_accounts, Accounts);
+default!(default_adlist_status, AdlistStatus);
 
 #[cfg(test)]
 mod test {
@@ -8,9 +8,13 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+mod auth;
 mod file_locations;
 mod general;
 mod root_config;
 mod web;
 
-pub use self::root_config::{Config, DEFAULT_CONFIG_LOCATION};
+pub use self::{
+    auth::AuthConfig,
+    root_config::{Config, DEFAULT_CONFIG_LOCATION},
+};
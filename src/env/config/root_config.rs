@@ -9,7 +9,7 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    env::config::{file_locations::Files, general::General, web::WebConfig},
+    env::config::{auth::AuthConfig, file_locations::Files, general::General, web::WebConfig},
     util::{Error, ErrorKind},
 };
 use failure::{Fail, ResultExt};
@@ -31,6 +31,8 @@ pub struct Config {
     pub file_locations: Files,
     #[serde(default)]
     pub web: WebConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
 impl Config {
@@ -74,7 +76,10 @@ impl Config {
 
     /// Check if the config settings are valid
     pub fn is_valid(&self) -> bool {
-        self.general.is_valid() && self.file_locations.is_valid() && self.web.is_valid()
+        self.general.is_valid()
+            && self.file_locations.is_valid()
+            && self.web.is_valid()
+            && self.auth.is_valid()
     }
 }
 
@@ -23,6 +23,9 @@ pub enum PiholeFile {
     GravityBackup,
     BlackList,
     BlackListBackup,
+    ApiTokens,
+    Accounts,
+    AdlistStatus,
 }
 
 impl PiholeFile {
@@ -41,6 +44,9 @@ impl PiholeFile {
             PiholeFile::GravityBackup => "/etc/pihole/gravity.list.bck",
             PiholeFile::BlackList => "/etc/pihole/black.list",
             PiholeFile::BlackListBackup => "/etc/pihole/black.list.bck",
+            PiholeFile::ApiTokens => "/etc/pihole/api_tokens.json",
+            PiholeFile::Accounts => "/etc/pihole/accounts.json",
+            PiholeFile::AdlistStatus => "/etc/pihole/adlist_status.json",
         }
     }
 }
@@ -13,7 +13,7 @@ mod env_impl;
 mod file;
 
 pub use self::{
-    config::{Config, DEFAULT_CONFIG_LOCATION},
+    config::{AuthConfig, Config, DEFAULT_CONFIG_LOCATION},
     env_impl::Env,
     file::PiholeFile,
 };
@@ -83,6 +83,18 @@ impl FtlClient {
         self
     }
 
+    /// Set the time of the client's last query
+    #[cfg(test)]
+    pub fn with_last_query_time(mut self, last_query_time: u64) -> Self {
+        self.last_query_time = last_query_time as libc::time_t;
+        self
+    }
+
+    /// Get the time of the client's last query
+    pub fn get_last_query_time(&self) -> u64 {
+        self.last_query_time as u64
+    }
+
     /// Get the IP address of the client
     pub fn get_ip<'a>(&self, strings: &'a FtlStrings) -> &'a str {
         strings.get_str(self.ip_str_id as usize).unwrap_or_default()
@@ -9,6 +9,9 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{ftl::FtlQueryType, settings::FtlPrivacyLevel};
+
+#[cfg(test)]
+use crate::ftl::memory_model::MAGIC_BYTE;
 use rocket::{
     form,
     form::{FromFormField, ValueField},
@@ -53,6 +56,54 @@ impl FtlQuery {
     pub fn is_blocked(&self) -> bool {
         BLOCKED_STATUSES.contains(&(self.status as i32))
     }
+
+    /// Check if the query was sent to an upstream, in which case
+    /// `upstream_id` is valid
+    pub fn is_forwarded(&self) -> bool {
+        matches!(
+            self.status,
+            FtlQueryStatus::Forward
+                | FtlQueryStatus::ExternalBlockIp
+                | FtlQueryStatus::ExternalBlockNull
+                | FtlQueryStatus::ExternalBlockNxdomainRa
+        )
+    }
+
+    /// Get the response time of the query in tenths of a millisecond, or
+    /// `None` if no response was received (the response time is at least 30
+    /// minutes)
+    pub fn get_response_time(&self) -> Option<u32> {
+        if self.response_time < 18_000_000 {
+            Some(self.response_time as u32)
+        } else {
+            None
+        }
+    }
+}
+
+/// A complete, forwarded A query in the first overTime slot. Tests override
+/// the fields they care about with struct update syntax.
+#[cfg(test)]
+impl Default for FtlQuery {
+    fn default() -> Self {
+        FtlQuery {
+            magic: MAGIC_BYTE,
+            id: 0,
+            database_id: 0,
+            timestamp: 1,
+            time_index: 1,
+            response_time: 1,
+            domain_id: 0,
+            client_id: 0,
+            upstream_id: 0,
+            query_type: FtlQueryType::A,
+            status: FtlQueryStatus::Forward,
+            reply_type: FtlQueryReplyType::IP,
+            dnssec_type: FtlDnssecType::Unspecified,
+            is_complete: true,
+            privacy_level: FtlPrivacyLevel::ShowAll,
+        }
+    }
 }
 
 /// The statuses an FTL query can have
@@ -0,0 +1,195 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Account Endpoints
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::{Admin, AuthData},
+    services::{
+        accounts::{Account, AccountService, Role},
+        PiholeModule,
+    },
+    util::{reply_data, reply_success, Reply},
+};
+use rocket::{serde::json::Json, State};
+use shaku_rocket::InjectProvided;
+
+/// Represents an API input to create an account
+#[derive(Deserialize)]
+pub struct AccountInput {
+    name: String,
+    role: Role,
+    password: String,
+}
+
+/// Get the accounts, starting with the primary account. The password hashes
+/// are not included.
+#[get("/auth/accounts")]
+pub fn get_accounts(
+    _auth: Admin,
+    account_service: InjectProvided<PiholeModule, dyn AccountService>,
+) -> Reply {
+    let mut accounts = vec![Account::primary()];
+    accounts.extend(account_service.list()?);
+
+    reply_data(accounts)
+}
+
+/// Create an account
+#[post("/auth/accounts", data = "<account_input>")]
+pub fn create_account(
+    _auth: Admin,
+    account_service: InjectProvided<PiholeModule, dyn AccountService>,
+    account_input: Json<AccountInput>,
+) -> Reply {
+    reply_data(account_service.create(
+        &account_input.name,
+        account_input.role,
+        &account_input.password,
+    )?)
+}
+
+/// Delete an account and revoke its sessions
+#[delete("/auth/accounts/<name>")]
+pub fn delete_account(
+    _auth: Admin,
+    auth_data: &State<AuthData>,
+    account_service: InjectProvided<PiholeModule, dyn AccountService>,
+    name: String,
+) -> Reply {
+    account_service.delete(&name)?;
+    auth_data.revoke_account_sessions(&name);
+
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        env::PiholeFile,
+        services::accounts::{Account, AccountService, MockAccountService, Role},
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Header, Method, Status};
+    use serde_json::Value;
+
+    const TEST_ACCOUNTS: &str = "[{\"name\":\"viewer\",\"role\":\"viewer\",\"hash\":\"$argon2id$v=19$m=4096,t=3,p=1$c2FsdHNhbHQ$aGFzaA\"}]";
+
+    /// Build a mock service which logs in the viewer account
+    fn viewer_service() -> MockAccountService {
+        let mut service = MockAccountService::new();
+
+        service
+            .expect_verify()
+            .with(eq("viewer"), eq("test_key"))
+            .return_const(Ok(Some(Account {
+                name: "viewer".to_owned(),
+                role: Role::Viewer,
+            })));
+
+        service
+    }
+
+    /// The primary account is listed along with the named accounts
+    #[test]
+    fn list_accounts() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/accounts")
+            .file(PiholeFile::Accounts, TEST_ACCOUNTS)
+            .expect_json(json!([
+                { "name": "admin", "role": "admin" },
+                { "name": "viewer", "role": "viewer" }
+            ]))
+            .test();
+    }
+
+    /// Creating an account passes the input to the service
+    #[test]
+    fn create_account() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/accounts")
+            .method(Method::Post)
+            .mock_provider::<dyn AccountService>(Box::new(|_| {
+                let mut service = MockAccountService::new();
+
+                service
+                    .expect_create()
+                    .with(eq("viewer"), eq(Role::Viewer), eq("hunter2"))
+                    .return_const(Ok(Account {
+                        name: "viewer".to_owned(),
+                        role: Role::Viewer,
+                    }));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "name": "viewer", "role": "viewer", "password": "hunter2" }))
+            .expect_json(json!({ "name": "viewer", "role": "viewer" }))
+            .test();
+    }
+
+    /// Deleting an account removes it from the file
+    #[test]
+    fn delete_account() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/accounts/viewer")
+            .method(Method::Delete)
+            .file_expect(PiholeFile::Accounts, TEST_ACCOUNTS, "[]")
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Viewers can authenticate and read statistics
+    #[test]
+    fn viewer_can_authenticate() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .header(Header::new("X-Pi-hole-User", "viewer"))
+            .mock_provider::<dyn AccountService>(Box::new(|_| Ok(Box::new(viewer_service()))))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Viewers can not modify the lists
+    #[test]
+    fn viewer_can_not_write_lists() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/whitelist")
+            .method(Method::Post)
+            .header(Header::new("X-Pi-hole-User", "viewer"))
+            .mock_provider::<dyn AccountService>(Box::new(|_| Ok(Box::new(viewer_service()))))
+            .body(json!({ "domain": "example.com" }))
+            .expect_status(Status::Forbidden)
+            .expect_json(json!({
+                "error": {
+                    "key": "forbidden",
+                    "message": "Forbidden",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// Viewers can not manage accounts
+    #[test]
+    fn viewer_can_not_list_accounts() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/accounts")
+            .header(Header::new("X-Pi-hole-User", "viewer"))
+            .mock_provider::<dyn AccountService>(Box::new(|_| Ok(Box::new(viewer_service()))))
+            .expect_status(Status::Forbidden)
+            .expect_json(json!({
+                "error": {
+                    "key": "forbidden",
+                    "message": "Forbidden",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+}
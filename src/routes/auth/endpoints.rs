@@ -0,0 +1,313 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Authentication Endpoints
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::Env,
+    routes::auth::{session::now, Admin, AuthData, User},
+    services::{
+        accounts::{AccountService, PRIMARY_ACCOUNT},
+        PiholeModule,
+    },
+    util::{reply_data, reply_error, reply_success, Error, ErrorKind, Reply},
+};
+use rocket::{http::CookieJar, serde::json::Json, State};
+use shaku_rocket::{Inject, InjectProvided};
+use std::net::IpAddr;
+
+/// Represents an API input to change the password
+#[derive(Deserialize)]
+pub struct PasswordChange {
+    old_password: String,
+    new_password: String,
+}
+
+/// Provides an endpoint to authenticate or check if already authenticated
+#[get("/auth")]
+pub fn check(_user: User) -> Reply {
+    reply_success()
+}
+
+/// Clears the user's authentication
+#[delete("/auth")]
+pub fn logout(user: User, cookies: &CookieJar, auth_data: &State<AuthData>) -> Reply {
+    user.logout(cookies, auth_data);
+    reply_success()
+}
+
+/// Change the password of the current account. Changing the primary account's
+/// password revokes all sessions, while other accounts only have their own
+/// sessions revoked. A wrong old password counts as a failed login attempt.
+#[put("/auth/password", data = "<data>")]
+pub fn change_password(
+    user: User,
+    client_ip: Option<IpAddr>,
+    cookies: &CookieJar,
+    auth_data: &State<AuthData>,
+    env: Inject<PiholeModule, Env>,
+    account_service: InjectProvided<PiholeModule, dyn AccountService>,
+    data: Json<PasswordChange>,
+) -> Reply {
+    let ip = client_ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    // Reject the attempt if the IP has failed to log in too often
+    if let Err(retry_after) = auth_data.check_throttle(&ip) {
+        return Err(Error::from(ErrorKind::TooManyRequests(retry_after)));
+    }
+
+    let result = if user.name == PRIMARY_ACCOUNT {
+        auth_data.change_password(&env, &data.old_password, &data.new_password)
+    } else {
+        account_service.change_password(&user.name, &data.old_password, &data.new_password)
+    };
+
+    if let Err(e) = result {
+        if e.kind() == ErrorKind::Unauthorized {
+            warn_!("Failed password change attempt from {}", ip);
+            auth_data.record_failure(&ip);
+        }
+
+        return Err(e);
+    }
+
+    if user.name != PRIMARY_ACCOUNT {
+        auth_data.revoke_account_sessions(&user.name);
+    }
+
+    user.logout(cookies, auth_data);
+
+    reply_success()
+}
+
+/// Get the active sessions. The session of the current user is marked.
+#[get("/auth/sessions")]
+pub fn get_sessions(admin: Admin, auth_data: &State<AuthData>) -> Reply {
+    let sessions: Vec<_> = auth_data
+        .sessions()
+        .into_iter()
+        .map(|session| {
+            json!({
+                "id": session.id,
+                "account": session.account,
+                "role": session.role,
+                "ip": session.ip,
+                "created": session.created,
+                "last_seen": session.last_seen,
+                "current": session.id == admin.0.id
+            })
+        })
+        .collect();
+
+    reply_data(sessions)
+}
+
+/// Revoke a session, so that it can no longer be used
+#[delete("/auth/sessions/<id>")]
+pub fn delete_session(_admin: Admin, auth_data: &State<AuthData>, id: usize) -> Reply {
+    if auth_data.revoke_session(id) {
+        reply_success()
+    } else {
+        reply_error(ErrorKind::NotFound)
+    }
+}
+
+/// Get the recent lockouts caused by failed login attempts. Lockouts which
+/// have not ended yet are marked as active.
+#[get("/auth/lockouts")]
+pub fn get_lockouts(_admin: Admin, auth_data: &State<AuthData>) -> Reply {
+    let now = now();
+    let lockouts: Vec<_> = auth_data
+        .lockout_events()
+        .into_iter()
+        .map(|event| {
+            json!({
+                "ip": event.ip,
+                "failed_attempts": event.failed_attempts,
+                "locked_at": event.locked_at,
+                "locked_until": event.locked_until,
+                "active": event.locked_until > now
+            })
+        })
+        .collect();
+
+    reply_data(lockouts)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{env::PiholeFile, testing::TestBuilder};
+    use rocket::http::{Header, Method, Status};
+    use serde_json::Value;
+
+    /// Providing the correct authentication should authorize the request
+    #[test]
+    fn authenticated() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .should_auth(true)
+            .expect_json(json!({
+                "status": "success"
+            }))
+            .test()
+    }
+
+    /// Providing no authorization should not authorize the request
+    #[test]
+    fn unauthenticated() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .should_auth(false)
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test()
+    }
+
+    /// Providing incorrect authorization should not authorize the request
+    #[test]
+    fn wrong_password() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .should_auth(false)
+            .header(Header::new(
+                "X-Pi-hole-Authenticate",
+                "obviously_not_correct",
+            ))
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// If no password is set for the API, an unauthenticated auth request is
+    /// authorized
+    #[test]
+    fn no_password_required() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .should_auth(false)
+            .auth_required(false)
+            .expect_json(json!({
+                "status": "success"
+            }))
+            .test();
+    }
+
+    /// Listing sessions requires authentication
+    #[test]
+    fn sessions_unauthenticated() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/sessions")
+            .should_auth(false)
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// An existing session can be revoked. The request's own session is the
+    /// first session created by the server.
+    #[test]
+    fn revoke_session() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/sessions/1")
+            .method(Method::Delete)
+            .expect_json(json!({
+                "status": "success"
+            }))
+            .test();
+    }
+
+    /// Revoking a session which does not exist is an error
+    #[test]
+    fn revoke_missing_session() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/sessions/1000")
+            .method(Method::Delete)
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// Changing the password requires the correct old password
+    #[test]
+    fn change_password_wrong_old_password() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/password")
+            .method(Method::Put)
+            .file(PiholeFile::SetupVars, "WEBPASSWORD=test_key\n")
+            .body(json!({
+                "old_password": "wrong",
+                "new_password": "hunter2"
+            }))
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// The new password can not be empty
+    #[test]
+    fn change_password_empty() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/password")
+            .method(Method::Put)
+            .file(PiholeFile::SetupVars, "WEBPASSWORD=test_key\n")
+            .body(json!({
+                "old_password": "test_key",
+                "new_password": ""
+            }))
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "bad_request",
+                    "message": "Bad request",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// There are no lockouts until an IP fails to log in too often
+    #[test]
+    fn no_lockouts() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/lockouts")
+            .expect_json(json!([]))
+            .test();
+    }
+}
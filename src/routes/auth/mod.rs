@@ -0,0 +1,23 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Authentication Functions And Routes
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+mod accounts;
+mod endpoints;
+mod scope;
+mod session;
+mod throttle;
+mod tokens;
+mod totp;
+mod user;
+
+pub use self::{
+    accounts::*, endpoints::*, scope::*, session::SessionInfo, throttle::RetryAfter, tokens::*,
+    totp::*, user::*,
+};
@@ -0,0 +1,105 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Scoped Authentication Guards
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::User,
+    services::{
+        api_tokens::{ApiTokenService, TokenScope},
+        PiholeModule,
+    },
+    util::{Error, ErrorKind},
+};
+use rocket::request::{self, FromRequest, Outcome, Request};
+use shaku::HasProvider;
+
+const TOKEN_HEADER: &str = "X-Pi-hole-Token";
+
+/// Get a service from the dependency injection module. Request guards can not
+/// use `InjectProvided`, so the module is loaded from the server state.
+pub(super) fn provide_service<I: ?Sized + 'static>(request: &Request<'_>) -> Result<Box<I>, Error>
+where
+    PiholeModule: HasProvider<I>,
+{
+    let module = request
+        .rocket()
+        .state::<Box<PiholeModule>>()
+        .ok_or(ErrorKind::Unknown)?;
+
+    module
+        .provide()
+        .map_err(|_| Error::from(ErrorKind::Unknown))
+}
+
+/// Check that the request is allowed to use the scope. If an API token is
+/// provided, it must have been granted the scope. Otherwise, the request must
+/// be authenticated as a `User` whose role grants the scope.
+async fn authorize(request: &Request<'_>, scope: TokenScope) -> request::Outcome<(), Error> {
+    let secret = match request.headers().get_one(TOKEN_HEADER) {
+        Some(secret) => secret,
+        None => {
+            return match User::from_request(request).await {
+                Outcome::Success(user) if user.role.has_scope(scope) => Outcome::Success(()),
+                Outcome::Success(_) => Error::from(ErrorKind::Forbidden).into_outcome(),
+                Outcome::Failure(failure) => Outcome::Failure(failure),
+                Outcome::Forward(forward) => Outcome::Forward(forward),
+            }
+        }
+    };
+
+    let token_service: Box<dyn ApiTokenService> = match provide_service(request) {
+        Ok(token_service) => token_service,
+        Err(e) => return e.into_outcome(),
+    };
+
+    match token_service.verify(secret) {
+        Ok(Some(token)) if token.has_scope(scope) => Outcome::Success(()),
+        Ok(Some(_)) => Error::from(ErrorKind::Forbidden).into_outcome(),
+        Ok(None) => Error::from(ErrorKind::Unauthorized).into_outcome(),
+        Err(e) => e.into_outcome(),
+    }
+}
+
+/// Create a request guard which requires a token scope
+macro_rules! scope_guard {
+    ($(#[$attr:meta])* $name:ident, $scope:expr) => {
+        $(#[$attr])*
+        pub struct $name;
+
+        #[rocket::async_trait]
+        impl<'r> FromRequest<'r> for $name {
+            type Error = Error;
+
+            async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+                authorize(request, $scope).await.map(|_| $name)
+            }
+        }
+    };
+}
+
+scope_guard!(
+    /// Requires a user or a token with the `stats:read` scope
+    CanReadStats,
+    TokenScope::StatsRead
+);
+scope_guard!(
+    /// Requires a user or a token with the `lists:write` scope
+    CanWriteLists,
+    TokenScope::ListsWrite
+);
+scope_guard!(
+    /// Requires a user or a token with the `settings:write` scope
+    CanWriteSettings,
+    TokenScope::SettingsWrite
+);
+scope_guard!(
+    /// Requires a user or a token with the `dns:status` scope
+    CanChangeDnsStatus,
+    TokenScope::DnsStatus
+);
@@ -0,0 +1,327 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Authentication Session Store
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::AuthConfig,
+    services::accounts::{Account, Role},
+};
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The number of random bytes used to generate a session token
+const TOKEN_BYTES: usize = 32;
+
+/// Information about an active session. The session token is not included so
+/// that sessions can be listed without leaking their credentials.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct SessionInfo {
+    /// The public ID of the session, used to revoke it
+    pub id: usize,
+    /// The name of the account which logged in
+    pub account: String,
+    /// The role of the account when it logged in. Requests check the account's
+    /// current role instead.
+    pub role: Role,
+    /// The IP address the session was created from, if known
+    pub ip: Option<String>,
+    /// When the session was created (Unix timestamp)
+    pub created: u64,
+    /// When the session was last used (Unix timestamp)
+    pub last_seen: u64,
+}
+
+/// Stores the active sessions, keyed by their random token. Sessions expire
+/// after being idle for too long, or after reaching their maximum age.
+pub struct SessionStore {
+    sessions: Mutex<HashMap<String, SessionInfo>>,
+    next_id: AtomicUsize,
+    idle_timeout: u64,
+    max_age: u64,
+}
+
+impl SessionStore {
+    /// Create an empty session store using the timeouts in the config
+    pub fn new(config: &AuthConfig) -> SessionStore {
+        SessionStore {
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicUsize::new(1),
+            idle_timeout: config.session_idle_timeout,
+            max_age: config.session_max_age,
+        }
+    }
+
+    /// Create a new session for the account and return its token and info
+    pub fn create(&self, account: Account, ip: Option<String>) -> (String, SessionInfo) {
+        self.create_at(account, ip, now())
+    }
+
+    /// Get the session referenced by the token and mark it as used. If the
+    /// session does not exist or has expired, `None` is returned.
+    pub fn touch(&self, token: &str) -> Option<SessionInfo> {
+        self.touch_at(token, now())
+    }
+
+    /// List the active sessions, ordered by ID
+    pub fn list(&self) -> Vec<SessionInfo> {
+        self.list_at(now())
+    }
+
+    /// Remove the session referenced by the token
+    pub fn remove_token(&self, token: &str) {
+        self.lock_sessions().remove(token);
+    }
+
+    /// Revoke every session
+    pub fn revoke_all(&self) {
+        self.lock_sessions().clear();
+    }
+
+    /// Revoke every session of the account
+    pub fn revoke_account(&self, account: &str) {
+        self.lock_sessions()
+            .retain(|_, session| session.account != account);
+    }
+
+    /// Revoke the session with the given public ID. Returns false if no
+    /// session with the ID exists.
+    pub fn revoke(&self, id: usize) -> bool {
+        let mut sessions = self.lock_sessions();
+        let len_before = sessions.len();
+
+        sessions.retain(|_, session| session.id != id);
+
+        sessions.len() != len_before
+    }
+
+    fn create_at(&self, account: Account, ip: Option<String>, now: u64) -> (String, SessionInfo) {
+        let token = generate_token();
+        let info = SessionInfo {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            account: account.name,
+            role: account.role,
+            ip,
+            created: now,
+            last_seen: now,
+        };
+
+        let mut sessions = self.lock_sessions();
+
+        // Clean out expired sessions so the store does not grow unbounded
+        self.remove_expired(&mut sessions, now);
+        sessions.insert(token.clone(), info.clone());
+
+        (token, info)
+    }
+
+    fn touch_at(&self, token: &str, now: u64) -> Option<SessionInfo> {
+        let mut sessions = self.lock_sessions();
+        let expired = match sessions.get(token) {
+            Some(session) => self.is_expired(session, now),
+            None => return None,
+        };
+
+        if expired {
+            sessions.remove(token);
+            return None;
+        }
+
+        sessions.get_mut(token).map(|session| {
+            session.last_seen = now;
+            session.clone()
+        })
+    }
+
+    fn list_at(&self, now: u64) -> Vec<SessionInfo> {
+        let mut sessions = self.lock_sessions();
+        self.remove_expired(&mut sessions, now);
+
+        let mut list: Vec<SessionInfo> = sessions.values().cloned().collect();
+        list.sort_by_key(|session| session.id);
+
+        list
+    }
+
+    /// Check if the session has been idle for too long or is too old
+    fn is_expired(&self, session: &SessionInfo, now: u64) -> bool {
+        now.saturating_sub(session.last_seen) >= self.idle_timeout
+            || now.saturating_sub(session.created) >= self.max_age
+    }
+
+    fn remove_expired(&self, sessions: &mut HashMap<String, SessionInfo>, now: u64) {
+        sessions.retain(|_, session| !self.is_expired(session, now));
+    }
+
+    /// Lock the session map. A panic while holding the lock does not leave the
+    /// map in an invalid state, so poisoning is ignored.
+    fn lock_sessions(&self) -> MutexGuard<HashMap<String, SessionInfo>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Generate a random session token
+fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+
+    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Get the current Unix timestamp in seconds
+pub(super) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Current time is older than epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SessionInfo, SessionStore};
+    use crate::{
+        env::AuthConfig,
+        services::accounts::{Account, Role},
+    };
+
+    fn store() -> SessionStore {
+        SessionStore::new(&AuthConfig {
+            session_idle_timeout: 100,
+            session_max_age: 1000,
+            ..AuthConfig::default()
+        })
+    }
+
+    /// A new session can be looked up by its token
+    #[test]
+    fn create_and_touch() {
+        let store = store();
+        let (token, info) = store.create_at(Account::primary(), Some("10.1.1.1".to_owned()), 0);
+
+        assert_eq!(
+            store.touch_at(&token, 50),
+            Some(SessionInfo {
+                last_seen: 50,
+                ..info
+            })
+        );
+    }
+
+    /// Tokens are random and unique per session
+    #[test]
+    fn unique_tokens() {
+        let store = store();
+        let (token_a, info_a) = store.create_at(Account::primary(), None, 0);
+        let (token_b, info_b) = store.create_at(Account::primary(), None, 0);
+
+        assert_ne!(token_a, token_b);
+        assert_ne!(info_a.id, info_b.id);
+    }
+
+    /// Unknown tokens are rejected
+    #[test]
+    fn unknown_token() {
+        let store = store();
+        store.create_at(Account::primary(), None, 0);
+
+        assert_eq!(store.touch_at("not_a_token", 0), None);
+    }
+
+    /// Sessions expire after being idle for too long
+    #[test]
+    fn idle_timeout() {
+        let store = store();
+        let (token, _) = store.create_at(Account::primary(), None, 0);
+
+        assert_eq!(store.touch_at(&token, 100), None);
+        assert!(store.list_at(100).is_empty());
+    }
+
+    /// Using a session keeps it from expiring due to the idle timeout
+    #[test]
+    fn touch_extends_idle_timeout() {
+        let store = store();
+        let (token, _) = store.create_at(Account::primary(), None, 0);
+
+        assert!(store.touch_at(&token, 90).is_some());
+        assert!(store.touch_at(&token, 180).is_some());
+    }
+
+    /// Sessions expire after the max age, even if they are in use
+    #[test]
+    fn max_age() {
+        let store = store();
+        let (token, _) = store.create_at(Account::primary(), None, 0);
+
+        for now in (90..1000).step_by(90) {
+            assert!(store.touch_at(&token, now).is_some());
+        }
+
+        assert_eq!(store.touch_at(&token, 1000), None);
+    }
+
+    /// Revoking a session by ID removes it
+    #[test]
+    fn revoke() {
+        let store = store();
+        let (token, info) = store.create_at(Account::primary(), None, 0);
+        let (other_token, _) = store.create_at(Account::primary(), None, 0);
+
+        assert!(store.revoke(info.id));
+        assert!(!store.revoke(info.id));
+        assert_eq!(store.touch_at(&token, 1), None);
+        assert!(store.touch_at(&other_token, 1).is_some());
+    }
+
+    /// Revoking all sessions removes every session
+    #[test]
+    fn revoke_all() {
+        let store = store();
+        let (token, _) = store.create_at(Account::primary(), None, 0);
+        store.create_at(Account::primary(), None, 0);
+
+        store.revoke_all();
+
+        assert_eq!(store.touch_at(&token, 1), None);
+        assert!(store.list_at(1).is_empty());
+    }
+
+    /// Revoking an account's sessions leaves other accounts logged in
+    #[test]
+    fn revoke_account() {
+        let store = store();
+        let viewer = Account {
+            name: "viewer".to_owned(),
+            role: Role::Viewer,
+        };
+        let (token, _) = store.create_at(viewer, None, 0);
+        let (other_token, _) = store.create_at(Account::primary(), None, 0);
+
+        store.revoke_account("viewer");
+
+        assert_eq!(store.touch_at(&token, 1), None);
+        assert!(store.touch_at(&other_token, 1).is_some());
+    }
+
+    /// Sessions are listed in ID order
+    #[test]
+    fn list() {
+        let store = store();
+        let (_, first) = store.create_at(Account::primary(), None, 0);
+        let (_, second) = store.create_at(Account::primary(), None, 0);
+
+        assert_eq!(store.list_at(0), vec![first, second]);
+    }
+}
@@ -0,0 +1,258 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Failed Login Throttling
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{env::AuthConfig, routes::auth::session::now};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// The maximum number of lockout events which are remembered
+const MAX_LOCKOUT_EVENTS: usize = 100;
+
+/// The number of seconds a client must wait before retrying. This is stored in
+/// the request's local cache so the 429 catcher can set `Retry-After`.
+pub struct RetryAfter(pub u64);
+
+/// A record of an IP being locked out after too many failed login attempts
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct LockoutEvent {
+    /// The IP address which was locked out
+    pub ip: String,
+    /// The number of failed attempts which triggered the lockout
+    pub failed_attempts: u32,
+    /// When the lockout started (Unix timestamp)
+    pub locked_at: u64,
+    /// When the lockout ends (Unix timestamp)
+    pub locked_until: u64,
+}
+
+/// The failed login attempts of a single IP
+struct Attempts {
+    failures: u32,
+    last_failure: u64,
+    blocked_until: u64,
+}
+
+/// Tracks failed login attempts per IP. Each failure blocks the IP for an
+/// exponentially increasing amount of time, and too many failures lock the IP
+/// out completely. Failures are forgotten after a successful login, or after
+/// the lockout duration has passed without another failure.
+pub struct LoginThrottle {
+    attempts: Mutex<HashMap<String, Attempts>>,
+    events: Mutex<VecDeque<LockoutEvent>>,
+    max_failed_attempts: u32,
+    backoff: u64,
+    lockout_duration: u64,
+}
+
+impl LoginThrottle {
+    /// Create an empty throttle using the thresholds in the config
+    pub fn new(config: &AuthConfig) -> LoginThrottle {
+        LoginThrottle {
+            attempts: Mutex::new(HashMap::new()),
+            events: Mutex::new(VecDeque::new()),
+            max_failed_attempts: config.max_failed_attempts,
+            backoff: config.login_backoff,
+            lockout_duration: config.lockout_duration,
+        }
+    }
+
+    /// Check if the IP is allowed to attempt a login. If it is blocked, the
+    /// number of seconds until it can try again is returned.
+    pub fn check(&self, ip: &str) -> Result<(), u64> {
+        self.check_at(ip, now())
+    }
+
+    /// Record a failed login attempt from the IP
+    pub fn record_failure(&self, ip: &str) {
+        self.record_failure_at(ip, now())
+    }
+
+    /// Forget the failed login attempts of the IP
+    pub fn record_success(&self, ip: &str) {
+        lock(&self.attempts).remove(ip);
+    }
+
+    /// Get the lockout events, oldest first
+    pub fn lockout_events(&self) -> Vec<LockoutEvent> {
+        lock(&self.events).iter().cloned().collect()
+    }
+
+    fn check_at(&self, ip: &str, now: u64) -> Result<(), u64> {
+        match lock(&self.attempts).get(ip) {
+            Some(attempts) if attempts.blocked_until > now => Err(attempts.blocked_until - now),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, ip: &str, now: u64) {
+        let mut attempts_map = lock(&self.attempts);
+
+        // Clean out stale entries so the map does not grow unbounded
+        attempts_map.retain(|_, attempts| !self.is_stale(attempts, now));
+
+        let attempts = attempts_map.entry(ip.to_owned()).or_insert(Attempts {
+            failures: 0,
+            last_failure: now,
+            blocked_until: now,
+        });
+
+        attempts.failures += 1;
+        attempts.last_failure = now;
+
+        if attempts.failures >= self.max_failed_attempts {
+            attempts.blocked_until = now + self.lockout_duration;
+
+            warn_!(
+                "Locked out {} for {} seconds after {} failed login attempts",
+                ip,
+                self.lockout_duration,
+                attempts.failures
+            );
+
+            let mut events = lock(&self.events);
+            if events.len() >= MAX_LOCKOUT_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(LockoutEvent {
+                ip: ip.to_owned(),
+                failed_attempts: attempts.failures,
+                locked_at: now,
+                locked_until: attempts.blocked_until,
+            });
+        } else {
+            attempts.blocked_until = now + self.backoff_delay(attempts.failures);
+        }
+    }
+
+    /// Get the number of seconds to block an IP after the given number of
+    /// failures. The delay doubles with each failure, up to the lockout
+    /// duration.
+    fn backoff_delay(&self, failures: u32) -> u64 {
+        2u64.checked_pow(failures - 1)
+            .and_then(|factor| factor.checked_mul(self.backoff))
+            .map(|delay| delay.min(self.lockout_duration))
+            .unwrap_or(self.lockout_duration)
+    }
+
+    /// Check if the failures should be forgotten because the IP is no longer
+    /// blocked and has not failed in a while
+    fn is_stale(&self, attempts: &Attempts, now: u64) -> bool {
+        attempts.blocked_until <= now
+            && now.saturating_sub(attempts.last_failure) >= self.lockout_duration
+    }
+}
+
+/// Lock a mutex. The throttle data is always left in a valid state, so
+/// poisoning is ignored.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LockoutEvent, LoginThrottle};
+    use crate::env::AuthConfig;
+
+    const IP: &str = "10.1.1.1";
+
+    fn throttle() -> LoginThrottle {
+        LoginThrottle::new(&AuthConfig {
+            max_failed_attempts: 4,
+            login_backoff: 1,
+            lockout_duration: 100,
+            ..AuthConfig::default()
+        })
+    }
+
+    /// An IP with no failures is not blocked
+    #[test]
+    fn no_failures() {
+        assert_eq!(throttle().check_at(IP, 0), Ok(()));
+    }
+
+    /// Each failure doubles the time the IP is blocked
+    #[test]
+    fn exponential_backoff() {
+        let throttle = throttle();
+
+        throttle.record_failure_at(IP, 0);
+        assert_eq!(throttle.check_at(IP, 0), Err(1));
+        assert_eq!(throttle.check_at(IP, 1), Ok(()));
+
+        throttle.record_failure_at(IP, 1);
+        assert_eq!(throttle.check_at(IP, 1), Err(2));
+
+        throttle.record_failure_at(IP, 3);
+        assert_eq!(throttle.check_at(IP, 3), Err(4));
+        assert_eq!(throttle.check_at(IP, 7), Ok(()));
+    }
+
+    /// Too many failures locks the IP out and records an event
+    #[test]
+    fn lockout() {
+        let throttle = throttle();
+
+        for now in 0..4 {
+            throttle.record_failure_at(IP, now * 10);
+        }
+
+        assert_eq!(throttle.check_at(IP, 30), Err(100));
+        assert_eq!(throttle.check_at(IP, 130), Ok(()));
+        assert_eq!(
+            throttle.lockout_events(),
+            vec![LockoutEvent {
+                ip: IP.to_owned(),
+                failed_attempts: 4,
+                locked_at: 30,
+                locked_until: 130
+            }]
+        );
+    }
+
+    /// Failures are tracked separately for each IP
+    #[test]
+    fn per_ip() {
+        let throttle = throttle();
+
+        throttle.record_failure_at(IP, 0);
+
+        assert_eq!(throttle.check_at("10.1.1.2", 0), Ok(()));
+    }
+
+    /// A successful login forgets the failures
+    #[test]
+    fn success_resets() {
+        let throttle = throttle();
+
+        for now in 0..3 {
+            throttle.record_failure_at(IP, now * 10);
+        }
+        throttle.record_success(IP);
+        throttle.record_failure_at(IP, 30);
+
+        assert_eq!(throttle.check_at(IP, 30), Err(1));
+    }
+
+    /// Failures are forgotten after the lockout duration passes without
+    /// another failure
+    #[test]
+    fn failures_expire() {
+        let throttle = throttle();
+
+        for now in 0..3 {
+            throttle.record_failure_at(IP, now * 10);
+        }
+        throttle.record_failure_at(IP, 200);
+
+        assert_eq!(throttle.check_at(IP, 200), Err(1));
+    }
+}
@@ -0,0 +1,224 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// API Token Endpoints
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::Admin,
+    services::{
+        api_tokens::{ApiTokenService, TokenScope},
+        PiholeModule,
+    },
+    util::{reply_data, reply_success, Reply},
+};
+use rocket::serde::json::Json;
+use shaku_rocket::InjectProvided;
+
+/// Represents an API input to create a token
+#[derive(Deserialize)]
+pub struct TokenInput {
+    name: String,
+    scopes: Vec<TokenScope>,
+}
+
+/// Get the API tokens. The secrets are not included.
+#[get("/auth/tokens")]
+pub fn get_tokens(
+    _auth: Admin,
+    token_service: InjectProvided<PiholeModule, dyn ApiTokenService>,
+) -> Reply {
+    reply_data(token_service.list()?)
+}
+
+/// Create an API token. The secret is only shown in this response.
+#[post("/auth/tokens", data = "<token_input>")]
+pub fn create_token(
+    _auth: Admin,
+    token_service: InjectProvided<PiholeModule, dyn ApiTokenService>,
+    token_input: Json<TokenInput>,
+) -> Reply {
+    let (secret, token) = token_service.create(&token_input.name, &token_input.scopes)?;
+
+    reply_data(json!({
+        "name": token.name,
+        "scopes": token.scopes,
+        "created": token.created,
+        "token": secret
+    }))
+}
+
+/// Delete an API token, so that it can no longer be used
+#[delete("/auth/tokens/<name>")]
+pub fn delete_token(
+    _auth: Admin,
+    token_service: InjectProvided<PiholeModule, dyn ApiTokenService>,
+    name: String,
+) -> Reply {
+    token_service.delete(&name)?;
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        env::PiholeFile,
+        services::{
+            api_tokens::{ApiToken, ApiTokenService, MockApiTokenService, TokenScope},
+            lists::{List, ListService, MockListService},
+        },
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Header, Method, Status};
+    use serde_json::Value;
+
+    const TEST_TOKENS: &str = "[{\"name\":\"automation\",\"scopes\":[\"lists:write\"],\
+                               \"created\":1000,\"hash\":\"cc0af97287543b65da2c7e1476426021826cab166f1e063ed012b855ff819656\"}]";
+
+    /// Tokens are listed without their hashes
+    #[test]
+    fn list_tokens() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/tokens")
+            .file(PiholeFile::ApiTokens, TEST_TOKENS)
+            .expect_json(json!([{
+                "name": "automation",
+                "scopes": ["lists:write"],
+                "created": 1000
+            }]))
+            .test();
+    }
+
+    /// The secret of a new token is returned once
+    #[test]
+    fn create_token() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/tokens")
+            .method(Method::Post)
+            .mock_provider::<dyn ApiTokenService>(Box::new(|_| {
+                let mut service = MockApiTokenService::new();
+
+                service
+                    .expect_create()
+                    .with(
+                        eq("backup"),
+                        function(|scopes: &[TokenScope]| scopes == [TokenScope::StatsRead]),
+                    )
+                    .return_const(Ok((
+                        "secret".to_owned(),
+                        ApiToken {
+                            name: "backup".to_owned(),
+                            scopes: vec![TokenScope::StatsRead],
+                            created: 1000,
+                        },
+                    )));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "name": "backup", "scopes": ["stats:read"] }))
+            .expect_json(json!({
+                "name": "backup",
+                "scopes": ["stats:read"],
+                "created": 1000,
+                "token": "secret"
+            }))
+            .test();
+    }
+
+    /// Deleting a token removes it from the file
+    #[test]
+    fn delete_token() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/tokens/automation")
+            .method(Method::Delete)
+            .file_expect(PiholeFile::ApiTokens, TEST_TOKENS, "[]")
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Tokens can not be used to manage tokens
+    #[test]
+    fn token_can_not_list_tokens() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/tokens")
+            .should_auth(false)
+            .header(Header::new("X-Pi-hole-Token", "test_token"))
+            .file(PiholeFile::ApiTokens, TEST_TOKENS)
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// A token with the required scope is authorized
+    #[test]
+    fn token_with_scope() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/whitelist")
+            .method(Method::Post)
+            .should_auth(false)
+            .header(Header::new("X-Pi-hole-Token", "test_token"))
+            .file(PiholeFile::ApiTokens, TEST_TOKENS)
+            .mock_provider::<dyn ListService>(Box::new(|_| {
+                let mut service = MockListService::new();
+
+                service
+                    .expect_add()
+                    .with(eq(List::White), eq("example.com"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "domain": "example.com" }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// A token without the required scope is forbidden
+    #[test]
+    fn token_without_scope() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/top_clients")
+            .should_auth(false)
+            .header(Header::new("X-Pi-hole-Token", "test_token"))
+            .file(PiholeFile::ApiTokens, TEST_TOKENS)
+            .expect_status(Status::Forbidden)
+            .expect_json(json!({
+                "error": {
+                    "key": "forbidden",
+                    "message": "Forbidden",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// An unknown token is unauthorized
+    #[test]
+    fn unknown_token() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/top_clients")
+            .should_auth(false)
+            .header(Header::new("X-Pi-hole-Token", "not_a_token"))
+            .file(PiholeFile::ApiTokens, TEST_TOKENS)
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+}
@@ -0,0 +1,226 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Two-Factor Authentication Endpoints
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::User,
+    services::{totp::TotpService, PiholeModule},
+    util::{reply_data, reply_success, Reply},
+};
+use rocket::serde::json::Json;
+use shaku_rocket::InjectProvided;
+use std::ffi::OsString;
+
+/// Represents an API input containing a two-factor code or recovery code
+#[derive(Deserialize)]
+pub struct TotpCode {
+    code: String,
+}
+
+/// Check if the logged in account has enrolled in two-factor authentication
+#[get("/auth/totp")]
+pub fn get_totp(user: User, totp_service: InjectProvided<PiholeModule, dyn TotpService>) -> Reply {
+    reply_data(json!({
+        "enrolled": totp_service.is_enrolled(&user.name)?
+    }))
+}
+
+/// Enroll the logged in account in two-factor authentication. The secret,
+/// otpauth URI, and recovery codes are only shown in this response.
+#[post("/auth/totp")]
+pub fn enroll_totp(
+    user: User,
+    totp_service: InjectProvided<PiholeModule, dyn TotpService>,
+) -> Reply {
+    let hostname = hostname::get().unwrap_or_else(|_| OsString::from("pi.hole"));
+    let label = format!("{}@{}", user.name, hostname.to_string_lossy());
+
+    reply_data(totp_service.enroll(&user.name, &label)?)
+}
+
+/// Confirm the enrollment with a code from the authenticator app. The secret
+/// is not used to log in until it is confirmed.
+#[post("/auth/totp/confirm", data = "<data>")]
+pub fn confirm_totp(
+    user: User,
+    totp_service: InjectProvided<PiholeModule, dyn TotpService>,
+    data: Json<TotpCode>,
+) -> Reply {
+    totp_service.confirm(&user.name, &data.code)?;
+    reply_success()
+}
+
+/// Disable two-factor authentication for the logged in account. A code or
+/// recovery code is required.
+#[delete("/auth/totp", data = "<data>")]
+pub fn disable_totp(
+    user: User,
+    totp_service: InjectProvided<PiholeModule, dyn TotpService>,
+    data: Json<TotpCode>,
+) -> Reply {
+    totp_service.disable(&user.name, &data.code)?;
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::totp::{MockTotpService, TotpEnrollment, TotpService},
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Header, Method, Status};
+    use serde_json::Value;
+
+    /// Build a mock service where the admin account is enrolled and accepts a
+    /// single code
+    fn enrolled_service() -> MockTotpService {
+        let mut service = MockTotpService::new();
+
+        service
+            .expect_is_enrolled()
+            .with(eq("admin"))
+            .return_const(Ok(true));
+        service
+            .expect_verify()
+            .with(eq("admin"), eq("123456"))
+            .return_const(Ok(true));
+        service.expect_verify().return_const(Ok(false));
+
+        service
+    }
+
+    /// The enrollment status is returned
+    #[test]
+    fn get_status() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/totp")
+            .expect_json(json!({ "enrolled": false }))
+            .test();
+    }
+
+    /// Enrolling returns the secret, URI, and recovery codes
+    #[test]
+    fn enroll() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/totp")
+            .method(Method::Post)
+            .mock_provider::<dyn TotpService>(Box::new(|_| {
+                let mut service = MockTotpService::new();
+
+                service.expect_is_enrolled().return_const(Ok(false));
+                service
+                    .expect_enroll()
+                    .withf(|account, label| account == "admin" && label.starts_with("admin@"))
+                    .return_const(Ok(TotpEnrollment {
+                        secret: "SECRET".to_owned(),
+                        uri: "otpauth://totp/Pi-hole:admin%40pi.hole?secret=SECRET".to_owned(),
+                        recovery_codes: vec!["0123456789".to_owned()],
+                    }));
+
+                Ok(Box::new(service))
+            }))
+            .expect_json(json!({
+                "secret": "SECRET",
+                "uri": "otpauth://totp/Pi-hole:admin%40pi.hole?secret=SECRET",
+                "recovery_codes": ["0123456789"]
+            }))
+            .test();
+    }
+
+    /// Confirming passes the code to the service
+    #[test]
+    fn confirm() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/totp/confirm")
+            .method(Method::Post)
+            .mock_provider::<dyn TotpService>(Box::new(|_| {
+                let mut service = MockTotpService::new();
+
+                service.expect_is_enrolled().return_const(Ok(false));
+                service
+                    .expect_confirm()
+                    .with(eq("admin"), eq("123456"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "code": "123456" }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Disabling passes the code to the service
+    #[test]
+    fn disable() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth/totp")
+            .method(Method::Delete)
+            .header(Header::new("X-Pi-hole-TOTP", "123456"))
+            .mock_provider::<dyn TotpService>(Box::new(|_| {
+                let mut service = enrolled_service();
+
+                service
+                    .expect_disable()
+                    .with(eq("admin"), eq("654321"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "code": "654321" }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Once enrolled, the password alone is not enough to log in
+    #[test]
+    fn login_requires_code() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .mock_provider::<dyn TotpService>(Box::new(|_| Ok(Box::new(enrolled_service()))))
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "totp_required",
+                    "message": "A two-factor authentication code is required",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// A wrong code is rejected
+    #[test]
+    fn login_wrong_code() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .header(Header::new("X-Pi-hole-TOTP", "000000"))
+            .mock_provider::<dyn TotpService>(Box::new(|_| Ok(Box::new(enrolled_service()))))
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "totp_required",
+                    "message": "A two-factor authentication code is required",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// The password and a valid code authenticate the request
+    #[test]
+    fn login_with_code() {
+        TestBuilder::new()
+            .endpoint("/admin/api/auth")
+            .header(Header::new("X-Pi-hole-TOTP", "123456"))
+            .mock_provider::<dyn TotpService>(Box::new(|_| Ok(Box::new(enrolled_service()))))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+}
@@ -0,0 +1,410 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Authentication Guard
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::{AuthConfig, Env},
+    routes::auth::{
+        scope::provide_service,
+        session::{SessionInfo, SessionStore},
+        throttle::{LockoutEvent, LoginThrottle, RetryAfter},
+    },
+    services::{
+        accounts::{
+            hash_password, verify_password, Account, AccountService, Role, PRIMARY_ACCOUNT,
+        },
+        totp::TotpService,
+    },
+    settings::{ConfigEntry, SetupVarsEntry},
+    util::{Error, ErrorKind},
+};
+use rocket::{
+    http::{Cookie, CookieJar},
+    request::{self, FromRequest, Outcome, Request},
+};
+use std::sync::{PoisonError, RwLock};
+
+const SESSION_ATTR: &str = "session";
+const AUTH_HEADER: &str = "X-Pi-hole-Authenticate";
+const ACCOUNT_HEADER: &str = "X-Pi-hole-User";
+const TOTP_HEADER: &str = "X-Pi-hole-TOTP";
+
+/// Marks that a request failed authentication because a two-factor code was
+/// missing or invalid. This is stored in the request's local cache so the 401
+/// catcher can report it.
+pub struct TotpRequired(pub bool);
+
+/// When used as a request guard, requests must be authenticated
+pub struct User {
+    /// The public ID of the user's session
+    pub id: usize,
+    /// The name of the account the user logged in with
+    pub name: String,
+    /// The role of the account, which determines what the user can access
+    pub role: Role,
+    token: String,
+}
+
+/// When used as a request guard, requests must be authenticated with an
+/// account which has the admin role
+pub struct Admin(pub User);
+
+/// Stores the API key, active sessions, and failed login attempts in the
+/// server state
+pub struct AuthData {
+    /// The web password, either hashed or a legacy value
+    key: RwLock<Option<String>>,
+    sessions: SessionStore,
+    throttle: LoginThrottle,
+}
+
+impl User {
+    /// Try to get the user's session from cookies. The account's current role
+    /// is used, and sessions of deleted accounts are revoked.
+    fn get_from_cookie(request: &Request, auth_data: &AuthData) -> Result<Option<Self>, Error> {
+        let cookies = request.cookies();
+        let token = match cookies.get_private(SESSION_ATTR) {
+            Some(cookie) => cookie.value().to_owned(),
+            None => return Ok(None),
+        };
+
+        let account = match auth_data.sessions.touch(&token) {
+            Some(session) => {
+                let account_service: Box<dyn AccountService> = provide_service(request)?;
+                let account = account_service.get(&session.account)?;
+
+                if account.is_none() {
+                    auth_data.sessions.revoke_account(&session.account);
+                }
+
+                account.map(|account| (session.id, account))
+            }
+            None => None,
+        };
+
+        match account {
+            Some((id, account)) => Ok(Some(User {
+                id,
+                name: account.name,
+                role: account.role,
+                token,
+            })),
+            None => {
+                // The session expired or was revoked, so remove the cookie
+                cookies.remove_private(Cookie::named(SESSION_ATTR));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Create a new session for the account and store the token in a cookie
+    fn create_and_store_user(request: &Request, auth_data: &AuthData, account: Account) -> User {
+        let ip = request.client_ip().map(|ip| ip.to_string());
+        let user = auth_data.create_user(account, ip);
+
+        // Set a new encrypted cookie with the session token
+        request.cookies().add_private(
+            Cookie::build(SESSION_ATTR, user.token.clone())
+                // Allow the web interface to read the cookie
+                .http_only(false)
+                .finish(),
+        );
+
+        user
+    }
+
+    /// Find the account which the key belongs to. If no account name is given,
+    /// the key is checked against the primary account's password.
+    fn check_credentials(
+        request: &Request,
+        auth_data: &AuthData,
+        key: &str,
+    ) -> Result<Option<Account>, Error> {
+        match request.headers().get_one(ACCOUNT_HEADER) {
+            None | Some(PRIMARY_ACCOUNT) => Ok(if auth_data.key_matches(key) {
+                Some(Account::primary())
+            } else {
+                None
+            }),
+            Some(name) => {
+                let account_service: Box<dyn AccountService> = provide_service(request)?;
+                account_service.verify(name, key)
+            }
+        }
+    }
+
+    /// Check the two-factor code, if the account has enrolled in two-factor
+    /// authentication. Returns false if the code is missing or invalid.
+    fn check_second_factor(
+        request: &Request,
+        account: &Account,
+        code: Option<&str>,
+    ) -> Result<bool, Error> {
+        let totp_service: Box<dyn TotpService> = provide_service(request)?;
+
+        if !totp_service.is_enrolled(&account.name)? {
+            return Ok(true);
+        }
+
+        match code {
+            Some(code) => totp_service.verify(&account.name, code),
+            None => Ok(false),
+        }
+    }
+
+    /// Log the user out by ending the session and removing the cookie
+    pub fn logout(&self, cookies: &CookieJar, auth_data: &AuthData) {
+        auth_data.sessions.remove_token(&self.token);
+        cookies.remove_private(Cookie::named(SESSION_ATTR));
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = Error;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        // Load the auth data
+        let auth_data: &AuthData = match request.rocket().state() {
+            Some(auth_data) => auth_data,
+            None => return Error::from(ErrorKind::Unknown).into_outcome(),
+        };
+
+        // Check if the user has already authenticated and has a valid session
+        match User::get_from_cookie(request, auth_data) {
+            Ok(Some(user)) => return Outcome::Success(user),
+            Ok(None) => (),
+            Err(e) => return e.into_outcome(),
+        }
+
+        // Check if a key is required for authentication
+        if !auth_data.key_required() {
+            return Outcome::Success(User::create_and_store_user(
+                request,
+                auth_data,
+                Account::primary(),
+            ));
+        }
+
+        // Check the user's key, if provided
+        if let Some(key) = request.headers().get_one(AUTH_HEADER) {
+            let ip = request
+                .client_ip()
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "unknown".to_owned());
+
+            // Reject the attempt if the IP has failed to log in too often
+            if let Err(retry_after) = auth_data.throttle.check(&ip) {
+                request.local_cache(|| RetryAfter(retry_after));
+                return Error::from(ErrorKind::TooManyRequests(retry_after)).into_outcome();
+            }
+
+            let account = match User::check_credentials(request, auth_data, key) {
+                Ok(account) => account,
+                Err(e) => return e.into_outcome(),
+            };
+
+            if let Some(account) = account {
+                let code = request.headers().get_one(TOTP_HEADER);
+
+                match User::check_second_factor(request, &account, code) {
+                    Ok(true) => {
+                        // The key and code match, so create and store a new
+                        // session and cookie
+                        auth_data.throttle.record_success(&ip);
+                        Outcome::Success(Self::create_and_store_user(request, auth_data, account))
+                    }
+                    Ok(false) => {
+                        // A missing code is part of the normal login flow,
+                        // but a wrong code is a failed attempt
+                        if code.is_some() {
+                            warn_!("Failed two-factor attempt from {}", ip);
+                            auth_data.throttle.record_failure(&ip);
+                        }
+
+                        request.local_cache(|| TotpRequired(true));
+                        Error::from(ErrorKind::TotpRequired).into_outcome()
+                    }
+                    Err(e) => e.into_outcome(),
+                }
+            } else {
+                // The key does not match
+                warn_!("Failed login attempt from {}", ip);
+                auth_data.throttle.record_failure(&ip);
+                Error::from(ErrorKind::Unauthorized).into_outcome()
+            }
+        } else {
+            // A key is required but not provided
+            Error::from(ErrorKind::Unauthorized).into_outcome()
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = Error;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match User::from_request(request).await {
+            Outcome::Success(user) if user.role == Role::Admin => Outcome::Success(Admin(user)),
+            Outcome::Success(_) => Error::from(ErrorKind::Forbidden).into_outcome(),
+            Outcome::Failure(failure) => Outcome::Failure(failure),
+            Outcome::Forward(forward) => Outcome::Forward(forward),
+        }
+    }
+}
+
+impl AuthData {
+    /// Create a new API key, session store, and login throttle
+    pub fn new(key: Option<String>, config: &AuthConfig) -> AuthData {
+        AuthData {
+            key: RwLock::new(key),
+            sessions: SessionStore::new(config),
+            throttle: LoginThrottle::new(config),
+        }
+    }
+
+    /// Check if the key matches the server's key
+    fn key_matches(&self, key: &str) -> bool {
+        self.key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            // If a password is required, check that the given one matches
+            .map(|api_key| verify_password(key, api_key))
+            // If no password is required, authenticate the user
+            .unwrap_or(true)
+    }
+
+    /// Check if a key is required to authenticate
+    fn key_required(&self) -> bool {
+        self.key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+    }
+
+    /// Change the primary account's password after checking the old one. The
+    /// new password is hashed and saved to setupVars, and all sessions are
+    /// revoked so that everyone has to log in again.
+    pub fn change_password(
+        &self,
+        env: &Env,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<(), Error> {
+        if new_password.is_empty() {
+            return Err(Error::from(ErrorKind::BadRequest));
+        }
+
+        if !self.key_matches(old_password) {
+            return Err(Error::from(ErrorKind::Unauthorized));
+        }
+
+        let hash = hash_password(new_password)?;
+        SetupVarsEntry::WebPassword.write(&hash, env)?;
+
+        *self.key.write().unwrap_or_else(PoisonError::into_inner) = Some(hash);
+        self.sessions.revoke_all();
+
+        Ok(())
+    }
+
+    /// Create a new user with a fresh session
+    fn create_user(&self, account: Account, ip: Option<String>) -> User {
+        let (token, session) = self.sessions.create(account, ip);
+
+        User {
+            id: session.id,
+            name: session.account,
+            role: session.role,
+            token,
+        }
+    }
+
+    /// List the active sessions
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.sessions.list()
+    }
+
+    /// Revoke a session by its ID. Returns false if the session does not
+    /// exist.
+    pub fn revoke_session(&self, id: usize) -> bool {
+        self.sessions.revoke(id)
+    }
+
+    /// Revoke every session of the account
+    pub fn revoke_account_sessions(&self, account: &str) {
+        self.sessions.revoke_account(account)
+    }
+
+    /// Check if the IP is allowed to attempt a login. If not, the number of
+    /// seconds until it can try again is returned.
+    pub fn check_throttle(&self, ip: &str) -> Result<(), u64> {
+        self.throttle.check(ip)
+    }
+
+    /// Count a failed attempt to authenticate from the IP
+    pub fn record_failure(&self, ip: &str) {
+        self.throttle.record_failure(ip)
+    }
+
+    /// List the recent lockouts caused by failed login attempts
+    pub fn lockout_events(&self) -> Vec<LockoutEvent> {
+        self.throttle.lockout_events()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AuthData;
+    use crate::{
+        env::{AuthConfig, PiholeFile},
+        services::accounts::{verify_password, Account},
+        settings::{ConfigEntry, SetupVarsEntry},
+        testing::TestEnvBuilder,
+        util::ErrorKind,
+    };
+
+    /// Changing the password saves the new hash and revokes all sessions
+    #[test]
+    fn change_password() {
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "WEBPASSWORD=test_key\n")
+            .build();
+        let auth_data = AuthData::new(Some("test_key".to_owned()), &AuthConfig::default());
+        auth_data.create_user(Account::primary(), None);
+
+        auth_data
+            .change_password(&env, "test_key", "hunter2")
+            .unwrap();
+
+        let stored = SetupVarsEntry::WebPassword.read(&env).unwrap();
+        assert!(verify_password("hunter2", &stored));
+        assert!(auth_data.key_matches("hunter2"));
+        assert!(!auth_data.key_matches("test_key"));
+        assert!(auth_data.sessions().is_empty());
+    }
+
+    /// The old password must be correct
+    #[test]
+    fn change_password_wrong_old_password() {
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "WEBPASSWORD=test_key\n")
+            .build();
+        let auth_data = AuthData::new(Some("test_key".to_owned()), &AuthConfig::default());
+
+        let error = auth_data
+            .change_password(&env, "wrong", "hunter2")
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::Unauthorized);
+        assert_eq!(SetupVarsEntry::WebPassword.read(&env).unwrap(), "test_key");
+    }
+}
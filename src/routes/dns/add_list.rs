@@ -9,7 +9,7 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    routes::auth::User,
+    routes::auth::CanWriteLists,
     services::{
         lists::{List, ListService},
         PiholeModule,
@@ -28,7 +28,7 @@ pub struct DomainInput {
 /// Add a domain to the whitelist
 #[post("/dns/whitelist", data = "<domain_input>")]
 pub fn add_whitelist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain_input: Json<DomainInput>,
 ) -> Reply {
@@ -39,7 +39,7 @@ pub fn add_whitelist(
 /// Add a domain to the blacklist
 #[post("/dns/blacklist", data = "<domain_input>")]
 pub fn add_blacklist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain_input: Json<DomainInput>,
 ) -> Reply {
@@ -50,7 +50,7 @@ pub fn add_blacklist(
 /// Add a domain to the regex list
 #[post("/dns/regexlist", data = "<domain_input>")]
 pub fn add_regexlist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain_input: Json<DomainInput>,
 ) -> Reply {
@@ -58,6 +58,17 @@ pub fn add_regexlist(
     reply_success()
 }
 
+/// Add a wildcard, such as `*.example.com`, to the regex list
+#[post("/dns/wildcardlist", data = "<domain_input>")]
+pub fn add_wildcardlist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain_input: Json<DomainInput>,
+) -> Reply {
+    list_service.add(List::Wildcard, &domain_input.0.domain)?;
+    reply_success()
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
@@ -103,4 +114,13 @@ mod test {
     fn test_add_regexlist() {
         add_test(List::Regex, "/admin/api/dns/regexlist", "^.*example.com$");
     }
+
+    #[test]
+    fn test_add_wildcardlist() {
+        add_test(
+            List::Wildcard,
+            "/admin/api/dns/wildcardlist",
+            "*.example.com",
+        );
+    }
 }
@@ -0,0 +1,298 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Managing Adlists
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::CanWriteLists,
+    services::{
+        adlists::{
+            AdlistDetails, AdlistInput, AdlistPatch, AdlistRepository, AdlistStatusRepository,
+        },
+        PiholeModule,
+    },
+    settings::ValueType,
+    util::{reply_data, reply_result, reply_success, Error, ErrorKind, Reply},
+};
+use rocket::serde::json::Json;
+use shaku_rocket::InjectProvided;
+
+/// Get all of the adlists, along with the status of their last update
+#[get("/dns/adlists")]
+pub fn get_adlists(
+    repo: InjectProvided<PiholeModule, dyn AdlistRepository>,
+    status_repo: InjectProvided<PiholeModule, dyn AdlistStatusRepository>,
+) -> Reply {
+    let mut statuses = status_repo.get_all()?;
+    let adlists: Vec<AdlistDetails> = repo
+        .get_all()?
+        .into_iter()
+        .map(|adlist| AdlistDetails {
+            status: statuses.remove(&adlist.id),
+            adlist,
+        })
+        .collect();
+
+    reply_data(adlists)
+}
+
+/// Add an adlist. The address must be an HTTP(S) or FTP URL.
+#[post("/dns/adlists", data = "<adlist_input>")]
+pub fn add_adlist(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn AdlistRepository>,
+    adlist_input: Json<AdlistInput>,
+) -> Reply {
+    if !ValueType::Url.is_valid(&adlist_input.address) {
+        return Err(Error::from(ErrorKind::InvalidUrl));
+    }
+
+    // A new adlist has not been fetched yet
+    reply_data(AdlistDetails {
+        adlist: repo.add(&adlist_input)?,
+        status: None,
+    })
+}
+
+/// Enable/disable an adlist or change its comment
+#[patch("/dns/adlists/<id>", data = "<adlist_patch>")]
+pub fn update_adlist(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn AdlistRepository>,
+    status_repo: InjectProvided<PiholeModule, dyn AdlistStatusRepository>,
+    id: i32,
+    adlist_patch: Json<AdlistPatch>,
+) -> Reply {
+    repo.update(id, &adlist_patch)?;
+
+    reply_data(AdlistDetails {
+        adlist: repo.get(id)?,
+        status: status_repo.get_all()?.remove(&id),
+    })
+}
+
+/// Delete an adlist
+#[delete("/dns/adlists/<id>")]
+pub fn delete_adlist(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn AdlistRepository>,
+    status_repo: InjectProvided<PiholeModule, dyn AdlistStatusRepository>,
+    id: i32,
+) -> Reply {
+    repo.remove(id)?;
+    status_repo.remove(id)?;
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::adlists::{
+            Adlist, AdlistHealth, AdlistInput, AdlistPatch, AdlistRepository, AdlistStatus,
+            AdlistStatusRepository, MockAdlistRepository, MockAdlistStatusRepository,
+        },
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Method, Status};
+    use serde_json::Value;
+
+    fn test_adlist() -> Adlist {
+        Adlist {
+            id: 1,
+            address: "https://example.com/hosts".to_owned(),
+            enabled: true,
+            comment: None,
+            date_added: 1557712118,
+            date_modified: 1557712118,
+            groups: vec![1],
+        }
+    }
+
+    fn test_adlist_json() -> Value {
+        json!({
+            "id": 1,
+            "address": "https://example.com/hosts",
+            "enabled": true,
+            "comment": null,
+            "date_added": 1557712118,
+            "date_modified": 1557712118,
+            "groups": [1],
+            "status": null
+        })
+    }
+
+    /// All adlists are returned with their dates and groups
+    #[test]
+    fn get_adlists() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists")
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                let mut repo = MockAdlistRepository::new();
+
+                repo.expect_get_all().return_const(Ok(vec![test_adlist()]));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!([test_adlist_json()]))
+            .test();
+    }
+
+    /// The status of the last update is included when the adlist has been
+    /// fetched
+    #[test]
+    fn get_adlists_with_status() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists")
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                let mut repo = MockAdlistRepository::new();
+
+                repo.expect_get_all().return_const(Ok(vec![test_adlist()]));
+
+                Ok(Box::new(repo))
+            }))
+            .mock_provider::<dyn AdlistStatusRepository>(Box::new(|_| {
+                let mut repo = MockAdlistStatusRepository::new();
+
+                repo.expect_get_all().return_const(Ok(vec![(
+                    1,
+                    AdlistStatus {
+                        health: AdlistHealth::Degraded,
+                        last_fetched: 1557712200,
+                        http_status: Some(200),
+                        etag: Some("\"abc\"".to_owned()),
+                        last_modified: None,
+                        checksum: Some("d41d8cd9".to_owned()),
+                        unchanged: true,
+                        domains: 0,
+                        invalid_lines: 1,
+                        invalid_samples: vec!["<html>".to_owned()],
+                        error: None,
+                    },
+                )]
+                .into_iter()
+                .collect()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!([{
+                "id": 1,
+                "address": "https://example.com/hosts",
+                "enabled": true,
+                "comment": null,
+                "date_added": 1557712118,
+                "date_modified": 1557712118,
+                "groups": [1],
+                "status": {
+                    "health": "degraded",
+                    "last_fetched": 1557712200,
+                    "http_status": 200,
+                    "etag": "\"abc\"",
+                    "last_modified": null,
+                    "checksum": "d41d8cd9",
+                    "unchanged": true,
+                    "domains": 0,
+                    "invalid_lines": 1,
+                    "invalid_samples": ["<html>"],
+                    "error": null
+                }
+            }]))
+            .test();
+    }
+
+    /// A valid URL is added and the new adlist is returned
+    #[test]
+    fn add_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists")
+            .method(Method::Post)
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                let mut repo = MockAdlistRepository::new();
+
+                repo.expect_add()
+                    .with(eq(AdlistInput {
+                        address: "https://example.com/hosts".to_owned(),
+                        comment: None,
+                        enabled: true,
+                    }))
+                    .return_const(Ok(test_adlist()));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!({ "address": "https://example.com/hosts" }))
+            .expect_json(test_adlist_json())
+            .test();
+    }
+
+    /// An invalid URL is rejected
+    #[test]
+    fn add_invalid_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists")
+            .method(Method::Post)
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                Ok(Box::new(MockAdlistRepository::new()))
+            }))
+            .body(json!({ "address": "not a url" }))
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "invalid_url",
+                    "message": "Invalid URL",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// Updating an adlist returns the updated adlist
+    #[test]
+    fn update_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists/1")
+            .method(Method::Patch)
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                let mut repo = MockAdlistRepository::new();
+
+                repo.expect_update()
+                    .with(
+                        eq(1),
+                        eq(AdlistPatch {
+                            enabled: Some(true),
+                            comment: None,
+                        }),
+                    )
+                    .return_const(Ok(()));
+                repo.expect_get()
+                    .with(eq(1))
+                    .return_const(Ok(test_adlist()));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!({ "enabled": true }))
+            .expect_json(test_adlist_json())
+            .test();
+    }
+
+    /// Deleting an adlist returns success
+    #[test]
+    fn delete_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/adlists/1")
+            .method(Method::Delete)
+            .mock_provider::<dyn AdlistRepository>(Box::new(|_| {
+                let mut repo = MockAdlistRepository::new();
+
+                repo.expect_remove().with(eq(1)).return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+}
@@ -0,0 +1,167 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Managing Audited Domains
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::{
+        auth::{CanReadStats, CanWriteLists},
+        dns::DomainList,
+    },
+    services::{domain_audit::DomainAuditRepository, lists::import_domains, PiholeModule},
+    settings::ValueType,
+    util::{reply_data, reply_result, reply_success, Reply},
+};
+use shaku_rocket::InjectProvided;
+
+/// Get all audited domains with when they were audited
+#[get("/dns/audit")]
+pub fn get_audit(
+    _auth: CanReadStats,
+    repo: InjectProvided<PiholeModule, dyn DomainAuditRepository>,
+) -> Reply {
+    reply_result(repo.get_entries())
+}
+
+/// Audit many domains at once, given as a JSON array or as plain text with
+/// one domain per line. The result of each domain is returned in the order
+/// they were given.
+#[post("/dns/audit", data = "<domains>")]
+pub fn add_audit(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn DomainAuditRepository>,
+    domains: DomainList,
+) -> Reply {
+    let domains: Vec<String> = domains
+        .0
+        .iter()
+        .map(|domain| domain.to_lowercase())
+        .collect();
+
+    reply_data(import_domains(
+        &domains,
+        |domain| ValueType::Hostname.is_valid(domain),
+        |valid_domains| repo.add_all(valid_domains),
+    )?)
+}
+
+/// Un-audit a domain
+#[delete("/dns/audit/<domain>")]
+pub fn delete_audit(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn DomainAuditRepository>,
+    domain: String,
+) -> Reply {
+    repo.remove(&domain.to_lowercase())?;
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::domain_audit::{AuditEntry, DomainAuditRepository, MockDomainAuditRepository},
+        testing::TestBuilder,
+        util::{Error, ErrorKind},
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Method, Status};
+
+    /// Audited domains are returned with their dates
+    #[test]
+    fn get_audit() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/audit")
+            .mock_provider::<dyn DomainAuditRepository>(Box::new(|_| {
+                let mut repo = MockDomainAuditRepository::new();
+
+                repo.expect_get_entries().return_const(Ok(vec![AuditEntry {
+                    id: 1,
+                    domain: "audited.domain".to_owned(),
+                    date_added: 1557712118,
+                }]));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!([{
+                "id": 1,
+                "domain": "audited.domain",
+                "date_added": 1557712118
+            }]))
+            .test();
+    }
+
+    /// Valid domains are audited, and the result of each domain is returned
+    #[test]
+    fn add_audit() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/audit")
+            .method(Method::Post)
+            .mock_provider::<dyn DomainAuditRepository>(Box::new(|_| {
+                let mut repo = MockDomainAuditRepository::new();
+
+                repo.expect_add_all()
+                    .withf(|domains| {
+                        domains == ["example.com".to_owned(), "audited.domain".to_owned()]
+                    })
+                    .return_const(Ok(vec![true, false]));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!(["Example.com", "not a domain", "audited.domain"]))
+            .expect_json(json!([
+                { "domain": "example.com", "status": "added" },
+                { "domain": "not a domain", "status": "invalid" },
+                { "domain": "audited.domain", "status": "duplicate" }
+            ]))
+            .test();
+    }
+
+    /// Un-auditing a domain returns success
+    #[test]
+    fn delete_audit() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/audit/audited.domain")
+            .method(Method::Delete)
+            .mock_provider::<dyn DomainAuditRepository>(Box::new(|_| {
+                let mut repo = MockDomainAuditRepository::new();
+
+                repo.expect_remove()
+                    .with(eq("audited.domain"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Un-auditing a domain which is not audited is not found
+    #[test]
+    fn delete_audit_not_found() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/audit/example.com")
+            .method(Method::Delete)
+            .mock_provider::<dyn DomainAuditRepository>(Box::new(|_| {
+                let mut repo = MockDomainAuditRepository::new();
+
+                repo.expect_remove()
+                    .returning(|_| Err(Error::from(ErrorKind::NotFound)));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+}
@@ -0,0 +1,236 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Importing And Exporting Domain Lists
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::CanWriteLists,
+    services::{
+        lists::{List, ListService},
+        PiholeModule,
+    },
+    util::{reply_result, Error, ErrorKind, Reply},
+};
+use rocket::{
+    data::{self, Data, FromData, ToByteUnit},
+    http::{ContentType, Status},
+    Request,
+};
+use shaku_rocket::InjectProvided;
+
+/// A list of domains, sent either as a JSON array or as plain text with one
+/// domain per line. In plain text, empty lines and lines starting with `#` are
+/// ignored.
+pub struct DomainList(pub Vec<String>);
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for DomainList {
+    type Error = Error;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let limit = request
+            .limits()
+            .get("json")
+            .unwrap_or_else(|| 1.mebibytes());
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            _ => {
+                return data::Outcome::Failure((
+                    Status::BadRequest,
+                    Error::from(ErrorKind::BadRequest),
+                ))
+            }
+        };
+
+        if request.content_type() == Some(&ContentType::JSON) {
+            match serde_json::from_str(&body) {
+                Ok(domains) => data::Outcome::Success(DomainList(domains)),
+                Err(_) => {
+                    data::Outcome::Failure((Status::BadRequest, Error::from(ErrorKind::BadRequest)))
+                }
+            }
+        } else {
+            data::Outcome::Success(DomainList(
+                body.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_owned)
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// The formats a list can be exported in
+#[derive(FromFormField, Copy, Clone)]
+pub enum ExportFormat {
+    Json,
+    Text,
+}
+
+/// Import many domains into the whitelist, blacklist, or regex list at once.
+/// The result of each domain is returned in the order they were given.
+#[post("/dns/<list>/import", data = "<domains>")]
+pub fn import_list(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    list: List,
+    domains: DomainList,
+) -> Reply {
+    reply_result(list_service.import(list, &domains.0))
+}
+
+/// Export the domains of the whitelist, blacklist, or regex list as a JSON
+/// array (the default) or as plain text with one domain per line
+#[get("/dns/<list>/export?<format>", rank = 2)]
+pub fn export_list(
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    list: List,
+    format: Option<ExportFormat>,
+) -> Result<(ContentType, String), Error> {
+    let domains: Vec<String> = list_service
+        .get(list)?
+        .into_iter()
+        .map(|entry| entry.domain)
+        .collect();
+
+    Ok(match format.unwrap_or(ExportFormat::Json) {
+        ExportFormat::Json => (ContentType::JSON, serde_json::to_string(&domains).unwrap()),
+        ExportFormat::Text => (ContentType::Plain, domains.join("\n")),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::lists::{
+            ImportResult, ImportStatus, List, ListEntry, ListService, MockListService,
+        },
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{ContentType, Method};
+
+    fn test_entry(domain: &str) -> ListEntry {
+        ListEntry {
+            id: 1,
+            domain: domain.to_owned(),
+            enabled: true,
+            date_added: 1557712118,
+            date_modified: 1557712118,
+            comment: None,
+        }
+    }
+
+    /// Build a mock service which expects the given domains to be imported
+    fn import_service(list: List, domains: Vec<String>) -> MockListService {
+        let mut service = MockListService::new();
+
+        service
+            .expect_import()
+            .withf(move |input_list, input_domains| {
+                *input_list == list && input_domains == domains.as_slice()
+            })
+            .return_const(Ok(vec![
+                ImportResult {
+                    domain: "example.com".to_owned(),
+                    status: ImportStatus::Added,
+                },
+                ImportResult {
+                    domain: "in valid".to_owned(),
+                    status: ImportStatus::Invalid,
+                },
+            ]));
+
+        service
+    }
+
+    /// A JSON array of domains is imported and the results are returned
+    #[test]
+    fn import_json() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/whitelist/import")
+            .method(Method::Post)
+            .mock_provider::<dyn ListService>(Box::new(|_| {
+                Ok(Box::new(import_service(
+                    List::White,
+                    vec!["example.com".to_owned(), "in valid".to_owned()],
+                )))
+            }))
+            .body(json!(["example.com", "in valid"]))
+            .expect_json(json!([
+                { "domain": "example.com", "status": "added" },
+                { "domain": "in valid", "status": "invalid" }
+            ]))
+            .test();
+    }
+
+    /// Plain text is imported line by line, skipping blank lines and comments
+    #[test]
+    fn import_text() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/blacklist/import")
+            .method(Method::Post)
+            .mock_provider::<dyn ListService>(Box::new(|_| {
+                Ok(Box::new(import_service(
+                    List::Black,
+                    vec!["example.com".to_owned(), "in valid".to_owned()],
+                )))
+            }))
+            .raw_body(
+                ContentType::Plain,
+                "# Blocked domains\nexample.com\n\n  in valid  \n",
+            )
+            .expect_json(json!([
+                { "domain": "example.com", "status": "added" },
+                { "domain": "in valid", "status": "invalid" }
+            ]))
+            .test();
+    }
+
+    /// Lists are exported as a JSON array by default
+    #[test]
+    fn export_json() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/regexlist/export")
+            .mock_provider::<dyn ListService>(Box::new(|_| {
+                let mut service = MockListService::new();
+
+                service
+                    .expect_get()
+                    .with(eq(List::Regex))
+                    .return_const(Ok(vec![test_entry("^.*example.com$")]));
+
+                Ok(Box::new(service))
+            }))
+            .expect_json(json!(["^.*example.com$"]))
+            .test();
+    }
+
+    /// Lists can be exported as plain text with one domain per line
+    #[test]
+    fn export_text() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/whitelist/export?format=text")
+            .mock_provider::<dyn ListService>(Box::new(|_| {
+                let mut service = MockListService::new();
+
+                service
+                    .expect_get()
+                    .with(eq(List::White))
+                    .return_const(Ok(vec![
+                        test_entry("example.com"),
+                        test_entry("example.net"),
+                    ]));
+
+                Ok(Box::new(service))
+            }))
+            .expect_raw("example.com\nexample.net")
+            .test();
+    }
+}
@@ -9,7 +9,7 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    routes::auth::User,
+    routes::auth::CanWriteLists,
     services::{
         lists::{List, ListService},
         PiholeModule,
@@ -21,7 +21,7 @@ use shaku_rocket::InjectProvided;
 /// Delete a domain from the whitelist
 #[delete("/dns/whitelist/<domain>")]
 pub fn delete_whitelist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain: String,
 ) -> Reply {
@@ -32,7 +32,7 @@ pub fn delete_whitelist(
 /// Delete a domain from the blacklist
 #[delete("/dns/blacklist/<domain>")]
 pub fn delete_blacklist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain: String,
 ) -> Reply {
@@ -43,7 +43,7 @@ pub fn delete_blacklist(
 /// Delete a domain from the regex list
 #[delete("/dns/regexlist/<domain>")]
 pub fn delete_regexlist(
-    _auth: User,
+    _auth: CanWriteLists,
     list_service: InjectProvided<PiholeModule, dyn ListService>,
     domain: String,
 ) -> Reply {
@@ -51,6 +51,17 @@ pub fn delete_regexlist(
     reply_success()
 }
 
+/// Delete a wildcard from the regex list
+#[delete("/dns/wildcardlist/<domain>")]
+pub fn delete_wildcardlist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain: String,
+) -> Reply {
+    list_service.remove(List::Wildcard, &domain)?;
+    reply_success()
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
@@ -105,4 +116,13 @@ mod test {
             "^.*example.com$",
         );
     }
+
+    #[test]
+    fn test_delete_wildcardlist() {
+        delete_test(
+            List::Wildcard,
+            "/admin/api/dns/wildcardlist/%2A.example.com",
+            "*.example.com",
+        );
+    }
 }
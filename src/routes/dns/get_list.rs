@@ -17,44 +17,77 @@ use crate::{
 };
 use shaku_rocket::InjectProvided;
 
-/// Get the Whitelist domains
+/// Get the Whitelist entries
 #[get("/dns/whitelist")]
 pub fn get_whitelist(service: InjectProvided<PiholeModule, dyn ListService>) -> Reply {
     reply_result(service.get(List::White))
 }
 
-/// Get the Blacklist domains
+/// Get the Blacklist entries
 #[get("/dns/blacklist")]
 pub fn get_blacklist(service: InjectProvided<PiholeModule, dyn ListService>) -> Reply {
     reply_result(service.get(List::Black))
 }
 
-/// Get the Regex list domains
+/// Get the Regex list entries
 #[get("/dns/regexlist")]
 pub fn get_regexlist(service: InjectProvided<PiholeModule, dyn ListService>) -> Reply {
     reply_result(service.get(List::Regex))
 }
 
+/// Get the wildcards, such as `*.example.com`
+#[get("/dns/wildcardlist")]
+pub fn get_wildcardlist(service: InjectProvided<PiholeModule, dyn ListService>) -> Reply {
+    reply_result(service.get(List::Wildcard))
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        services::lists::{List, ListService, MockListService},
+        services::lists::{List, ListEntry, ListService, MockListService},
         testing::TestBuilder,
     };
     use mockall::predicate::*;
 
-    /// Test that the domains are returned correctly
+    /// Test that the entries are returned correctly, with their metadata
     fn get_test(list: List, endpoint: &str, domains: Vec<String>) {
+        let entries: Vec<ListEntry> = domains
+            .iter()
+            .enumerate()
+            .map(|(i, domain)| ListEntry {
+                id: i as i32 + 1,
+                domain: domain.clone(),
+                enabled: i == 0,
+                date_added: 1557712172,
+                date_modified: 1557723911,
+                comment: Some("Added for testing".to_owned()),
+            })
+            .collect();
+        let expected: Vec<_> = domains
+            .iter()
+            .enumerate()
+            .map(|(i, domain)| {
+                json!({
+                    "id": i + 1,
+                    "domain": domain,
+                    "enabled": i == 0,
+                    "date_added": 1557712172,
+                    "date_modified": 1557723911,
+                    "comment": "Added for testing"
+                })
+            })
+            .collect();
+
         TestBuilder::new()
             .endpoint(endpoint)
-            .expect_json(json!(domains))
+            .expect_json(json!(expected))
             .mock_provider::<dyn ListService>(Box::new(move |_| {
                 let mut service = MockListService::new();
 
                 service
                     .expect_get()
                     .with(eq(list))
-                    .return_const(Ok(domains.clone()));
+                    .return_const(Ok(entries.clone()));
 
                 Ok(Box::new(service))
             }))
@@ -87,4 +120,13 @@ mod test {
             vec!["^.*example.com$".to_owned(), "example.net".to_owned()],
         );
     }
+
+    #[test]
+    fn test_get_wildcardlist() {
+        get_test(
+            List::Wildcard,
+            "/admin/api/dns/wildcardlist",
+            vec!["*.example.com".to_owned(), "*.example.net".to_owned()],
+        );
+    }
 }
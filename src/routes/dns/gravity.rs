@@ -0,0 +1,226 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Updating Gravity
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::Env,
+    routes::{
+        auth::{CanReadStats, CanWriteLists},
+        dns::common::reload_dns,
+    },
+    services::{
+        gravity::{GravityJobs, GravityService},
+        PiholeModule,
+    },
+    util::{reply_data, Error, ErrorKind, Reply},
+};
+use rocket::{
+    response::stream::{Event, EventStream},
+    tokio::time::sleep,
+    State,
+};
+use shaku::HasProvider;
+use shaku_rocket::Inject;
+use std::time::Duration;
+use task_scheduler::Scheduler;
+
+/// How often the event stream checks for new job events
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Start downloading the enabled adlists and rebuilding gravity in the
+/// background. FTL is reloaded when the job finishes. If an update is already
+/// running, its job ID is returned instead of starting another one.
+#[post("/dns/gravity/update")]
+pub fn update_gravity(
+    _auth: CanWriteLists,
+    env: Inject<PiholeModule, Env>,
+    module: &State<Box<PiholeModule>>,
+    scheduler: &State<Scheduler>,
+    jobs: &State<GravityJobs>,
+) -> Reply {
+    // The service is moved to the job, so it can't be borrowed from the
+    // request like other injected services
+    let module: &PiholeModule = module;
+    let service: Box<dyn GravityService> = module
+        .provide()
+        .map_err(|_| Error::from(ErrorKind::GravityDatabase))?;
+
+    let (id, started) = jobs.start();
+
+    if started {
+        let jobs = jobs.inner().clone();
+
+        // Don't schedule the job when testing. The Clone implementation for
+        // Env::Test is not available, and we don't want to be scheduling work
+        // which runs after the tests.
+        if env.is_test() {
+            run_update(service.as_ref(), &jobs, id, &env);
+        } else {
+            let env_copy = env.clone();
+
+            scheduler.after_duration(Duration::from_secs(0), move || {
+                run_update(service.as_ref(), &jobs, id, &env_copy)
+            });
+        }
+    }
+
+    reply_data(json!({ "id": id }))
+}
+
+/// Get the progress and result of a gravity update job
+#[get("/dns/gravity/jobs/<id>")]
+pub fn get_gravity_job(_auth: CanReadStats, jobs: &State<GravityJobs>, id: u64) -> Reply {
+    match jobs.get(id) {
+        Some(job) => reply_data(job),
+        None => Err(Error::from(ErrorKind::NotFound)),
+    }
+}
+
+/// Stream the events of a gravity update job as Server-Sent Events. Events
+/// which already happened are sent first, and the stream ends when the job
+/// finishes.
+#[get("/dns/gravity/jobs/<id>/events")]
+pub fn get_gravity_job_events(
+    _auth: CanReadStats,
+    jobs: &State<GravityJobs>,
+    id: u64,
+) -> Result<EventStream![], Error> {
+    if jobs.get(id).is_none() {
+        return Err(Error::from(ErrorKind::NotFound));
+    }
+
+    let jobs = jobs.inner().clone();
+
+    Ok(EventStream! {
+        let mut sent = 0;
+
+        // The job is forgotten if enough newer jobs finish in the meantime
+        while let Some((events, running)) = jobs.events_since(id, sent) {
+            sent += events.len();
+
+            for event in events {
+                yield Event::json(&event);
+            }
+
+            if !running {
+                break;
+            }
+
+            sleep(EVENT_POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// Run a gravity update job and record its outcome
+fn run_update(service: &dyn GravityService, jobs: &GravityJobs, id: u64, env: &Env) {
+    // Fail the job if the update panics, so it is not left running forever
+    let guard = jobs.guard(id);
+    let result = service
+        .update(&jobs.progress(id))
+        .and_then(|result| reload_dns(env).map(|_| result));
+
+    guard.finish(result);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::gravity::{
+            AdlistUpdateResult, GravityEvent, GravityService, GravityUpdateResult,
+            MockGravityService,
+        },
+        testing::TestBuilder,
+    };
+    use rocket::http::{Method, Status};
+
+    /// The job ID is returned, and the job is run
+    #[test]
+    fn update_gravity() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/gravity/update")
+            .method(Method::Post)
+            .mock_provider::<dyn GravityService>(Box::new(|_| {
+                let mut service = MockGravityService::new();
+
+                service.expect_update().times(1).returning(|progress| {
+                    progress.report(GravityEvent::Parsed {
+                        adlist_id: 1,
+                        domains: 2,
+                        invalid_lines: 1,
+                    });
+
+                    Ok(GravityUpdateResult {
+                        adlists: vec![AdlistUpdateResult {
+                            id: 1,
+                            address: "https://example.com/hosts".to_owned(),
+                            domains: 2,
+                            invalid_lines: 1,
+                            error: None,
+                        }],
+                        domains: 2,
+                        whitelisted: 0,
+                        timestamp: 1557712118,
+                    })
+                });
+
+                Ok(Box::new(service))
+            }))
+            .expect_json(json!({ "id": 1 }))
+            .test();
+    }
+
+    /// Unknown jobs are not found
+    #[test]
+    fn get_gravity_job_not_found() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/gravity/jobs/1")
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Unknown jobs have no event stream
+    #[test]
+    fn get_gravity_job_events_not_found() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/gravity/jobs/1/events")
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// The jobs are only shown to authenticated users
+    #[test]
+    fn get_gravity_job_unauthorized() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/gravity/jobs/1")
+            .should_auth(false)
+            .expect_status(Status::Unauthorized)
+            .expect_json(json!({
+                "error": {
+                    "key": "unauthorized",
+                    "message": "Unauthorized",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+}
@@ -0,0 +1,391 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Managing Groups
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::CanWriteLists,
+    services::{
+        groups::{GroupInput, GroupRepository},
+        lists::List,
+        PiholeModule,
+    },
+    util::{reply_data, reply_result, reply_success, Error, ErrorKind, Reply},
+};
+use rocket::serde::json::Json;
+use shaku_rocket::InjectProvided;
+
+/// Check that a group has a name
+fn validate_group(input: &GroupInput) -> Result<(), Error> {
+    if input.name.trim().is_empty() {
+        Err(Error::from(ErrorKind::BadRequest))
+    } else {
+        Ok(())
+    }
+}
+
+/// Get all of the groups
+#[get("/dns/groups")]
+pub fn get_groups(repo: InjectProvided<PiholeModule, dyn GroupRepository>) -> Reply {
+    reply_result(repo.get_all())
+}
+
+/// Create a group
+#[post("/dns/groups", data = "<group_input>")]
+pub fn add_group(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    group_input: Json<GroupInput>,
+) -> Reply {
+    validate_group(&group_input)?;
+    reply_data(repo.add(&group_input)?)
+}
+
+/// Update the name, description, and enabled status of a group
+#[put("/dns/groups/<id>", data = "<group_input>")]
+pub fn update_group(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+    group_input: Json<GroupInput>,
+) -> Reply {
+    validate_group(&group_input)?;
+    repo.update(id, &group_input)?;
+    reply_success()
+}
+
+/// Delete a group. The list entries and adlists assigned to it are kept.
+#[delete("/dns/groups/<id>")]
+pub fn delete_group(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+) -> Reply {
+    repo.remove(id)?;
+    reply_success()
+}
+
+/// Get the list entries and adlists assigned to a group
+#[get("/dns/groups/<id>/members")]
+pub fn get_group_members(
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+) -> Reply {
+    reply_result(repo.get_members(id))
+}
+
+/// Assign a whitelist, blacklist, regex list, or wildcard entry to a group
+#[put("/dns/groups/<id>/<list>/<domain>", rank = 2)]
+pub fn add_group_domain(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+    list: List,
+    domain: String,
+) -> Reply {
+    repo.add_domain(id, list.stored_list(), &list.to_stored(&domain))?;
+    reply_success()
+}
+
+/// Unassign a whitelist, blacklist, regex list, or wildcard entry from a group
+#[delete("/dns/groups/<id>/<list>/<domain>", rank = 2)]
+pub fn delete_group_domain(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+    list: List,
+    domain: String,
+) -> Reply {
+    repo.remove_domain(id, list.stored_list(), &list.to_stored(&domain))?;
+    reply_success()
+}
+
+/// Assign an adlist to a group
+#[put("/dns/groups/<id>/adlists/<adlist_id>")]
+pub fn add_group_adlist(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+    adlist_id: i32,
+) -> Reply {
+    repo.add_adlist(id, adlist_id)?;
+    reply_success()
+}
+
+/// Unassign an adlist from a group
+#[delete("/dns/groups/<id>/adlists/<adlist_id>")]
+pub fn delete_group_adlist(
+    _auth: CanWriteLists,
+    repo: InjectProvided<PiholeModule, dyn GroupRepository>,
+    id: i32,
+    adlist_id: i32,
+) -> Reply {
+    repo.remove_adlist(id, adlist_id)?;
+    reply_success()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::{
+            groups::{Group, GroupInput, GroupMembers, GroupRepository, MockGroupRepository},
+            lists::List,
+        },
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::{Method, Status};
+    use serde_json::Value;
+
+    /// All groups are returned
+    #[test]
+    fn get_groups() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups")
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_get_all().return_const(Ok(vec![Group {
+                    id: 1,
+                    enabled: true,
+                    name: "Test Group".to_owned(),
+                    description: None,
+                }]));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!([{
+                "id": 1,
+                "enabled": true,
+                "name": "Test Group",
+                "description": null
+            }]))
+            .test();
+    }
+
+    /// A created group is returned with its ID. Groups are enabled by default.
+    #[test]
+    fn add_group() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups")
+            .method(Method::Post)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_add()
+                    .with(eq(GroupInput {
+                        name: "Kids".to_owned(),
+                        description: Some("Devices used by kids".to_owned()),
+                        enabled: true,
+                    }))
+                    .return_const(Ok(Group {
+                        id: 2,
+                        enabled: true,
+                        name: "Kids".to_owned(),
+                        description: Some("Devices used by kids".to_owned()),
+                    }));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!({ "name": "Kids", "description": "Devices used by kids" }))
+            .expect_json(json!({
+                "id": 2,
+                "enabled": true,
+                "name": "Kids",
+                "description": "Devices used by kids"
+            }))
+            .test();
+    }
+
+    /// Groups need a name
+    #[test]
+    fn add_group_without_name() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups")
+            .method(Method::Post)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                Ok(Box::new(MockGroupRepository::new()))
+            }))
+            .body(json!({ "name": " " }))
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "bad_request",
+                    "message": "Bad request",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+
+    /// Updating a group passes the new properties to the repository
+    #[test]
+    fn update_group() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1")
+            .method(Method::Put)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_update()
+                    .with(
+                        eq(1),
+                        eq(GroupInput {
+                            name: "Renamed".to_owned(),
+                            description: None,
+                            enabled: false,
+                        }),
+                    )
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!({ "name": "Renamed", "enabled": false }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Deleting a group returns success
+    #[test]
+    fn delete_group() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1")
+            .method(Method::Delete)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_remove().with(eq(1)).return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// The members of a group are returned
+    #[test]
+    fn get_group_members() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/members")
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_get_members()
+                    .with(eq(1))
+                    .return_const(Ok(GroupMembers {
+                        whitelist: vec!["test.com".to_owned()],
+                        adlists: vec![1],
+                        ..GroupMembers::default()
+                    }));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({
+                "whitelist": ["test.com"],
+                "blacklist": [],
+                "regexlist": [],
+                "adlists": [1]
+            }))
+            .test();
+    }
+
+    /// List entries are assigned using the list name in the path
+    #[test]
+    fn add_group_domain() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/regexlist/%5E.%2Aexample.com%24")
+            .method(Method::Put)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_add_domain()
+                    .with(eq(1), eq(List::Regex), eq("^.*example.com$"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Wildcards are assigned using their stored regex
+    #[test]
+    fn add_group_wildcard() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/wildcardlist/%2A.example.com")
+            .method(Method::Put)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_add_domain()
+                    .with(eq(1), eq(List::Regex), eq("(^|\\.)example\\.com$"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// List entries are unassigned using the list name in the path
+    #[test]
+    fn delete_group_domain() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/blacklist/example.com")
+            .method(Method::Delete)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_remove_domain()
+                    .with(eq(1), eq(List::Black), eq("example.com"))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Adlists are assigned by their ID
+    #[test]
+    fn add_group_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/adlists/2")
+            .method(Method::Put)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_add_adlist()
+                    .with(eq(1), eq(2))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+
+    /// Adlists are unassigned by their ID
+    #[test]
+    fn delete_group_adlist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/groups/1/adlists/2")
+            .method(Method::Delete)
+            .mock_provider::<dyn GroupRepository>(Box::new(|_| {
+                let mut repo = MockGroupRepository::new();
+
+                repo.expect_remove_adlist()
+                    .with(eq(1), eq(2))
+                    .return_const(Ok(()));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({ "status": "success" }))
+            .test();
+    }
+}
@@ -0,0 +1,112 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoint For Looking Up Why A Domain Is Blocked
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    services::{lookup::LookupRepository, PiholeModule},
+    settings::ValueType,
+    util::{reply_result, Error, ErrorKind, Reply},
+};
+use shaku_rocket::InjectProvided;
+
+/// Find every list entry, regex, and gravity entry which matches a domain, and
+/// the verdict FTL would reach for it
+#[get("/dns/lookup/<domain>")]
+pub fn lookup_domain(
+    repo: InjectProvided<PiholeModule, dyn LookupRepository>,
+    domain: String,
+) -> Reply {
+    let domain = domain.to_lowercase();
+
+    if !ValueType::Hostname.is_valid(&domain) {
+        return Err(Error::from(ErrorKind::InvalidDomain));
+    }
+
+    reply_result(repo.lookup(&domain))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::lookup::{
+            DomainLookup, GravityMatch, LookupEntry, LookupRepository, MockLookupRepository,
+        },
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::Status;
+    use serde_json::Value;
+
+    /// The matches and the verdict are returned
+    #[test]
+    fn lookup_domain() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/lookup/Example.com")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                let mut repo = MockLookupRepository::new();
+
+                repo.expect_lookup()
+                    .with(eq("example.com"))
+                    .return_const(Ok(DomainLookup::new(
+                        "example.com".to_owned(),
+                        None,
+                        Some(LookupEntry {
+                            id: 1,
+                            domain: "example.com".to_owned(),
+                            enabled: true,
+                            comment: Some("Broken site".to_owned()),
+                            groups: vec![1],
+                        }),
+                        Vec::new(),
+                        GravityMatch::default(),
+                        &[1],
+                    )));
+
+                Ok(Box::new(repo))
+            }))
+            .expect_json(json!({
+                "domain": "example.com",
+                "whitelist": null,
+                "blacklist": {
+                    "id": 1,
+                    "domain": "example.com",
+                    "enabled": true,
+                    "comment": "Broken site",
+                    "groups": [1]
+                },
+                "regex": [],
+                "gravity": {
+                    "found": false,
+                    "adlists": []
+                },
+                "blocked": true,
+                "verdict": "blacklisted"
+            }))
+            .test();
+    }
+
+    /// Invalid domains are rejected
+    #[test]
+    fn lookup_invalid_domain() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/lookup/in%20valid")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(MockLookupRepository::new()))
+            }))
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "invalid_domain",
+                    "message": "Invalid domain",
+                    "data": Value::Null
+                }
+            }))
+            .test();
+    }
+}
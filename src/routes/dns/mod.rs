@@ -9,9 +9,20 @@
 // Please see LICENSE file for your rights under this license.
 
 mod add_list;
+mod adlists;
+mod audit;
+mod bulk_list;
 mod common;
 mod delete_list;
 mod get_list;
+mod gravity;
+mod groups;
+mod lookup;
 mod status;
+mod test_regex;
+mod update_list;
 
-pub use self::{add_list::*, delete_list::*, get_list::*, status::*};
+pub use self::{
+    add_list::*, adlists::*, audit::*, bulk_list::*, delete_list::*, get_list::*, gravity::*,
+    groups::*, lookup::*, status::*, test_regex::*, update_list::*,
+};
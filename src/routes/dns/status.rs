@@ -10,7 +10,7 @@
 
 use crate::{
     env::{Env, PiholeFile},
-    routes::dns::common::reload_dns,
+    routes::{auth::CanChangeDnsStatus, dns::common::reload_dns},
     services::PiholeModule,
     settings::{ConfigEntry, SetupVarsEntry},
     util::{reply_data, reply_error, reply_success, Error, ErrorKind, Reply},
@@ -35,6 +35,7 @@ pub fn get_status(env: Inject<PiholeModule, Env>) -> Reply {
 /// Enable/Disable blocking
 #[post("/dns/status", data = "<data>")]
 pub fn change_status(
+    _auth: CanChangeDnsStatus,
     env: Inject<PiholeModule, Env>,
     scheduler: &State<Scheduler>,
     data: Json<ChangeStatus>,
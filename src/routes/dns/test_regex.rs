@@ -0,0 +1,334 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoint For Testing A Regex Before Adding It
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::{ftl::FtlDatabase, DatabaseService},
+    env::Env,
+    ftl::FtlMemory,
+    routes::{auth::CanReadStats, stats::common::HIDDEN_DOMAIN},
+    services::{
+        lists::{List, ListEntry, ListRepository},
+        PiholeModule,
+    },
+    settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
+    util::{reply_result, Error, ErrorKind, Reply},
+};
+use diesel::{prelude::*, sqlite::SqliteConnection};
+use failure::ResultExt;
+use regex::Regex;
+use rocket::{serde::json::Json, State};
+use shaku_rocket::{Inject, InjectProvided};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Represents the input of a regex test
+#[derive(Deserialize)]
+pub struct RegexTestInput {
+    regex: String,
+    /// If set, also check the domains queried in the last `days` days
+    days: Option<u64>,
+}
+
+/// The domains which a regex would match
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct RegexTestReply {
+    /// Domains in FTL's memory
+    memory: Vec<String>,
+    /// Domains in the query database, if `days` was given
+    database: Option<Vec<String>>,
+    /// Whitelist entries which would be overruled by the regex
+    whitelist: Vec<String>,
+}
+
+/// Find the domains which a candidate regex would match, without adding it to
+/// the regex list
+#[post("/dns/regexlist/test", data = "<input>")]
+pub fn test_regex(
+    _auth: CanReadStats,
+    ftl_memory: &State<FtlMemory>,
+    env: Inject<PiholeModule, Env>,
+    db_service: Inject<PiholeModule, dyn DatabaseService<FtlDatabase>>,
+    list_repo: InjectProvided<PiholeModule, dyn ListRepository>,
+    input: Json<RegexTestInput>,
+) -> Reply {
+    // Only connect to the database if it will be searched
+    let database = match input.days {
+        Some(days) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+
+            Some((
+                db_service.get_connection()?,
+                now.saturating_sub(days.saturating_mul(86400)),
+            ))
+        }
+        None => None,
+    };
+
+    reply_result(test_regex_impl(
+        ftl_memory,
+        &env,
+        database
+            .as_ref()
+            .map(|(db, from)| (db as &SqliteConnection, *from)),
+        list_repo.get(List::White)?,
+        &input.regex,
+    ))
+}
+
+/// Match the regex against the domains in FTL's memory, the domains queried
+/// since the timestamp (if a database is given), and the whitelist
+fn test_regex_impl(
+    ftl_memory: &FtlMemory,
+    env: &Env,
+    database: Option<(&SqliteConnection, u64)>,
+    whitelist: Vec<ListEntry>,
+    regex: &str,
+) -> Result<RegexTestReply, Error> {
+    if !List::Regex.accepts(regex) {
+        return Err(Error::from(ErrorKind::InvalidDomain));
+    }
+
+    let regex = Regex::new(regex).context(ErrorKind::InvalidDomain)?;
+
+    let whitelist = whitelist
+        .into_iter()
+        .map(|entry| entry.domain)
+        .filter(|domain| regex.is_match(domain))
+        .collect();
+
+    // Do not reveal queried domains if they are private
+    if FtlConfEntry::PrivacyLevel.read_as::<FtlPrivacyLevel>(env)? >= FtlPrivacyLevel::HideDomains {
+        return Ok(RegexTestReply {
+            memory: Vec::new(),
+            database: database.map(|_| Vec::new()),
+            whitelist,
+        });
+    }
+
+    let lock = ftl_memory.lock()?;
+    let counters = ftl_memory.counters(&lock)?;
+    let strings = ftl_memory.strings(&lock)?;
+    let domains = ftl_memory.domains(&lock)?;
+
+    let mut memory: Vec<String> = domains
+        .iter()
+        // Skip the uninitialized domains
+        .take(counters.total_domains as usize)
+        .map(|domain| domain.get_domain(&strings))
+        .filter(|domain| *domain != HIDDEN_DOMAIN && regex.is_match(domain))
+        .map(str::to_owned)
+        .collect();
+    memory.sort();
+
+    let database = match database {
+        Some((db, from)) => {
+            use crate::databases::ftl::queries::dsl::*;
+
+            // Timestamps are stored as 32 bit integers
+            let from = from.min(i32::MAX as u64) as i32;
+
+            let queried_domains: Vec<String> = queries
+                .select(domain)
+                .distinct()
+                .filter(timestamp.ge(from))
+                .filter(domain.ne(HIDDEN_DOMAIN))
+                .order(domain)
+                .load(db)
+                .context(ErrorKind::FtlDatabase)?;
+
+            Some(
+                queried_domains
+                    .into_iter()
+                    .filter(|queried_domain| regex.is_match(queried_domain))
+                    .collect(),
+            )
+        }
+        None => None,
+    };
+
+    Ok(RegexTestReply {
+        memory,
+        database,
+        whitelist,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{test_regex_impl, RegexTestReply};
+    use crate::{
+        databases::ftl::connect_to_ftl_test_db,
+        env::PiholeFile,
+        ftl::{FtlCounters, FtlDomain, FtlMemory, FtlRegexMatch, FtlSettings},
+        services::lists::{ListEntry, ListRepository, MockListRepository},
+        testing::{TestBuilder, TestEnvBuilder},
+        util::ErrorKind,
+    };
+    use rocket::http::Method;
+    use std::collections::HashMap;
+
+    fn test_entry(domain: &str) -> ListEntry {
+        ListEntry {
+            id: 1,
+            domain: domain.to_owned(),
+            enabled: true,
+            date_added: 1557712118,
+            date_modified: 1557712118,
+            comment: None,
+        }
+    }
+
+    /// Three domains in FTL's memory
+    fn test_memory() -> FtlMemory {
+        let mut strings = HashMap::new();
+        strings.insert(1, "ads.example.com".to_owned());
+        strings.insert(2, "example.net".to_owned());
+        strings.insert(3, "tracker.example.com".to_owned());
+
+        FtlMemory::Test {
+            queries: Vec::new(),
+            domains: vec![
+                FtlDomain::new(1, 0, 1, FtlRegexMatch::NotBlocked),
+                FtlDomain::new(1, 0, 2, FtlRegexMatch::NotBlocked),
+                FtlDomain::new(1, 0, 3, FtlRegexMatch::NotBlocked),
+            ],
+            over_time: Vec::new(),
+            strings,
+            clients: Vec::new(),
+            upstreams: Vec::new(),
+            counters: FtlCounters {
+                total_domains: 3,
+                ..FtlCounters::default()
+            },
+            settings: FtlSettings::default(),
+        }
+    }
+
+    /// Domains in memory and conflicting whitelist entries are returned
+    #[test]
+    fn memory_and_whitelist() {
+        TestBuilder::new()
+            .endpoint("/admin/api/dns/regexlist/test")
+            .method(Method::Post)
+            .ftl_memory(test_memory())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn ListRepository>(Box::new(|_| {
+                let mut repo = MockListRepository::new();
+
+                repo.expect_get().return_const(Ok(vec![
+                    test_entry("tracker.example.com"),
+                    test_entry("example.org"),
+                ]));
+
+                Ok(Box::new(repo))
+            }))
+            .body(json!({ "regex": "\\.example\\.com$" }))
+            .expect_json(json!({
+                "memory": ["ads.example.com", "tracker.example.com"],
+                "database": null,
+                "whitelist": ["tracker.example.com"]
+            }))
+            .test();
+    }
+
+    /// Queried domains are found in the database when asked for
+    #[test]
+    fn database() {
+        let db = connect_to_ftl_test_db();
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, "")
+            .build();
+
+        assert_eq!(
+            test_regex_impl(
+                &test_memory(),
+                &env,
+                Some((&*db, 0)),
+                Vec::new(),
+                "ubuntu\\.pool\\.ntp\\.org$"
+            )
+            .unwrap(),
+            RegexTestReply {
+                memory: Vec::new(),
+                database: Some(vec![
+                    "0.ubuntu.pool.ntp.org".to_owned(),
+                    "1.ubuntu.pool.ntp.org".to_owned(),
+                    "2.ubuntu.pool.ntp.org".to_owned(),
+                    "3.ubuntu.pool.ntp.org".to_owned()
+                ]),
+                whitelist: Vec::new()
+            }
+        );
+    }
+
+    /// Domains are not revealed if the privacy level hides them
+    #[test]
+    fn private_domains() {
+        let db = connect_to_ftl_test_db();
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, "PRIVACYLEVEL=1")
+            .build();
+
+        assert_eq!(
+            test_regex_impl(
+                &test_memory(),
+                &env,
+                Some((&*db, 0)),
+                vec![test_entry("ads.example.com")],
+                "example"
+            )
+            .unwrap(),
+            RegexTestReply {
+                memory: Vec::new(),
+                database: Some(Vec::new()),
+                whitelist: vec!["ads.example.com".to_owned()]
+            }
+        );
+    }
+
+    /// Timestamps after the 32 bit range do not overflow
+    #[test]
+    fn database_future_timestamp() {
+        let db = connect_to_ftl_test_db();
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, "")
+            .build();
+
+        assert_eq!(
+            test_regex_impl(
+                &test_memory(),
+                &env,
+                Some((&*db, u64::MAX)),
+                Vec::new(),
+                "ubuntu"
+            )
+            .unwrap()
+            .database,
+            Some(Vec::new())
+        );
+    }
+
+    /// A regex which does not compile is rejected
+    #[test]
+    fn invalid_regex() {
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, "")
+            .build();
+
+        assert_eq!(
+            test_regex_impl(&test_memory(), &env, None, Vec::new(), "(")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidDomain
+        );
+    }
+}
@@ -0,0 +1,151 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Endpoints For Updating List Entries
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    routes::auth::CanWriteLists,
+    services::{
+        lists::{List, ListEntryPatch, ListService},
+        PiholeModule,
+    },
+    util::{reply_result, Reply},
+};
+use rocket::serde::json::Json;
+use shaku_rocket::InjectProvided;
+
+/// Enable/disable a whitelist entry or change its comment
+#[patch("/dns/whitelist/<domain>", data = "<patch>")]
+pub fn update_whitelist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain: String,
+    patch: Json<ListEntryPatch>,
+) -> Reply {
+    reply_result(list_service.update(List::White, &domain, &patch))
+}
+
+/// Enable/disable a blacklist entry or change its comment
+#[patch("/dns/blacklist/<domain>", data = "<patch>")]
+pub fn update_blacklist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain: String,
+    patch: Json<ListEntryPatch>,
+) -> Reply {
+    reply_result(list_service.update(List::Black, &domain, &patch))
+}
+
+/// Enable/disable a regex list entry or change its comment
+#[patch("/dns/regexlist/<domain>", data = "<patch>")]
+pub fn update_regexlist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain: String,
+    patch: Json<ListEntryPatch>,
+) -> Reply {
+    reply_result(list_service.update(List::Regex, &domain, &patch))
+}
+
+/// Enable/disable a wildcard or change its comment
+#[patch("/dns/wildcardlist/<domain>", data = "<patch>")]
+pub fn update_wildcardlist(
+    _auth: CanWriteLists,
+    list_service: InjectProvided<PiholeModule, dyn ListService>,
+    domain: String,
+    patch: Json<ListEntryPatch>,
+) -> Reply {
+    reply_result(list_service.update(List::Wildcard, &domain, &patch))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        services::lists::{List, ListEntry, ListEntryPatch, ListService, MockListService},
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::Method;
+
+    /// Test that a successful update returns the updated entry
+    fn update_test(list: List, endpoint: &str, domain: &'static str) {
+        TestBuilder::new()
+            .endpoint(endpoint)
+            .method(Method::Patch)
+            .mock_provider::<dyn ListService>(Box::new(move |_| {
+                let mut service = MockListService::new();
+
+                service
+                    .expect_update()
+                    .with(
+                        eq(list),
+                        eq(domain),
+                        eq(ListEntryPatch {
+                            enabled: Some(false),
+                            comment: Some("Temporarily allowed".to_owned()),
+                        }),
+                    )
+                    .return_const(Ok(ListEntry {
+                        id: 1,
+                        domain: domain.to_owned(),
+                        enabled: false,
+                        date_added: 1557712172,
+                        date_modified: 1557723911,
+                        comment: Some("Temporarily allowed".to_owned()),
+                    }));
+
+                Ok(Box::new(service))
+            }))
+            .body(json!({ "enabled": false, "comment": "Temporarily allowed" }))
+            .expect_json(json!({
+                "id": 1,
+                "domain": domain,
+                "enabled": false,
+                "date_added": 1557712172,
+                "date_modified": 1557723911,
+                "comment": "Temporarily allowed"
+            }))
+            .test();
+    }
+
+    #[test]
+    fn test_update_whitelist() {
+        update_test(
+            List::White,
+            "/admin/api/dns/whitelist/example.com",
+            "example.com",
+        );
+    }
+
+    #[test]
+    fn test_update_blacklist() {
+        update_test(
+            List::Black,
+            "/admin/api/dns/blacklist/example.com",
+            "example.com",
+        );
+    }
+
+    #[test]
+    fn test_update_regexlist() {
+        update_test(
+            List::Regex,
+            "/admin/api/dns/regexlist/%5E.%2Aexample.com%24",
+            "^.*example.com$",
+        );
+    }
+
+    #[test]
+    fn test_update_wildcardlist() {
+        update_test(
+            List::Wildcard,
+            "/admin/api/dns/wildcardlist/%2A.example.com",
+            "*.example.com",
+        );
+    }
+}
@@ -10,7 +10,10 @@
 
 use crate::{
     env::Env,
-    routes::{auth::User, settings::common::restart_dns},
+    routes::{
+        auth::{CanWriteSettings, User},
+        settings::common::restart_dns,
+    },
     services::PiholeModule,
     settings::{generate_dnsmasq_config, ConfigEntry, SetupVarsEntry},
     util::{reply_data, reply_success, Error, ErrorKind, Reply},
@@ -69,7 +72,11 @@ pub fn get_dhcp(env: Inject<PiholeModule, Env>, _auth: User) -> Reply {
 
 /// Update DHCP Configuration
 #[put("/settings/dhcp", data = "<data>")]
-pub fn put_dhcp(env: Inject<PiholeModule, Env>, _auth: User, data: Json<DhcpSettings>) -> Reply {
+pub fn put_dhcp(
+    env: Inject<PiholeModule, Env>,
+    _auth: CanWriteSettings,
+    data: Json<DhcpSettings>,
+) -> Reply {
     let settings: DhcpSettings = data.into_inner();
 
     if !settings.is_valid() {
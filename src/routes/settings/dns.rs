@@ -10,7 +10,10 @@
 
 use crate::{
     env::Env,
-    routes::{auth::User, settings::common::restart_dns},
+    routes::{
+        auth::{CanWriteSettings, User},
+        settings::common::restart_dns,
+    },
     services::PiholeModule,
     settings::{generate_dnsmasq_config, ConfigEntry, SetupVarsEntry, ValueType},
     util::{reply_data, reply_success, Error, ErrorKind, Reply},
@@ -125,7 +128,11 @@ pub fn get_dns(env: Inject<PiholeModule, Env>, _auth: User) -> Reply {
 
 /// Update DNS Configuration
 #[put("/settings/dns", data = "<data>")]
-pub fn put_dns(env: Inject<PiholeModule, Env>, _auth: User, data: Json<DnsSettings>) -> Reply {
+pub fn put_dns(
+    env: Inject<PiholeModule, Env>,
+    _auth: CanWriteSettings,
+    data: Json<DnsSettings>,
+) -> Reply {
     let settings: DnsSettings = data.into_inner();
 
     if !settings.is_valid() {
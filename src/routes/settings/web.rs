@@ -10,7 +10,7 @@
 
 use crate::{
     env::Env,
-    routes::auth::User,
+    routes::auth::CanWriteSettings,
     services::PiholeModule,
     settings::{ConfigEntry, SetupVarsEntry},
     util::{reply_data, reply_success, Error, ErrorKind, Reply},
@@ -31,7 +31,11 @@ pub fn get_web(env: Inject<PiholeModule, Env>) -> Reply {
 
 /// Update web interface settings
 #[put("/settings/web", data = "<settings>")]
-pub fn put_web(_auth: User, env: Inject<PiholeModule, Env>, settings: Json<WebSettings>) -> Reply {
+pub fn put_web(
+    _auth: CanWriteSettings,
+    env: Inject<PiholeModule, Env>,
+    settings: Json<WebSettings>,
+) -> Reply {
     let settings = settings.into_inner();
 
     if !settings.is_valid() {
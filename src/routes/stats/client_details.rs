@@ -0,0 +1,424 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Client Details Endpoint
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::ftl::FtlDatabase,
+    env::Env,
+    ftl::{ClientReply, FtlMemory, FtlQueryType},
+    routes::{
+        auth::CanReadStats,
+        stats::{
+            common::{get_current_over_time_slot, HIDDEN_CLIENT, HIDDEN_DOMAIN},
+            database::client_details_db::get_client_details_db,
+            query_types::QueryTypeReply,
+            top_domains::TopDomainItemReply,
+        },
+    },
+    services::PiholeModule,
+    settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
+    util::{reply_result, Error, ErrorKind, Reply},
+};
+use diesel::SqliteConnection;
+use rocket::State;
+use shaku_rocket::{Inject, InjectProvided};
+use std::collections::HashMap;
+
+pub use client_details as route;
+
+/// Get the details of a client: its query counts, queries over time, top
+/// domains, and query types. The details are calculated from the queries in
+/// memory (the last 24 hours), or from the database if both `from` and
+/// `until` are given. Giving only one of them is a bad request.
+#[get("/stats/clients/<ip>?<params..>")]
+pub fn client_details(
+    _auth: CanReadStats,
+    ftl_memory: &State<FtlMemory>,
+    env: Inject<PiholeModule, Env>,
+    db: InjectProvided<PiholeModule, FtlDatabase>,
+    ip: String,
+    params: DetailsParams,
+) -> Reply {
+    if params.from.is_none() && params.until.is_none() {
+        reply_result(get_client_details(
+            ftl_memory,
+            &env,
+            &ip,
+            params.limit.unwrap_or(10),
+        ))
+    } else {
+        reply_result(get_client_details_db(
+            &env,
+            &db as &SqliteConnection,
+            &ip,
+            &params,
+        ))
+    }
+}
+
+/// Represents the possible GET parameters for client and domain details
+/// requests. Both `from` and `until` must be given to use the database.
+#[derive(FromForm, Default)]
+pub struct DetailsParams {
+    pub from: Option<u64>,
+    pub until: Option<u64>,
+    pub interval: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl DetailsParams {
+    /// Get the length of the overTime intervals in seconds. The default is ten
+    /// minutes, and an interval of zero is rejected.
+    pub fn interval(&self) -> Result<usize, Error> {
+        match self.interval.unwrap_or(600) {
+            0 => Err(Error::from(ErrorKind::BadRequest)),
+            interval => Ok(interval),
+        }
+    }
+}
+
+/// Represents the reply structure for client details
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct ClientDetailsReply {
+    pub client: ClientReply,
+    pub total_queries: usize,
+    pub blocked_queries: usize,
+    /// The timestamp of the client's most recent query
+    pub last_seen: u64,
+    pub over_time: Vec<ClientOverTimeItem>,
+    pub top_domains: Vec<TopDomainItemReply>,
+    pub top_blocked: Vec<TopDomainItemReply>,
+    pub query_types: Vec<QueryTypeReply>,
+}
+
+/// The number of queries a client made in an overTime interval
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct ClientOverTimeItem {
+    pub timestamp: u64,
+    pub count: usize,
+}
+
+/// Check if the client's details can be shared. Hidden clients and clients
+/// hidden by the privacy level are treated as if they do not exist. Returns
+/// true if the domains can be shared as well.
+pub fn check_privacy_client_details(env: &Env, ip: &str) -> Result<bool, Error> {
+    let privacy_level = FtlConfEntry::PrivacyLevel.read_as::<FtlPrivacyLevel>(env)?;
+
+    if privacy_level >= FtlPrivacyLevel::HideDomainsAndClients || ip == HIDDEN_CLIENT {
+        return Err(Error::from(ErrorKind::NotFound));
+    }
+
+    Ok(privacy_level < FtlPrivacyLevel::HideDomains)
+}
+
+/// Get the details of a client from the queries in memory
+fn get_client_details(
+    ftl_memory: &FtlMemory,
+    env: &Env,
+    ip: &str,
+    limit: usize,
+) -> Result<ClientDetailsReply, Error> {
+    let show_domains = check_privacy_client_details(env, ip)?;
+
+    let lock = ftl_memory.lock()?;
+    let counters = ftl_memory.counters(&lock)?;
+    let strings = ftl_memory.strings(&lock)?;
+    let clients = ftl_memory.clients(&lock)?;
+    let domains = ftl_memory.domains(&lock)?;
+    let queries = ftl_memory.queries(&lock)?;
+    let over_time = ftl_memory.over_time(&lock)?;
+
+    // Find the client
+    let (client_id, client) = clients
+        .iter()
+        .take(counters.total_clients as usize)
+        .enumerate()
+        .find(|(_, client)| client.get_ip(&strings) == ip)
+        .ok_or(ErrorKind::NotFound)?;
+
+    // Get the client's overTime data, skipping the slots without any data
+    let over_time: Vec<ClientOverTimeItem> = over_time
+        .iter()
+        .take(get_current_over_time_slot(&over_time) + 1)
+        .enumerate()
+        .skip_while(|(_, time)| time.total_queries <= 0 && time.blocked_queries <= 0)
+        .map(|(i, time)| ClientOverTimeItem {
+            timestamp: time.timestamp as u64,
+            count: *client.over_time.get(i).unwrap_or(&0) as usize,
+        })
+        .collect();
+
+    // Count the client's queries by domain and query type
+    let mut permitted_counts: HashMap<usize, usize> = HashMap::new();
+    let mut blocked_counts: HashMap<usize, usize> = HashMap::new();
+    let mut query_type_counts: HashMap<FtlQueryType, usize> = HashMap::new();
+
+    for query in queries
+        .iter()
+        .take(counters.total_queries as usize)
+        .filter(|query| query.client_id as usize == client_id)
+    {
+        *query_type_counts.entry(query.query_type).or_default() += 1;
+
+        // Skip domains hidden by the query's privacy level
+        if !show_domains || query.privacy_level >= FtlPrivacyLevel::HideDomains {
+            continue;
+        }
+
+        let domain_counts = if query.is_blocked() {
+            &mut blocked_counts
+        } else {
+            &mut permitted_counts
+        };
+
+        *domain_counts.entry(query.domain_id as usize).or_default() += 1;
+    }
+
+    // Sort the domains by count (descending) and map them into the output
+    // format
+    let top_domains = |counts: HashMap<usize, usize>| -> Vec<TopDomainItemReply> {
+        let mut counts: Vec<(&str, usize)> = counts
+            .into_iter()
+            .map(|(domain_id, count)| (domains[domain_id].get_domain(&strings), count))
+            .filter(|(domain, _)| *domain != HIDDEN_DOMAIN)
+            .collect();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.truncate(limit);
+
+        counts
+            .into_iter()
+            .map(|(domain, count)| TopDomainItemReply {
+                domain: domain.to_owned(),
+                count,
+            })
+            .collect()
+    };
+
+    Ok(ClientDetailsReply {
+        client: client.as_reply(&strings),
+        total_queries: client.query_count as usize,
+        blocked_queries: client.blocked_count as usize,
+        last_seen: client.get_last_query_time(),
+        over_time,
+        top_domains: top_domains(permitted_counts),
+        top_blocked: top_domains(blocked_counts),
+        query_types: FtlQueryType::variants()
+            .iter()
+            .map(|variant| QueryTypeReply {
+                name: variant.get_name(),
+                count: query_type_counts.get(variant).copied().unwrap_or_default(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        env::PiholeFile,
+        ftl::{
+            FtlClient, FtlCounters, FtlDomain, FtlMemory, FtlOverTime, FtlQuery, FtlQueryStatus,
+            FtlQueryType, FtlRegexMatch, FtlSettings,
+        },
+        testing::TestBuilder,
+    };
+    use rocket::http::Status;
+    use std::collections::HashMap;
+
+    /// Make a query of the first client, or of the second client if `other`
+    fn query(
+        id: i32,
+        query_type: FtlQueryType,
+        status: FtlQueryStatus,
+        domain_id: i32,
+        other: bool,
+    ) -> FtlQuery {
+        FtlQuery {
+            id,
+            domain_id,
+            client_id: if other { 1 } else { 0 },
+            query_type,
+            status,
+            ..FtlQuery::default()
+        }
+    }
+
+    /// Two clients and three domains. The first client made five queries, one
+    /// of them blocked, in the second overTime slot. The other client made one
+    /// query.
+    fn test_data() -> FtlMemory {
+        let mut strings = HashMap::new();
+        strings.insert(1, "10.1.1.1".to_owned());
+        strings.insert(2, "client1".to_owned());
+        strings.insert(3, "10.1.1.2".to_owned());
+        strings.insert(4, "example.com".to_owned());
+        strings.insert(5, "github.com".to_owned());
+        strings.insert(6, "ads.net".to_owned());
+
+        FtlMemory::Test {
+            clients: vec![
+                FtlClient::new(5, 1, 1, Some(2))
+                    .with_over_time(vec![0, 5, 0])
+                    .with_last_query_time(2),
+                FtlClient::new(1, 0, 3, None).with_over_time(vec![0, 1, 0]),
+            ],
+            domains: vec![
+                FtlDomain::new(3, 0, 4, FtlRegexMatch::NotBlocked),
+                FtlDomain::new(2, 0, 5, FtlRegexMatch::NotBlocked),
+                FtlDomain::new(1, 1, 6, FtlRegexMatch::NotBlocked),
+            ],
+            over_time: vec![
+                FtlOverTime::new(0, 0, 0, 0, 0, [0; 7]),
+                FtlOverTime::new(1, 6, 1, 0, 5, [0; 7]),
+                FtlOverTime::new(2, 0, 0, 0, 0, [0; 7]),
+            ],
+            strings,
+            upstreams: Vec::new(),
+            queries: vec![
+                query(1, FtlQueryType::A, FtlQueryStatus::Forward, 0, false),
+                query(2, FtlQueryType::AAAA, FtlQueryStatus::Forward, 0, false),
+                query(3, FtlQueryType::A, FtlQueryStatus::Forward, 1, false),
+                query(4, FtlQueryType::A, FtlQueryStatus::Cache, 1, false),
+                query(5, FtlQueryType::A, FtlQueryStatus::Gravity, 2, false),
+                query(6, FtlQueryType::A, FtlQueryStatus::Forward, 0, true),
+            ],
+            counters: FtlCounters {
+                total_queries: 6,
+                total_clients: 2,
+                total_domains: 3,
+                ..FtlCounters::default()
+            },
+            settings: FtlSettings::default(),
+        }
+    }
+
+    /// The client's counts, overTime data, top domains, and query types are
+    /// returned
+    #[test]
+    fn client_details() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/clients/10.1.1.1?limit=1")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .expect_json(json!({
+                "client": { "name": "client1", "ip": "10.1.1.1" },
+                "total_queries": 5,
+                "blocked_queries": 1,
+                "last_seen": 2,
+                "over_time": [
+                    { "timestamp": 1, "count": 5 },
+                    { "timestamp": 2, "count": 0 }
+                ],
+                "top_domains": [
+                    { "domain": "example.com", "count": 2 }
+                ],
+                "top_blocked": [
+                    { "domain": "ads.net", "count": 1 }
+                ],
+                "query_types": [
+                    { "name": "A", "count": 4 },
+                    { "name": "AAAA", "count": 1 },
+                    { "name": "ANY", "count": 0 },
+                    { "name": "SRV", "count": 0 },
+                    { "name": "SOA", "count": 0 },
+                    { "name": "PTR", "count": 0 },
+                    { "name": "TXT", "count": 0 }
+                ]
+            }))
+            .test();
+    }
+
+    /// Domains are not shown if the privacy level hides domains
+    #[test]
+    fn privacy_hide_domains() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/clients/10.1.1.2")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "PRIVACYLEVEL=1")
+            .expect_json(json!({
+                "client": { "name": "", "ip": "10.1.1.2" },
+                "total_queries": 1,
+                "blocked_queries": 0,
+                "last_seen": 0,
+                "over_time": [
+                    { "timestamp": 1, "count": 1 },
+                    { "timestamp": 2, "count": 0 }
+                ],
+                "top_domains": [],
+                "top_blocked": [],
+                "query_types": [
+                    { "name": "A", "count": 1 },
+                    { "name": "AAAA", "count": 0 },
+                    { "name": "ANY", "count": 0 },
+                    { "name": "SRV", "count": 0 },
+                    { "name": "SOA", "count": 0 },
+                    { "name": "PTR", "count": 0 },
+                    { "name": "TXT", "count": 0 }
+                ]
+            }))
+            .test();
+    }
+
+    /// Unknown clients are not found
+    #[test]
+    fn not_found() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/clients/10.1.1.3")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Clients are not found if the privacy level hides clients
+    #[test]
+    fn privacy_hide_clients() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/clients/10.1.1.1")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "PRIVACYLEVEL=2")
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Both timestamps are required to use the database
+    #[test]
+    fn from_without_until() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/clients/10.1.1.1?from=1")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "bad_request",
+                    "message": "Bad request",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+}
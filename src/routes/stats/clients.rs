@@ -12,7 +12,7 @@ use crate::{
     env::Env,
     ftl::{ClientReply, FtlClient, FtlMemory, ShmLockGuard},
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::common::{remove_excluded_clients, remove_hidden_clients},
     },
     services::PiholeModule,
@@ -27,7 +27,7 @@ pub use clients as route;
 /// Get client information
 #[get("/stats/clients?<params..>")]
 pub fn clients(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
     params: ClientParams,
@@ -0,0 +1,317 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Client Details Database Functions
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::Env,
+    ftl::{ClientReply, FtlQueryType, BLOCKED_STATUSES},
+    routes::stats::{
+        client_details::{
+            check_privacy_client_details, ClientDetailsReply, ClientOverTimeItem, DetailsParams,
+        },
+        common::HIDDEN_DOMAIN,
+        database::{
+            over_time_clients_db::get_client_over_time, over_time_history_db::align_from_until,
+        },
+        query_types::QueryTypeReply,
+        top_domains::TopDomainItemReply,
+    },
+    util::{Error, ErrorKind},
+};
+use diesel::{
+    dsl::{max, sql},
+    prelude::*,
+    sql_types::BigInt,
+    sqlite::SqliteConnection,
+};
+use failure::ResultExt;
+use std::collections::HashMap;
+
+/// Get the details of a client from the database. The time range is aligned
+/// with the interval.
+pub fn get_client_details_db(
+    env: &Env,
+    db: &SqliteConnection,
+    ip: &str,
+    params: &DetailsParams,
+) -> Result<ClientDetailsReply, Error> {
+    use crate::databases::ftl::queries::dsl::*;
+
+    let show_domains = check_privacy_client_details(env, ip)?;
+    let interval = params.interval()?;
+    let limit = params.limit.unwrap_or(10);
+
+    // Both timestamps are required to use the database
+    let (from, until) = match (params.from, params.until) {
+        (Some(from), Some(until)) => align_from_until(from, until, interval as u64)?,
+        _ => return Err(Error::from(ErrorKind::BadRequest)),
+    };
+
+    // Only consider the client's queries in the time interval
+    let client_queries = || {
+        queries
+            .filter(client.eq(ip))
+            .filter(timestamp.ge(from as i32))
+            .filter(timestamp.lt(until as i32))
+    };
+
+    let total_queries: i64 = client_queries()
+        .count()
+        .get_result(db)
+        .context(ErrorKind::FtlDatabase)?;
+
+    // Clients without queries are not known to the database
+    if total_queries == 0 {
+        return Err(Error::from(ErrorKind::NotFound));
+    }
+
+    let blocked_queries: i64 = client_queries()
+        .filter(status.eq_any(&BLOCKED_STATUSES))
+        .count()
+        .get_result(db)
+        .context(ErrorKind::FtlDatabase)?;
+
+    let last_seen: Option<i32> = client_queries()
+        .select(max(timestamp))
+        .get_result(db)
+        .context(ErrorKind::FtlDatabase)?;
+
+    // Fill in the intervals without queries
+    let client_over_time = get_client_over_time(from, until, interval, ip, db)?;
+    let over_time = (from..until)
+        .step_by(interval)
+        .map(|time| ClientOverTimeItem {
+            // Display the timestamps as centered in the overTime slot interval
+            timestamp: time + (interval / 2) as u64,
+            count: client_over_time.get(&(time as i32)).copied().unwrap_or(0) as usize,
+        })
+        .collect();
+
+    let (top_domains, top_blocked) = if show_domains {
+        (
+            get_client_top_domains(db, ip, from, until, false, limit)?,
+            get_client_top_domains(db, ip, from, until, true, limit)?,
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    let query_type_counts: HashMap<i32, i64> = client_queries()
+        .select((query_type, sql::<BigInt>("COUNT(*)")))
+        .group_by(query_type)
+        .load(db)
+        .context(ErrorKind::FtlDatabase)?
+        .into_iter()
+        .collect();
+
+    Ok(ClientDetailsReply {
+        // The database does not store client names
+        client: ClientReply {
+            name: "".to_owned(),
+            ip: ip.to_owned(),
+        },
+        total_queries: total_queries as usize,
+        blocked_queries: blocked_queries as usize,
+        last_seen: last_seen.unwrap_or_default() as u64,
+        over_time,
+        top_domains,
+        top_blocked,
+        query_types: FtlQueryType::variants()
+            .iter()
+            .map(|variant| QueryTypeReply {
+                name: variant.get_name(),
+                count: query_type_counts
+                    .get(&(*variant as i32))
+                    .copied()
+                    .unwrap_or_default() as usize,
+            })
+            .collect(),
+    })
+}
+
+/// Get the client's top permitted or blocked domains, sorted by count
+fn get_client_top_domains(
+    db: &SqliteConnection,
+    ip: &str,
+    from: u64,
+    until: u64,
+    blocked: bool,
+    limit: usize,
+) -> Result<Vec<TopDomainItemReply>, Error> {
+    use crate::databases::ftl::queries::dsl::*;
+
+    let db_query = queries
+        .select((domain, sql::<BigInt>("COUNT(*)")))
+        .filter(client.eq(ip))
+        .filter(timestamp.ge(from as i32))
+        .filter(timestamp.lt(until as i32))
+        // Skip domains hidden by the privacy level
+        .filter(domain.ne(HIDDEN_DOMAIN))
+        .group_by(domain)
+        .order((sql::<BigInt>("COUNT(*)").desc(), domain))
+        .limit(limit as i64)
+        .into_boxed();
+
+    let db_query = if blocked {
+        db_query.filter(status.eq_any(&BLOCKED_STATUSES))
+    } else {
+        db_query.filter(status.ne_all(&BLOCKED_STATUSES))
+    };
+
+    Ok(db_query
+        .load::<(String, i64)>(db)
+        .context(ErrorKind::FtlDatabase)?
+        .into_iter()
+        .map(|(domain_name, count)| TopDomainItemReply {
+            domain: domain_name,
+            count: count as usize,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::get_client_details_db;
+    use crate::{
+        databases::ftl::connect_to_ftl_test_db,
+        env::{Env, PiholeFile},
+        ftl::ClientReply,
+        routes::stats::{
+            client_details::{ClientDetailsReply, ClientOverTimeItem, DetailsParams},
+            query_types::QueryTypeReply,
+            top_domains::TopDomainItemReply,
+        },
+        testing::TestEnvBuilder,
+        util::ErrorKind,
+    };
+
+    const FROM_TIMESTAMP: u64 = 164_400;
+    const UNTIL_TIMESTAMP: u64 = 164_999;
+
+    fn env(ftl_config: &str) -> Env {
+        TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, ftl_config)
+            .build()
+    }
+
+    fn params(from: u64, until: u64) -> DetailsParams {
+        DetailsParams {
+            from: Some(from),
+            until: Some(until),
+            limit: Some(3),
+            ..DetailsParams::default()
+        }
+    }
+
+    fn query_types(counts: [usize; 7]) -> Vec<QueryTypeReply> {
+        ["A", "AAAA", "ANY", "SRV", "SOA", "PTR", "TXT"]
+            .iter()
+            .zip(counts.iter())
+            .map(|(name, count)| QueryTypeReply {
+                name: (*name).to_owned(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    /// The client's details are calculated from the queries in the interval
+    #[test]
+    fn client_details() {
+        let expected = ClientDetailsReply {
+            client: ClientReply {
+                name: "".to_owned(),
+                ip: "127.0.0.1".to_owned(),
+            },
+            total_queries: 25,
+            blocked_queries: 0,
+            last_seen: 164_700,
+            over_time: vec![ClientOverTimeItem {
+                timestamp: 164_700,
+                count: 25,
+            }],
+            top_domains: vec![
+                TopDomainItemReply {
+                    domain: "github.com".to_owned(),
+                    count: 8,
+                },
+                TopDomainItemReply {
+                    domain: "ftl.pi-hole.net".to_owned(),
+                    count: 6,
+                },
+                TopDomainItemReply {
+                    domain: "4.4.8.8.in-addr.arpa".to_owned(),
+                    count: 3,
+                },
+            ],
+            top_blocked: Vec::new(),
+            query_types: query_types([9, 9, 0, 0, 0, 7, 0]),
+        };
+
+        let db = connect_to_ftl_test_db();
+        let actual = get_client_details_db(
+            &env(""),
+            &db,
+            "127.0.0.1",
+            &params(FROM_TIMESTAMP, UNTIL_TIMESTAMP),
+        )
+        .unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    /// Domains are not shown if the privacy level hides domains
+    #[test]
+    fn privacy_hide_domains() {
+        let db = connect_to_ftl_test_db();
+        let actual = get_client_details_db(
+            &env("PRIVACYLEVEL=1"),
+            &db,
+            "127.0.0.1",
+            &params(FROM_TIMESTAMP, UNTIL_TIMESTAMP),
+        )
+        .unwrap();
+
+        assert_eq!(actual.total_queries, 25);
+        assert_eq!(actual.top_domains, Vec::new());
+        assert_eq!(actual.top_blocked, Vec::new());
+    }
+
+    /// An interval of zero is rejected
+    #[test]
+    fn zero_interval() {
+        let db = connect_to_ftl_test_db();
+        let error = get_client_details_db(
+            &env(""),
+            &db,
+            "127.0.0.1",
+            &DetailsParams {
+                interval: Some(0),
+                ..params(FROM_TIMESTAMP, UNTIL_TIMESTAMP)
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::BadRequest);
+    }
+
+    /// Clients without queries in the interval are not found
+    #[test]
+    fn not_found() {
+        let db = connect_to_ftl_test_db();
+        let error = get_client_details_db(
+            &env(""),
+            &db,
+            "10.1.1.1",
+            &params(FROM_TIMESTAMP + 600, UNTIL_TIMESTAMP + 600),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
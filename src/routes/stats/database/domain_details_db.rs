@@ -0,0 +1,270 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Domain Details Database Functions
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::Env,
+    ftl::BLOCKED_STATUSES,
+    routes::stats::{
+        client_details::DetailsParams,
+        common::HIDDEN_CLIENT,
+        database::over_time_history_db::{
+            align_from_until, get_blocked_intervals, get_total_intervals,
+        },
+        domain_details::{check_privacy_domain_details, DomainDetailsReply, DomainOverTimeItem},
+        top_clients::TopClientItemReply,
+    },
+    services::lookup::LookupRepository,
+    settings::ValueType,
+    util::{Error, ErrorKind},
+};
+use diesel::{dsl::sql, prelude::*, sql_types::BigInt, sqlite::SqliteConnection};
+use failure::ResultExt;
+use std::collections::HashMap;
+
+/// Get the details of a domain from the database. The time range is aligned
+/// with the interval. The regex match status is not stored in the database.
+pub fn get_domain_details_db(
+    env: &Env,
+    db: &SqliteConnection,
+    lookup: &dyn LookupRepository,
+    input_domain: &str,
+    params: &DetailsParams,
+) -> Result<DomainDetailsReply, Error> {
+    use crate::databases::ftl::queries::dsl::*;
+
+    check_privacy_domain_details(env, input_domain)?;
+    let interval = params.interval()?;
+    let limit = params.limit.unwrap_or(10);
+
+    // Both timestamps are required to use the database
+    let (from, until) = match (params.from, params.until) {
+        (Some(from), Some(until)) => align_from_until(from, until, interval as u64)?,
+        _ => return Err(Error::from(ErrorKind::BadRequest)),
+    };
+
+    // Only consider the domain's queries in the time interval
+    let domain_queries = || {
+        queries
+            .filter(domain.eq(input_domain))
+            .filter(timestamp.ge(from as i32))
+            .filter(timestamp.lt(until as i32))
+    };
+
+    let total_queries: i64 = domain_queries()
+        .count()
+        .get_result(db)
+        .context(ErrorKind::FtlDatabase)?;
+
+    // Domains without queries are not known to the database
+    if total_queries == 0 {
+        return Err(Error::from(ErrorKind::NotFound));
+    }
+
+    let blocked_queries: i64 = domain_queries()
+        .filter(status.eq_any(&BLOCKED_STATUSES))
+        .count()
+        .get_result(db)
+        .context(ErrorKind::FtlDatabase)?;
+
+    let total_over_time = get_total_intervals(from, until, interval, Some(input_domain), db)?;
+    let blocked_over_time = get_blocked_intervals(from, until, interval, Some(input_domain), db)?;
+
+    // Fill in the intervals without queries
+    let over_time = (from..until)
+        .step_by(interval)
+        .map(|time| {
+            let count = |counts: &HashMap<i32, i64>| {
+                counts.get(&(time as i32)).copied().unwrap_or_default() as usize
+            };
+
+            DomainOverTimeItem {
+                // Display the timestamps as centered in the overTime slot
+                // interval
+                timestamp: time + (interval / 2) as u64,
+                total_queries: count(&total_over_time),
+                blocked_queries: count(&blocked_over_time),
+            }
+        })
+        .collect();
+
+    let clients = domain_queries()
+        .select((client, sql::<BigInt>("COUNT(*)")))
+        .filter(client.ne(HIDDEN_CLIENT))
+        .group_by(client)
+        .order((sql::<BigInt>("COUNT(*)").desc(), client))
+        .limit(limit as i64)
+        .load::<(String, i64)>(db)
+        .context(ErrorKind::FtlDatabase)?
+        .into_iter()
+        .map(|(client_identifier, count)| {
+            if ValueType::IPv4.is_valid(&client_identifier)
+                || ValueType::IPv6.is_valid(&client_identifier)
+            {
+                // If the identifier is an IP address, use it as the client IP
+                TopClientItemReply {
+                    name: "".to_owned(),
+                    ip: client_identifier,
+                    count: count as usize,
+                }
+            } else {
+                // If the identifier is not an IP address, use it as the name
+                TopClientItemReply {
+                    name: client_identifier,
+                    ip: "".to_owned(),
+                    count: count as usize,
+                }
+            }
+        })
+        .collect();
+
+    Ok(DomainDetailsReply {
+        domain: input_domain.to_owned(),
+        total_queries: total_queries as usize,
+        blocked_queries: blocked_queries as usize,
+        permitted_queries: (total_queries - blocked_queries) as usize,
+        regex_match: None,
+        over_time,
+        clients,
+        lists: lookup.lookup(input_domain)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::get_domain_details_db;
+    use crate::{
+        databases::ftl::connect_to_ftl_test_db,
+        env::{Env, PiholeFile},
+        routes::stats::{
+            client_details::DetailsParams,
+            domain_details::{DomainDetailsReply, DomainOverTimeItem},
+            top_clients::TopClientItemReply,
+        },
+        services::lookup::{DomainLookup, GravityMatch, MockLookupRepository},
+        testing::TestEnvBuilder,
+        util::ErrorKind,
+    };
+    use mockall::predicate::*;
+
+    const PARAMS: DetailsParams = DetailsParams {
+        from: Some(164_400),
+        until: Some(165_599),
+        interval: None,
+        limit: None,
+    };
+
+    fn env(ftl_config: &str) -> Env {
+        TestEnvBuilder::new()
+            .file(PiholeFile::FtlConfig, ftl_config)
+            .build()
+    }
+
+    fn lookup(domain: &str) -> DomainLookup {
+        DomainLookup::new(
+            domain.to_owned(),
+            None,
+            None,
+            Vec::new(),
+            GravityMatch::default(),
+            &[],
+        )
+    }
+
+    /// The domain's details are calculated from the queries in the interval
+    #[test]
+    fn domain_details() {
+        let expected = DomainDetailsReply {
+            domain: "github.com".to_owned(),
+            total_queries: 12,
+            blocked_queries: 0,
+            permitted_queries: 12,
+            regex_match: None,
+            over_time: vec![
+                DomainOverTimeItem {
+                    timestamp: 164_700,
+                    total_queries: 8,
+                    blocked_queries: 0,
+                },
+                DomainOverTimeItem {
+                    timestamp: 165_300,
+                    total_queries: 4,
+                    blocked_queries: 0,
+                },
+            ],
+            clients: vec![TopClientItemReply {
+                name: "".to_owned(),
+                ip: "127.0.0.1".to_owned(),
+                count: 12,
+            }],
+            lists: lookup("github.com"),
+        };
+
+        let mut repo = MockLookupRepository::new();
+        repo.expect_lookup()
+            .with(eq("github.com"))
+            .return_const(Ok(lookup("github.com")));
+
+        let db = connect_to_ftl_test_db();
+        let actual = get_domain_details_db(&env(""), &db, &repo, "github.com", &PARAMS).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    /// An interval of zero is rejected
+    #[test]
+    fn zero_interval() {
+        let db = connect_to_ftl_test_db();
+        let error = get_domain_details_db(
+            &env(""),
+            &db,
+            &MockLookupRepository::new(),
+            "github.com",
+            &DetailsParams {
+                interval: Some(0),
+                ..PARAMS
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::BadRequest);
+    }
+
+    /// Domains without queries in the interval are not found
+    #[test]
+    fn not_found() {
+        let db = connect_to_ftl_test_db();
+        let error = get_domain_details_db(
+            &env(""),
+            &db,
+            &MockLookupRepository::new(),
+            "example.com",
+            &PARAMS,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    /// Domains are not found if the privacy level hides domains
+    #[test]
+    fn privacy_hide_domains() {
+        let db = connect_to_ftl_test_db();
+        let error = get_domain_details_db(
+            &env("PRIVACYLEVEL=1"),
+            &db,
+            &MockLookupRepository::new(),
+            "github.com",
+            &PARAMS,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
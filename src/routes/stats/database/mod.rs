@@ -8,6 +8,8 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+pub mod client_details_db;
+pub mod domain_details_db;
 pub mod over_time_clients_db;
 pub mod over_time_history_db;
 pub mod query_types_db;
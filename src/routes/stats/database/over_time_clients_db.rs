@@ -13,7 +13,7 @@ use crate::{
     env::Env,
     ftl::ClientReply,
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             common::{get_excluded_clients, HIDDEN_CLIENT},
             database::over_time_history_db::align_from_until,
@@ -37,7 +37,7 @@ pub fn over_time_clients_db(
     from: u64,
     until: u64,
     interval: Option<usize>,
-    _auth: User,
+    _auth: CanReadStats,
     db: InjectProvided<PiholeModule, FtlDatabase>,
     env: Inject<PiholeModule, Env>,
 ) -> Reply {
@@ -145,7 +145,7 @@ fn get_client_identifiers(
 }
 
 /// Get the overTime data for the client in the specified interval
-fn get_client_over_time(
+pub fn get_client_over_time(
     from: u64,
     until: u64,
     interval: usize,
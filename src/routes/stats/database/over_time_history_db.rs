@@ -11,11 +11,15 @@
 use crate::{
     databases::ftl::FtlDatabase,
     ftl::BLOCKED_STATUSES,
-    routes::{auth::User, stats::over_time_history::OverTimeItem},
+    routes::{auth::CanReadStats, stats::over_time_history::OverTimeItem},
     services::PiholeModule,
     util::{reply_result, Error, ErrorKind, Reply},
 };
-use diesel::{dsl::sql, prelude::*, sql_types::BigInt};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{BigInt, Integer},
+};
 use failure::ResultExt;
 use shaku_rocket::InjectProvided;
 use std::collections::HashMap;
@@ -29,7 +33,7 @@ pub fn over_time_history_db(
     from: u64,
     until: u64,
     interval: Option<usize>,
-    _auth: User,
+    _auth: CanReadStats,
     db: InjectProvided<PiholeModule, FtlDatabase>,
 ) -> Reply {
     reply_result(over_time_history_db_impl(
@@ -50,8 +54,8 @@ fn over_time_history_db_impl(
     let (from, until) = align_from_until(from, until, interval as u64)?;
 
     // Get the overTime data
-    let total_intervals = get_total_intervals(from, until, interval, db)?;
-    let blocked_intervals = get_blocked_intervals(from, until, interval, db)?;
+    let total_intervals = get_total_intervals(from, until, interval, None, db)?;
+    let blocked_intervals = get_blocked_intervals(from, until, interval, None, db)?;
 
     let mut over_time: Vec<OverTimeItem> = Vec::with_capacity((until - from) as usize / interval);
 
@@ -73,12 +77,12 @@ fn over_time_history_db_impl(
 }
 
 /// Align `from` and `until` with the interval. Also check that the time
-/// interval is increasing from `from` to `until`. If it is not, an error is
-/// returned.
+/// interval is increasing from `from` to `until`, and that the interval is not
+/// zero. If not, an error is returned.
 pub fn align_from_until(from: u64, until: u64, interval: u64) -> Result<(u64, u64), Error> {
     let is_range_increasing = from < until;
 
-    if !is_range_increasing {
+    if !is_range_increasing || interval == 0 {
         // The timestamps should increase from "from" to "until"
         return Err(Error::from(ErrorKind::BadRequest));
     }
@@ -90,17 +94,19 @@ pub fn align_from_until(from: u64, until: u64, interval: u64) -> Result<(u64, u6
     Ok((from, until))
 }
 
-/// Get the over time data for all queries from the database
-fn get_total_intervals(
+/// Get the over time data for all queries from the database, optionally only
+/// for a single domain
+pub fn get_total_intervals(
     from: u64,
     until: u64,
     interval: usize,
+    domain_filter: Option<&str>,
     db: &SqliteConnection,
 ) -> Result<HashMap<i32, i64>, Error> {
     use crate::databases::ftl::queries::dsl::*;
 
     // SQL snippet for calculating the interval timestamp of the query
-    let interval_sql = sql(&format!(
+    let interval_sql = sql::<Integer>(&format!(
         "(timestamp / {interval}) * {interval}",
         interval = interval
     ));
@@ -111,7 +117,12 @@ fn get_total_intervals(
         .filter(status.ne(0))
         .filter(timestamp.ge(from as i32))
         .filter(timestamp.lt(until as i32))
-        .group_by(&interval_sql);
+        .group_by(&interval_sql)
+        .into_boxed();
+    let sql_query = match domain_filter {
+        Some(domain_filter) => sql_query.filter(domain.eq(domain_filter)),
+        None => sql_query,
+    };
 
     // Execute SQL query
     Ok(sql_query
@@ -122,17 +133,19 @@ fn get_total_intervals(
         .collect())
 }
 
-/// Get the over time data for blocked queries from the database
-fn get_blocked_intervals(
+/// Get the over time data for blocked queries from the database, optionally
+/// only for a single domain
+pub fn get_blocked_intervals(
     from: u64,
     until: u64,
     interval: usize,
+    domain_filter: Option<&str>,
     db: &SqliteConnection,
 ) -> Result<HashMap<i32, i64>, Error> {
     use crate::databases::ftl::queries::dsl::*;
 
     // SQL snippet for calculating the interval timestamp of the query
-    let interval_sql = sql(&format!(
+    let interval_sql = sql::<Integer>(&format!(
         "(timestamp / {interval}) * {interval}",
         interval = interval
     ));
@@ -143,7 +156,12 @@ fn get_blocked_intervals(
         .filter(status.eq_any(&BLOCKED_STATUSES))
         .filter(timestamp.ge(from as i32))
         .filter(timestamp.lt(until as i32))
-        .group_by(&interval_sql);
+        .group_by(&interval_sql)
+        .into_boxed();
+    let sql_query = match domain_filter {
+        Some(domain_filter) => sql_query.filter(domain.eq(domain_filter)),
+        None => sql_query,
+    };
 
     // Execute SQL query
     Ok(sql_query
@@ -156,9 +174,12 @@ fn get_blocked_intervals(
 
 #[cfg(test)]
 mod test {
-    use super::{get_blocked_intervals, get_total_intervals, over_time_history_db_impl};
+    use super::{
+        align_from_until, get_blocked_intervals, get_total_intervals, over_time_history_db_impl,
+    };
     use crate::{
         databases::ftl::connect_to_ftl_test_db, routes::stats::over_time_history::OverTimeItem,
+        util::ErrorKind,
     };
     use std::collections::HashMap;
 
@@ -205,7 +226,28 @@ mod test {
         expected.insert(175_800, 3);
 
         let db = connect_to_ftl_test_db();
-        let actual = get_total_intervals(FROM_TIMESTAMP, UNTIL_TIMESTAMP, INTERVAL, &db).unwrap();
+        let actual =
+            get_total_intervals(FROM_TIMESTAMP, UNTIL_TIMESTAMP, INTERVAL, None, &db).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    /// Verify the total intervals can be limited to a single domain
+    #[test]
+    fn total_intervals_domain() {
+        let mut expected = HashMap::new();
+        expected.insert(164_400, 8);
+        expected.insert(165_000, 4);
+
+        let db = connect_to_ftl_test_db();
+        let actual = get_total_intervals(
+            FROM_TIMESTAMP,
+            UNTIL_TIMESTAMP,
+            INTERVAL,
+            Some("github.com"),
+            &db,
+        )
+        .unwrap();
 
         assert_eq!(actual, expected);
     }
@@ -216,8 +258,23 @@ mod test {
         let expected = HashMap::new();
 
         let db = connect_to_ftl_test_db();
-        let actual = get_blocked_intervals(FROM_TIMESTAMP, UNTIL_TIMESTAMP, INTERVAL, &db).unwrap();
+        let actual =
+            get_blocked_intervals(FROM_TIMESTAMP, UNTIL_TIMESTAMP, INTERVAL, None, &db).unwrap();
 
         assert_eq!(actual, expected);
     }
+
+    /// A range which does not increase or an interval of zero is rejected
+    #[test]
+    fn align_invalid() {
+        assert_eq!(align_from_until(100, 700, 600).unwrap(), (0, 1200));
+        assert_eq!(
+            align_from_until(700, 100, 600).unwrap_err().kind(),
+            ErrorKind::BadRequest
+        );
+        assert_eq!(
+            align_from_until(100, 700, 0).unwrap_err().kind(),
+            ErrorKind::BadRequest
+        );
+    }
 }
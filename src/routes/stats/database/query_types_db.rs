@@ -11,7 +11,7 @@
 use crate::{
     databases::ftl::FtlDatabase,
     ftl::FtlQueryType,
-    routes::{auth::User, stats::query_types::QueryTypeReply},
+    routes::{auth::CanReadStats, stats::query_types::QueryTypeReply},
     services::PiholeModule,
     util::{reply_result, Error, ErrorKind, Reply},
 };
@@ -27,7 +27,7 @@ pub use query_types_db as route;
 pub fn query_types_db(
     from: u64,
     until: u64,
-    _auth: User,
+    _auth: CanReadStats,
     db: InjectProvided<PiholeModule, FtlDatabase>,
 ) -> Reply {
     reply_result(query_types_db_impl(from, until, &db as &SqliteConnection))
@@ -13,7 +13,7 @@ use crate::{
     env::Env,
     ftl::{FtlQueryStatus, FtlQueryType, BLOCKED_STATUSES},
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             database::query_types_db::get_query_type_counts,
             summary::{ReplyTypes, Summary, TotalQueries},
@@ -32,7 +32,7 @@ use shaku_rocket::{Inject, InjectProvided};
 pub fn get_summary_db(
     from: u64,
     until: u64,
-    _auth: User,
+    _auth: CanReadStats,
     db: InjectProvided<PiholeModule, FtlDatabase>,
     env: Inject<PiholeModule, Env>,
 ) -> Reply {
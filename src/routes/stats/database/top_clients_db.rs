@@ -13,7 +13,7 @@ use crate::{
     env::Env,
     ftl::BLOCKED_STATUSES,
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             common::{get_excluded_clients, HIDDEN_CLIENT},
             database::{
@@ -38,7 +38,7 @@ pub use top_clients_db as route;
 /// Get the top clients
 #[get("/stats/database/top_clients?<from>&<until>&<params..>")]
 pub fn top_clients_db(
-    _auth: User,
+    _auth: CanReadStats,
     env: Inject<PiholeModule, Env>,
     db: InjectProvided<PiholeModule, FtlDatabase>,
     from: u64,
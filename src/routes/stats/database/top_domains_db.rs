@@ -13,7 +13,7 @@ use crate::{
     env::Env,
     ftl::BLOCKED_STATUSES,
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             common::{get_excluded_domains, HIDDEN_DOMAIN},
             database::{
@@ -37,7 +37,7 @@ pub use top_domains_db as route;
 /// Return the top domains
 #[get("/stats/database/top_domains?<from>&<until>&<params..>")]
 pub fn top_domains_db(
-    _auth: User,
+    _auth: CanReadStats,
     env: Inject<PiholeModule, Env>,
     db: InjectProvided<PiholeModule, FtlDatabase>,
     from: u64,
@@ -12,7 +12,7 @@ use crate::{
     databases::ftl::FtlDatabase,
     ftl::FtlQueryStatus,
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             database::summary_db::{get_blocked_query_count, get_query_status_count},
             upstreams::{UpstreamItemReply, UpstreamsReply},
@@ -33,7 +33,7 @@ pub use upstreams_db as route;
 pub fn upstreams_db(
     from: u64,
     until: u64,
-    _auth: User,
+    _auth: CanReadStats,
     db: InjectProvided<PiholeModule, FtlDatabase>,
 ) -> Reply {
     reply_result(upstreams_db_impl(from, until, &db as &SqliteConnection))
@@ -0,0 +1,503 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Domain Details Endpoint
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::ftl::FtlDatabase,
+    env::Env,
+    ftl::FtlMemory,
+    routes::{
+        auth::CanReadStats,
+        stats::{
+            client_details::DetailsParams,
+            common::{get_current_over_time_slot, HIDDEN_CLIENT, HIDDEN_DOMAIN},
+            database::domain_details_db::get_domain_details_db,
+            top_clients::TopClientItemReply,
+        },
+    },
+    services::{
+        lookup::{DomainLookup, LookupRepository},
+        PiholeModule,
+    },
+    settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
+    util::{reply_result, Error, ErrorKind, Reply},
+};
+use diesel::SqliteConnection;
+use rocket::State;
+use shaku_rocket::{Inject, InjectProvided};
+use std::collections::HashMap;
+
+pub use domain_details as route;
+
+/// Get the details of a domain: its query counts, regex match status, queries
+/// over time, the clients which queried it, and the list entries which affect
+/// it. The details are calculated from the queries in memory (the last 24
+/// hours), or from the database if both `from` and `until` are given. Giving
+/// only one of them is a bad request.
+#[get("/stats/domains/<domain>?<params..>")]
+pub fn domain_details(
+    _auth: CanReadStats,
+    ftl_memory: &State<FtlMemory>,
+    env: Inject<PiholeModule, Env>,
+    db: InjectProvided<PiholeModule, FtlDatabase>,
+    lookup: InjectProvided<PiholeModule, dyn LookupRepository>,
+    domain: String,
+    params: DetailsParams,
+) -> Reply {
+    let domain = domain.to_lowercase();
+
+    if params.from.is_none() && params.until.is_none() {
+        reply_result(get_domain_details(
+            ftl_memory,
+            &env,
+            &*lookup,
+            &domain,
+            params.limit.unwrap_or(10),
+        ))
+    } else {
+        reply_result(get_domain_details_db(
+            &env,
+            &db as &SqliteConnection,
+            &*lookup,
+            &domain,
+            &params,
+        ))
+    }
+}
+
+/// Represents the reply structure for domain details
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct DomainDetailsReply {
+    pub domain: String,
+    pub total_queries: usize,
+    pub blocked_queries: usize,
+    pub permitted_queries: usize,
+    /// The domain's regex match status, if known (only in memory)
+    pub regex_match: Option<u8>,
+    pub over_time: Vec<DomainOverTimeItem>,
+    /// The clients which queried the domain, sorted by count
+    pub clients: Vec<TopClientItemReply>,
+    /// The list entries which affect the domain
+    pub lists: DomainLookup,
+}
+
+/// The number of queries for a domain in an overTime interval
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct DomainOverTimeItem {
+    pub timestamp: u64,
+    pub total_queries: usize,
+    pub blocked_queries: usize,
+}
+
+/// Check if the domain's details can be shared. Hidden domains and domains
+/// hidden by the privacy level are treated as if they do not exist.
+pub fn check_privacy_domain_details(env: &Env, domain: &str) -> Result<(), Error> {
+    if FtlConfEntry::PrivacyLevel.read_as::<FtlPrivacyLevel>(env)? >= FtlPrivacyLevel::HideDomains
+        || domain == HIDDEN_DOMAIN
+    {
+        return Err(Error::from(ErrorKind::NotFound));
+    }
+
+    Ok(())
+}
+
+/// Get the details of a domain from the queries in memory
+fn get_domain_details(
+    ftl_memory: &FtlMemory,
+    env: &Env,
+    lookup: &dyn LookupRepository,
+    domain: &str,
+    limit: usize,
+) -> Result<DomainDetailsReply, Error> {
+    check_privacy_domain_details(env, domain)?;
+
+    let lock = ftl_memory.lock()?;
+    let counters = ftl_memory.counters(&lock)?;
+    let strings = ftl_memory.strings(&lock)?;
+    let clients = ftl_memory.clients(&lock)?;
+    let domains = ftl_memory.domains(&lock)?;
+    let queries = ftl_memory.queries(&lock)?;
+    let over_time = ftl_memory.over_time(&lock)?;
+
+    // Find the domain
+    let (domain_id, ftl_domain) = domains
+        .iter()
+        .take(counters.total_domains as usize)
+        .enumerate()
+        .find(|(_, ftl_domain)| ftl_domain.get_domain(&strings) == domain)
+        .ok_or(ErrorKind::NotFound)?;
+
+    // Count the domain's queries by client and overTime slot, skipping
+    // queries hidden by their privacy level
+    let mut client_counts: HashMap<usize, usize> = HashMap::new();
+    let mut slot_counts: HashMap<usize, (usize, usize)> = HashMap::new();
+
+    for query in queries
+        .iter()
+        .take(counters.total_queries as usize)
+        .filter(|query| query.domain_id as usize == domain_id)
+        .filter(|query| query.privacy_level < FtlPrivacyLevel::HideDomains)
+    {
+        *client_counts.entry(query.client_id as usize).or_default() += 1;
+
+        let slot = slot_counts.entry(query.time_index as usize).or_default();
+        slot.0 += 1;
+
+        if query.is_blocked() {
+            slot.1 += 1;
+        }
+    }
+
+    // Get the domain's overTime data, skipping the slots without any data
+    let over_time: Vec<DomainOverTimeItem> = over_time
+        .iter()
+        .take(get_current_over_time_slot(&over_time) + 1)
+        .enumerate()
+        .skip_while(|(_, time)| time.total_queries <= 0 && time.blocked_queries <= 0)
+        .map(|(i, time)| {
+            let (total_queries, blocked_queries) = slot_counts.get(&i).copied().unwrap_or_default();
+
+            DomainOverTimeItem {
+                timestamp: time.timestamp as u64,
+                total_queries,
+                blocked_queries,
+            }
+        })
+        .collect();
+
+    // Sort the clients by count (descending) and map them into the output
+    // format
+    let mut client_counts: Vec<TopClientItemReply> = client_counts
+        .into_iter()
+        .map(|(client_id, count)| {
+            let client = clients[client_id].as_reply(&strings);
+
+            TopClientItemReply {
+                name: client.name,
+                ip: client.ip,
+                count,
+            }
+        })
+        .filter(|client| client.ip != HIDDEN_CLIENT)
+        .collect();
+
+    client_counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.ip.cmp(&b.ip)));
+    client_counts.truncate(limit);
+
+    let total_queries = ftl_domain.query_count as usize;
+    let blocked_queries = ftl_domain.blocked_count as usize;
+
+    Ok(DomainDetailsReply {
+        domain: domain.to_owned(),
+        total_queries,
+        blocked_queries,
+        permitted_queries: total_queries - blocked_queries,
+        regex_match: Some(ftl_domain.regex_match as u8),
+        over_time,
+        clients: client_counts,
+        lists: lookup.lookup(domain)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        env::PiholeFile,
+        ftl::{
+            FtlClient, FtlCounters, FtlDomain, FtlMemory, FtlOverTime, FtlQuery, FtlQueryStatus,
+            FtlRegexMatch, FtlSettings,
+        },
+        services::lookup::{DomainLookup, GravityMatch, LookupRepository, MockLookupRepository},
+        settings::FtlPrivacyLevel,
+        testing::TestBuilder,
+    };
+    use mockall::predicate::*;
+    use rocket::http::Status;
+    use std::collections::HashMap;
+
+    /// Make a query for the domain by the client in the overTime slot
+    fn query(
+        id: i32,
+        status: FtlQueryStatus,
+        domain_id: i32,
+        client_id: i32,
+        time_index: u32,
+        privacy_level: FtlPrivacyLevel,
+    ) -> FtlQuery {
+        FtlQuery {
+            id,
+            timestamp: time_index as libc::time_t,
+            time_index,
+            domain_id,
+            client_id,
+            status,
+            privacy_level,
+            ..FtlQuery::default()
+        }
+    }
+
+    /// Three clients and two domains. The first domain was queried four times
+    /// by the first two clients, and one of the queries was blocked (by a
+    /// regex). The second domain is hidden and was queried by the hidden
+    /// client.
+    fn test_data() -> FtlMemory {
+        let mut strings = HashMap::new();
+        strings.insert(1, "10.1.1.1".to_owned());
+        strings.insert(2, "client1".to_owned());
+        strings.insert(3, "10.1.1.2".to_owned());
+        strings.insert(4, "0.0.0.0".to_owned());
+        strings.insert(5, "example.com".to_owned());
+        strings.insert(6, "hidden".to_owned());
+
+        FtlMemory::Test {
+            clients: vec![
+                FtlClient::new(3, 1, 1, Some(2)),
+                FtlClient::new(1, 0, 3, None),
+                FtlClient::new(1, 0, 4, None),
+            ],
+            domains: vec![
+                FtlDomain::new(4, 1, 5, FtlRegexMatch::Blocked),
+                FtlDomain::new(1, 0, 6, FtlRegexMatch::Unknown),
+            ],
+            over_time: vec![
+                FtlOverTime::new(0, 0, 0, 0, 0, [0; 7]),
+                FtlOverTime::new(1, 3, 1, 0, 2, [0; 7]),
+                FtlOverTime::new(2, 2, 0, 0, 2, [0; 7]),
+            ],
+            strings,
+            upstreams: Vec::new(),
+            queries: vec![
+                query(
+                    1,
+                    FtlQueryStatus::Forward,
+                    0,
+                    0,
+                    1,
+                    FtlPrivacyLevel::ShowAll,
+                ),
+                query(
+                    2,
+                    FtlQueryStatus::Wildcard,
+                    0,
+                    0,
+                    1,
+                    FtlPrivacyLevel::ShowAll,
+                ),
+                query(3, FtlQueryStatus::Cache, 0, 1, 1, FtlPrivacyLevel::ShowAll),
+                query(
+                    4,
+                    FtlQueryStatus::Forward,
+                    0,
+                    0,
+                    2,
+                    FtlPrivacyLevel::ShowAll,
+                ),
+                query(
+                    5,
+                    FtlQueryStatus::Forward,
+                    1,
+                    2,
+                    2,
+                    FtlPrivacyLevel::HideDomainsAndClients,
+                ),
+            ],
+            counters: FtlCounters {
+                total_queries: 5,
+                total_clients: 3,
+                total_domains: 2,
+                ..FtlCounters::default()
+            },
+            settings: FtlSettings::default(),
+        }
+    }
+
+    /// A lookup repository which finds nothing for the domain
+    fn lookup_repo(domain: &'static str) -> MockLookupRepository {
+        let mut repo = MockLookupRepository::new();
+
+        repo.expect_lookup()
+            .with(eq(domain))
+            .return_const(Ok(DomainLookup::new(
+                domain.to_owned(),
+                None,
+                None,
+                Vec::new(),
+                GravityMatch::default(),
+                &[],
+            )));
+
+        repo
+    }
+
+    /// The domain's counts, regex match status, overTime data, clients, and
+    /// list entries are returned
+    #[test]
+    fn domain_details() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/Example.com")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(lookup_repo("example.com")))
+            }))
+            .expect_json(json!({
+                "domain": "example.com",
+                "total_queries": 4,
+                "blocked_queries": 1,
+                "permitted_queries": 3,
+                "regex_match": 1,
+                "over_time": [
+                    { "timestamp": 1, "total_queries": 3, "blocked_queries": 1 },
+                    { "timestamp": 2, "total_queries": 1, "blocked_queries": 0 }
+                ],
+                "clients": [
+                    { "name": "client1", "ip": "10.1.1.1", "count": 3 },
+                    { "name": "", "ip": "10.1.1.2", "count": 1 }
+                ],
+                "lists": {
+                    "domain": "example.com",
+                    "whitelist": null,
+                    "blacklist": null,
+                    "regex": [],
+                    "gravity": {
+                        "found": false,
+                        "adlists": []
+                    },
+                    "blocked": false,
+                    "verdict": "not_listed"
+                }
+            }))
+            .test();
+    }
+
+    /// The limit applies to the clients
+    #[test]
+    fn limit() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/example.com?limit=1")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(lookup_repo("example.com")))
+            }))
+            .expect_json(json!({
+                "domain": "example.com",
+                "total_queries": 4,
+                "blocked_queries": 1,
+                "permitted_queries": 3,
+                "regex_match": 1,
+                "over_time": [
+                    { "timestamp": 1, "total_queries": 3, "blocked_queries": 1 },
+                    { "timestamp": 2, "total_queries": 1, "blocked_queries": 0 }
+                ],
+                "clients": [
+                    { "name": "client1", "ip": "10.1.1.1", "count": 3 }
+                ],
+                "lists": {
+                    "domain": "example.com",
+                    "whitelist": null,
+                    "blacklist": null,
+                    "regex": [],
+                    "gravity": {
+                        "found": false,
+                        "adlists": []
+                    },
+                    "blocked": false,
+                    "verdict": "not_listed"
+                }
+            }))
+            .test();
+    }
+
+    /// The hidden domain is not found
+    #[test]
+    fn hidden_domain() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/hidden")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(MockLookupRepository::new()))
+            }))
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Domains are not found if the privacy level hides domains
+    #[test]
+    fn privacy_hide_domains() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/example.com")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "PRIVACYLEVEL=1")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(MockLookupRepository::new()))
+            }))
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Unknown domains are not found
+    #[test]
+    fn not_found() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/example.org")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(MockLookupRepository::new()))
+            }))
+            .expect_status(Status::NotFound)
+            .expect_json(json!({
+                "error": {
+                    "key": "not_found",
+                    "message": "Not found",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+
+    /// Both timestamps are required to use the database
+    #[test]
+    fn until_without_from() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/domains/example.com?until=1")
+            .ftl_memory(test_data())
+            .file(PiholeFile::FtlConfig, "")
+            .mock_provider::<dyn LookupRepository>(Box::new(|_| {
+                Ok(Box::new(MockLookupRepository::new()))
+            }))
+            .expect_status(Status::BadRequest)
+            .expect_json(json!({
+                "error": {
+                    "key": "bad_request",
+                    "message": "Bad request",
+                    "data": null
+                }
+            }))
+            .test();
+    }
+}
@@ -22,7 +22,8 @@ use diesel::{prelude::*, sqlite::Sqlite};
 use failure::ResultExt;
 
 /// Load queries from the database according to the parameters. A cursor is
-/// also returned, if more queries can be loaded.
+/// also returned, if more queries can be loaded. The database does not store
+/// response times, so no queries are loaded when filtering by response time.
 ///
 /// # Arguments:
 /// - `db`: A connection to the FTL database
@@ -40,6 +41,10 @@ pub fn load_queries_from_database(
     // Use the Diesel DSL of this table for easy querying
     use crate::databases::ftl::queries::dsl::*;
 
+    if filters_response_time(params) {
+        return Ok((Vec::new(), None));
+    }
+
     // Start creating the database query
     let db_query = queries
         // The query must be boxed, because we are dynamically building it
@@ -55,9 +60,9 @@ pub fn load_queries_from_database(
     // Apply filters
     let db_query = filter_time_from_db(db_query, params);
     let db_query = filter_time_until_db(db_query, params);
-    let db_query = filter_domain_db(db_query, params);
-    let db_query = filter_client_db(db_query, params);
-    let db_query = filter_upstream_db(db_query, params);
+    let db_query = filter_domain_db(db_query, params, db)?;
+    let db_query = filter_client_db(db_query, params, db)?;
+    let db_query = filter_upstream_db(db_query, params, db)?;
     let db_query = filter_query_type_db(db_query, params);
     let db_query = filter_status_db(db_query, params);
     let db_query = filter_blocked_db(db_query, params);
@@ -12,7 +12,10 @@ use crate::{
     databases::ftl::FtlDatabase,
     env::Env,
     ftl::{FtlDnssecType, FtlMemory, FtlQueryReplyType, FtlQueryStatus, FtlQueryType},
-    routes::{auth::User, stats::history::get_history::get_history},
+    routes::{
+        auth::CanReadStats,
+        stats::history::{get_history::get_history, pattern::Pattern},
+    },
     services::PiholeModule,
     util::{reply_result, Error, ErrorKind, Reply},
 };
@@ -30,7 +33,7 @@ pub use history as route;
 /// Get the query history according to the specified parameters
 #[get("/stats/history?<params..>")]
 pub fn history(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
     params: HistoryParams,
@@ -57,22 +60,59 @@ pub struct QueryReply {
     pub dnssec: u8,
     pub reply: u8,
     pub response_time: u32,
+    /// Only included when the `details` parameter is true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<QueryDetails>,
 }
 
-/// Represents the possible GET parameters on `/stats/history`
+/// Extra information about a query, which is not included by default to keep
+/// the history small. Database queries only have the client IP, upstream IP,
+/// and database ID.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct QueryDetails {
+    pub client_ip: String,
+    pub client_name: Option<String>,
+    /// The upstream the query was forwarded to, if it was forwarded
+    pub upstream: Option<UpstreamReply>,
+    /// If the domain is blocked by a regex filter (`FtlRegexMatch`)
+    pub regex_match: Option<u8>,
+    pub db_id: Option<i64>,
+}
+
+/// The upstream a query was forwarded to
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct UpstreamReply {
+    pub ip: String,
+    pub name: Option<String>,
+}
+
+/// Represents the possible GET parameters on `/stats/history`. The domain,
+/// client, and upstream filters are patterns (see `Pattern`), and the client,
+/// query type, and status filters accept comma separated lists. Response times
+/// are in tenths of a millisecond, like in `QueryReply`. `slowest` returns the
+/// slowest answered queries first, without a cursor.
+///
+/// The database does not store response times, so only in-memory queries
+/// (from the last 24 hours) are returned when filtering or sorting by response
+/// time. Database queries are returned with a response time of 0 otherwise.
+/// `details` adds the fields of `QueryDetails` to each query.
 #[derive(FromForm)]
 pub struct HistoryParams {
     pub cursor: Option<HistoryCursor>,
     pub from: Option<u64>,
     pub until: Option<u64>,
-    pub domain: Option<String>,
-    pub client: Option<String>,
-    pub upstream: Option<String>,
-    pub query_type: Option<FtlQueryType>,
-    pub status: Option<FtlQueryStatus>,
+    pub domain: Option<Pattern>,
+    pub client: Option<ValueList<Pattern>>,
+    pub upstream: Option<Pattern>,
+    pub query_type: Option<ValueList<FtlQueryType>>,
+    pub status: Option<ValueList<FtlQueryStatus>>,
     pub blocked: Option<bool>,
     pub dnssec: Option<FtlDnssecType>,
     pub reply: Option<FtlQueryReplyType>,
+    pub min_response_time: Option<u32>,
+    pub max_response_time: Option<u32>,
+    pub slowest: Option<bool>,
+    pub details: Option<bool>,
     pub limit: Option<usize>,
 }
 
@@ -90,6 +130,10 @@ impl Default for HistoryParams {
             blocked: None,
             dnssec: None,
             reply: None,
+            min_response_time: None,
+            max_response_time: None,
+            slowest: None,
+            details: None,
             limit: Some(100),
         }
     }
@@ -127,3 +171,36 @@ impl<'v> FromFormField<'v> for HistoryCursor {
         Ok(cursor)
     }
 }
+
+/// A comma separated list of values, which filters match if any value matches.
+/// Regular expressions (values starting with `re:`) may contain commas, so a
+/// regular expression extends to the end of the list.
+#[derive(Clone)]
+pub struct ValueList<T>(pub Vec<T>);
+
+impl<'v, T: FromFormField<'v>> FromFormField<'v> for ValueList<T> {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        let mut values = Vec::new();
+        let mut rest = field.value.trim_start();
+
+        loop {
+            let (value, next) = if rest.starts_with("re:") {
+                (rest, None)
+            } else {
+                match rest.split_once(',') {
+                    Some((value, next)) => (value, Some(next)),
+                    None => (rest, None),
+                }
+            };
+
+            values.push(T::from_value(ValueField::from_value(value.trim()))?);
+
+            match next {
+                Some(next) => rest = next.trim_start(),
+                None => break,
+            }
+        }
+
+        Ok(ValueList(values))
+    }
+}
@@ -0,0 +1,441 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// History Export Endpoint
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::ftl::FtlDatabase,
+    env::Env,
+    ftl::{FtlMemory, FtlQuery},
+    routes::{
+        auth::CanReadStats,
+        stats::history::{
+            database::load_queries_from_database,
+            get_history::{filter_queries, is_within_24_hours},
+            map_query_to_json::{map_db_query_to_json, map_query_to_json},
+            HistoryParams, QueryReply,
+        },
+    },
+    services::PiholeModule,
+    settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
+    util::{Error, ErrorKind},
+};
+use rocket::{http::ContentType, response::stream::TextStream, State};
+use shaku::{HasComponent, HasProvider};
+
+/// How many queries are loaded at a time
+const BATCH_SIZE: usize = 1000;
+
+/// The header of CSV exports
+const CSV_HEADER: &str = "timestamp,type,status,domain,client,dnssec,reply,response_time\n";
+
+/// The formats the history can be exported in
+#[derive(FromFormField, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HistoryExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl HistoryExportFormat {
+    /// Get the content type of the format
+    fn content_type(self) -> ContentType {
+        match self {
+            HistoryExportFormat::Csv => ContentType::CSV,
+            HistoryExportFormat::Ndjson => ContentType::new("application", "x-ndjson"),
+        }
+    }
+
+    /// Write the queries in the format, one per line
+    fn write(self, queries: &[QueryReply]) -> String {
+        let mut output = String::new();
+
+        for query in queries {
+            match self {
+                HistoryExportFormat::Csv => {
+                    output.push_str(&format!(
+                        "{},{},{},{},{},{},{},{}\n",
+                        query.timestamp,
+                        query.r#type,
+                        query.status,
+                        csv_field(&query.domain),
+                        csv_field(&query.client),
+                        query.dnssec,
+                        query.reply,
+                        query.response_time
+                    ));
+                }
+                HistoryExportFormat::Ndjson => {
+                    output.push_str(&serde_json::to_string(query).unwrap());
+                    output.push('\n');
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Write an error which ended the export early. It is the last line of
+    /// the export, so clients can tell that the export is incomplete. In CSV
+    /// exports, the first field is `error` instead of a timestamp.
+    fn write_error(self, error: &Error) -> String {
+        match self {
+            HistoryExportFormat::Csv => {
+                format!("error,{},{}\n", error.key(), csv_field(&error.to_string()))
+            }
+            HistoryExportFormat::Ndjson => format!(
+                "{}\n",
+                json!({
+                    "error": {
+                        "key": error.key(),
+                        "message": error.to_string()
+                    }
+                })
+            ),
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a comma, quote, or line break
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Export every query matching the filters as CSV or newline-delimited JSON,
+/// most recent first. The same filters as `/stats/history` are supported,
+/// except for the cursor, limit, and `slowest`. Query details are only included
+/// in NDJSON exports. The queries are loaded from memory and then the database
+/// in batches, and each batch is sent as soon as it is loaded. If a batch can
+/// not be loaded, the export ends with an error line.
+#[get("/stats/history/export?<format>&<params..>")]
+pub fn history_export<'r>(
+    _auth: CanReadStats,
+    ftl_memory: &'r State<FtlMemory>,
+    module: &'r State<Box<PiholeModule>>,
+    format: HistoryExportFormat,
+    params: HistoryParams,
+) -> Result<(ContentType, TextStream![String + 'r]), Error> {
+    let ftl_memory: &FtlMemory = ftl_memory;
+    let module: &PiholeModule = module;
+    let env: &Env = module.resolve_ref();
+    let db: Box<FtlDatabase> = module
+        .provide()
+        .map_err(|_| Error::from(ErrorKind::FtlDatabase))?;
+
+    // Check if query details are private
+    let private =
+        FtlConfEntry::PrivacyLevel.read_as::<FtlPrivacyLevel>(env)? >= FtlPrivacyLevel::Maximum;
+
+    Ok((
+        format.content_type(),
+        TextStream! {
+            if format == HistoryExportFormat::Csv {
+                yield CSV_HEADER.to_owned();
+            }
+
+            if !private {
+                let mut exporter = HistoryExporter::new(params, BATCH_SIZE);
+
+                loop {
+                    match exporter.next_batch(ftl_memory, env, &db) {
+                        Ok(Some(queries)) => yield format.write(&queries),
+                        Ok(None) => break,
+                        Err(error) => {
+                            // The response has already started, so the error
+                            // is reported in the last line instead of the
+                            // status
+                            error.print_stacktrace();
+                            yield format.write_error(&error);
+                            break;
+                        }
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// Where the next batch of queries comes from
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum ExportStage {
+    /// In-memory queries older than the query with this ID, or the most
+    /// recent queries if `None`
+    Memory(Option<i32>),
+    /// Database queries with an ID less than or equal to this ID, or the most
+    /// recent queries if `None`
+    Database(Option<i64>),
+    Done,
+}
+
+/// Loads the queries matching the filters in batches, so that only one batch
+/// is kept in memory at a time
+pub struct HistoryExporter {
+    params: HistoryParams,
+    batch_size: usize,
+    stage: ExportStage,
+    /// The oldest database ID of the in-memory queries. Older queries are
+    /// loaded from the database.
+    oldest_db_id: Option<i64>,
+}
+
+impl HistoryExporter {
+    pub fn new(params: HistoryParams, batch_size: usize) -> Self {
+        HistoryExporter {
+            params,
+            batch_size,
+            stage: ExportStage::Memory(None),
+            oldest_db_id: None,
+        }
+    }
+
+    /// Load the next batch of queries, or `None` if all of the queries have
+    /// been loaded. Batches can be empty.
+    pub fn next_batch(
+        &mut self,
+        ftl_memory: &FtlMemory,
+        env: &Env,
+        db: &FtlDatabase,
+    ) -> Result<Option<Vec<QueryReply>>, Error> {
+        match self.stage {
+            ExportStage::Memory(before_id) => {
+                let queries = self.load_memory_batch(ftl_memory, env, before_id)?;
+
+                self.stage = if queries.len() == self.batch_size {
+                    ExportStage::Memory(queries.last().map(|query| query.id))
+                } else if is_within_24_hours(self.params.from, self.params.until) {
+                    // All of the queries are in memory
+                    ExportStage::Done
+                } else {
+                    // Continue with the queries which are older than the
+                    // in-memory queries
+                    ExportStage::Database(self.oldest_db_id.map(|id| id - 1))
+                };
+
+                Ok(Some(queries.into_iter().map(|query| query.reply).collect()))
+            }
+            ExportStage::Database(start_id) => {
+                let (queries, cursor) =
+                    load_queries_from_database(db, start_id, &self.params, env, self.batch_size)?;
+
+                self.stage = match cursor {
+                    Some(cursor) => ExportStage::Database(cursor.db_id),
+                    None => ExportStage::Done,
+                };
+
+                Ok(Some(
+                    queries
+                        .into_iter()
+                        .map(map_db_query_to_json(self.params.details.unwrap_or(false)))
+                        .collect(),
+                ))
+            }
+            ExportStage::Done => Ok(None),
+        }
+    }
+
+    /// Load a batch of in-memory queries older than the query with the ID
+    /// `before_id`, most recent first
+    fn load_memory_batch(
+        &mut self,
+        ftl_memory: &FtlMemory,
+        env: &Env,
+        before_id: Option<i32>,
+    ) -> Result<Vec<MemoryQuery>, Error> {
+        let lock = ftl_memory.lock()?;
+        let counters = ftl_memory.counters(&lock)?;
+        let queries = ftl_memory.queries(&lock)?;
+
+        // Skip the uninitialized queries (FTL allocates more than it uses)
+        let queries: &[FtlQuery] = &queries[..queries.len().min(counters.total_queries as usize)];
+
+        if before_id.is_none() {
+            self.oldest_db_id = queries
+                .iter()
+                .map(|query| query.database_id)
+                .filter(|&id| id != 0)
+                .min();
+        }
+
+        // The queries are stored oldest first, in order of their IDs
+        let end = match before_id {
+            Some(before_id) => queries.partition_point(|query| query.id < before_id),
+            None => queries.len(),
+        };
+        let queries_iter = Box::new(queries[..end].iter().rev());
+
+        let queries_iter = filter_queries(queries_iter, &self.params, env, ftl_memory, &lock)?;
+        let map_to_json =
+            map_query_to_json(ftl_memory, &lock, self.params.details.unwrap_or(false))?;
+
+        Ok(queries_iter
+            .take(self.batch_size)
+            .map(|query| MemoryQuery {
+                id: query.id,
+                reply: map_to_json(query),
+            })
+            .collect())
+    }
+}
+
+/// An in-memory query, along with its ID for finding the next batch
+struct MemoryQuery {
+    id: i32,
+    reply: QueryReply,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{csv_field, HistoryExportFormat, HistoryExporter, CSV_HEADER};
+    use crate::{
+        databases::ftl::connect_to_ftl_test_db,
+        env::{Env, PiholeFile},
+        ftl::ShmLockGuard,
+        routes::stats::history::{
+            map_query_to_json::map_query_to_json,
+            testing::{test_memory, test_queries},
+            HistoryParams, QueryReply,
+        },
+        testing::{TestBuilder, TestEnvBuilder},
+        util::{Error, ErrorKind},
+    };
+
+    fn env() -> Env {
+        TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, "")
+            .build()
+    }
+
+    /// Map the test queries with the given IDs to their replies, most recent
+    /// first
+    fn expected(ids: &[i32]) -> Vec<QueryReply> {
+        let ftl_memory = test_memory();
+
+        test_queries()
+            .iter()
+            .rev()
+            .filter(|query| ids.contains(&query.id))
+            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap())
+            .collect()
+    }
+
+    /// Run the exporter until it is done, and get all of the batches
+    fn export(params: HistoryParams, batch_size: usize) -> Vec<Vec<QueryReply>> {
+        let ftl_memory = test_memory();
+        let env = env();
+        let db = connect_to_ftl_test_db();
+        let mut exporter = HistoryExporter::new(params, batch_size);
+        let mut batches = Vec::new();
+
+        while let Some(batch) = exporter.next_batch(&ftl_memory, &env, &db).unwrap() {
+            batches.push(batch);
+        }
+
+        batches
+    }
+
+    /// Every in-memory query is exported in batches, followed by the older
+    /// queries in the database, without duplicates
+    #[test]
+    fn memory_and_database() {
+        let batches = export(HistoryParams::default(), 3);
+        let queries: Vec<QueryReply> = batches.into_iter().flatten().collect();
+
+        // The private query is not exported
+        assert_eq!(queries[..8], expected(&[1, 2, 3, 4, 5, 6, 7, 8])[..]);
+
+        // The database IDs of the in-memory queries start at 95
+        assert_eq!(queries.len(), 8 + 94);
+        assert!(queries[8..].iter().all(|query| query.timestamp <= 177_180));
+    }
+
+    /// The filters are applied to the in-memory and database queries
+    #[test]
+    fn filters() {
+        let params = HistoryParams {
+            blocked: Some(true),
+            ..HistoryParams::default()
+        };
+        let queries: Vec<QueryReply> = export(params, 2).into_iter().flatten().collect();
+
+        assert_eq!(queries[..4], expected(&[4, 6, 7, 8])[..]);
+        assert!(queries[4..].iter().all(|query| query.timestamp <= 177_180));
+    }
+
+    /// CSV fields with commas or quotes are quoted
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("example.com"), "example.com");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    /// An error which ends the export is written as a final line which can
+    /// not be mistaken for a query
+    #[test]
+    fn export_error() {
+        let error = Error::from(ErrorKind::FtlDatabase);
+
+        assert_eq!(
+            HistoryExportFormat::Csv.write_error(&error),
+            format!("error,{},{}\n", error.key(), error)
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &HistoryExportFormat::Ndjson.write_error(&error)
+            )
+            .unwrap(),
+            json!({
+                "error": {
+                    "key": error.key(),
+                    "message": error.to_string()
+                }
+            })
+        );
+    }
+
+    /// The queries are exported as CSV with a header
+    #[test]
+    fn export_csv() {
+        let expected = format!(
+            "{}{}",
+            CSV_HEADER,
+            HistoryExportFormat::Csv.write(&expected(&[6, 7, 8]))
+        );
+
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/history/export?format=csv&from=263585")
+            .ftl_memory(test_memory())
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, "")
+            .need_database(true)
+            .expect_raw(&expected)
+            .test();
+    }
+
+    /// The queries are exported as newline-delimited JSON
+    #[test]
+    fn export_ndjson() {
+        let queries = expected(&[8]);
+        let expected = format!("{}\n", serde_json::to_string(&queries[0]).unwrap());
+
+        assert_eq!(HistoryExportFormat::Ndjson.write(&queries), expected);
+
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/history/export?format=ndjson&from=263586")
+            .ftl_memory(test_memory())
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, "")
+            .need_database(true)
+            .expect_raw(&expected)
+            .test();
+    }
+}
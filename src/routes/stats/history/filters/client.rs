@@ -9,15 +9,15 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::ftl::queries,
+    databases::ftl::{queries, FtlDatabase},
     ftl::{FtlMemory, FtlQuery, ShmLockGuard},
-    routes::stats::history::endpoints::HistoryParams,
+    routes::stats::history::{endpoints::HistoryParams, pattern::filter_patterns_db},
     util::Error,
 };
-use diesel::{prelude::*, sqlite::Sqlite};
+use diesel::sqlite::Sqlite;
 use std::{collections::HashSet, iter};
 
-/// Only show queries of the specified client
+/// Only show queries of clients matching any of the client patterns
 pub fn filter_client<'a>(
     queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
     params: &HistoryParams,
@@ -38,7 +38,11 @@ pub fn filter_client<'a>(
                 let ip = client.get_ip(&strings);
                 let name = client.get_name(&strings).unwrap_or_default();
 
-                if ip.contains(client_filter) || name.contains(client_filter) {
+                if client_filter
+                    .0
+                    .iter()
+                    .any(|pattern| pattern.is_match(ip) || pattern.is_match(name))
+                {
                     Some(i)
                 } else {
                     None
@@ -58,18 +62,17 @@ pub fn filter_client<'a>(
     }
 }
 
-/// Only show queries of the specified client in database results
+/// Only show queries of clients matching any of the client patterns in
+/// database results
 pub fn filter_client_db<'a>(
     db_query: queries::BoxedQuery<'a, Sqlite>,
     params: &HistoryParams,
-) -> queries::BoxedQuery<'a, Sqlite> {
-    // Use the Diesel DSL of this table for easy querying
-    use self::queries::dsl::*;
-
+    db: &FtlDatabase,
+) -> Result<queries::BoxedQuery<'a, Sqlite>, Error> {
     if let Some(ref search_client) = params.client {
-        db_query.filter(client.like(format!("%{}%", search_client)))
+        filter_patterns_db(db_query, db, queries::client, &search_client.0)
     } else {
-        db_query
+        Ok(db_query)
     }
 }
 
@@ -81,11 +84,22 @@ mod test {
         ftl::{FtlQuery, ShmLockGuard},
         routes::stats::history::{
             database::execute_query,
-            endpoints::HistoryParams,
+            endpoints::{HistoryParams, ValueList},
+            pattern::Pattern,
             testing::{test_memory, test_queries},
         },
     };
     use diesel::prelude::*;
+    use rocket::form::{FromFormField, ValueField};
+
+    fn clients(patterns: &[&str]) -> ValueList<Pattern> {
+        ValueList(
+            patterns
+                .iter()
+                .map(|pattern| Pattern::parse(pattern).unwrap())
+                .collect(),
+        )
+    }
 
     /// Only return queries from the specified client IP
     #[test]
@@ -95,7 +109,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_client(
             Box::new(queries.iter()),
             &HistoryParams {
-                client: Some("192.168.1.10".to_owned()),
+                client: Some(clients(&["192.168.1.10"])),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -116,7 +130,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_client(
             Box::new(queries.iter()),
             &HistoryParams {
-                client: Some(".10".to_owned()),
+                client: Some(clients(&[".10"])),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -136,7 +150,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_client(
             Box::new(queries.iter()),
             &HistoryParams {
-                client: Some("client1".to_owned()),
+                client: Some(clients(&["client1"])),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -157,7 +171,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_client(
             Box::new(queries.iter()),
             &HistoryParams {
-                client: Some("t1".to_owned()),
+                client: Some(clients(&["t1"])),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -169,6 +183,44 @@ mod test {
         assert_eq!(filtered_queries, expected_queries);
     }
 
+    /// Only return queries from any of the clients in the list
+    #[test]
+    fn list() {
+        let queries = test_queries();
+        let expected_queries = vec![
+            &queries[0],
+            &queries[1],
+            &queries[2],
+            &queries[6],
+            &queries[7],
+        ];
+        let filtered_queries: Vec<&FtlQuery> = filter_client(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                client: Some(clients(&["client1", r"re:^192\.168\.1\.12$"])),
+                ..HistoryParams::default()
+            },
+            &test_memory(),
+            &ShmLockGuard::Test,
+        )
+        .unwrap()
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Regular expressions in the client list may contain commas
+    #[test]
+    fn list_parsing() {
+        let field = ValueField::from_value(r"client1, 10.1.*, re:^192\.168\.1\.1{1,2}$");
+        let list = ValueList::<Pattern>::from_value(field).unwrap();
+
+        assert_eq!(list.0.len(), 3);
+        assert!(list.0[1].is_match("10.1.1.1"));
+        assert!(list.0[2].is_match("192.168.1.11"));
+        assert!(!list.0[2].is_match("192.168.1.111"));
+    }
+
     /// Only queries with a client similar to the input are returned. This is a
     /// database filter.
     #[test]
@@ -176,14 +228,33 @@ mod test {
         use crate::databases::ftl::queries::dsl::*;
 
         let params = HistoryParams {
-            client: Some("10.1".to_owned()),
+            client: Some(clients(&["10.1"])),
             ..HistoryParams::default()
         };
 
-        let db_query = filter_client_db(queries.into_boxed(), &params);
-        let filtered_queries = execute_query(&connect_to_ftl_test_db(), db_query).unwrap();
+        let db = connect_to_ftl_test_db();
+        let db_query = filter_client_db(queries.into_boxed(), &params, &db).unwrap();
+        let filtered_queries = execute_query(&db, db_query).unwrap();
 
         assert_eq!(filtered_queries.len(), 1);
         assert_eq!(filtered_queries[0].client, "10.1.1.1");
     }
+
+    /// Queries from any of the clients in the list are returned. This is a
+    /// database filter.
+    #[test]
+    fn database_list() {
+        use crate::databases::ftl::queries::dsl::*;
+
+        let params = HistoryParams {
+            client: Some(clients(&["10.1.1.1", "127.0.0.*"])),
+            ..HistoryParams::default()
+        };
+
+        let db = connect_to_ftl_test_db();
+        let db_query = filter_client_db(queries.into_boxed(), &params, &db).unwrap();
+        let filtered_queries = execute_query(&db, db_query).unwrap();
+
+        assert_eq!(filtered_queries.len(), 94);
+    }
 }
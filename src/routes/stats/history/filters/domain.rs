@@ -9,15 +9,15 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::ftl::queries,
+    databases::ftl::{queries, FtlDatabase},
     ftl::{FtlMemory, FtlQuery, ShmLockGuard},
-    routes::stats::history::endpoints::HistoryParams,
+    routes::stats::history::{endpoints::HistoryParams, pattern::filter_patterns_db},
     util::Error,
 };
-use diesel::{prelude::*, sqlite::Sqlite};
-use std::{collections::HashSet, iter};
+use diesel::sqlite::Sqlite;
+use std::{collections::HashSet, iter, slice};
 
-/// Only show queries of the specified domain
+/// Only show queries of domains matching the domain pattern
 pub fn filter_domain<'a>(
     queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
     params: &HistoryParams,
@@ -35,7 +35,7 @@ pub fn filter_domain<'a>(
             .take(counters.total_domains as usize)
             .enumerate()
             .filter_map(|(i, domain)| {
-                if domain.get_domain(&strings).contains(domain_filter) {
+                if domain_filter.is_match(domain.get_domain(&strings)) {
                     Some(i)
                 } else {
                     None
@@ -55,18 +55,22 @@ pub fn filter_domain<'a>(
     }
 }
 
-/// Only show queries of the specified domain in database results
+/// Only show queries of domains matching the domain pattern in database
+/// results
 pub fn filter_domain_db<'a>(
     db_query: queries::BoxedQuery<'a, Sqlite>,
     params: &HistoryParams,
-) -> queries::BoxedQuery<'a, Sqlite> {
-    // Use the Diesel DSL of this table for easy querying
-    use self::queries::dsl::*;
-
+    db: &FtlDatabase,
+) -> Result<queries::BoxedQuery<'a, Sqlite>, Error> {
     if let Some(ref search_domain) = params.domain {
-        db_query.filter(domain.like(format!("%{}%", search_domain)))
+        filter_patterns_db(
+            db_query,
+            db,
+            queries::domain,
+            slice::from_ref(search_domain),
+        )
     } else {
-        db_query
+        Ok(db_query)
     }
 }
 
@@ -79,6 +83,7 @@ mod test {
         routes::stats::history::{
             database::execute_query,
             endpoints::HistoryParams,
+            pattern::Pattern,
             testing::{test_memory, test_queries},
         },
     };
@@ -92,7 +97,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_domain(
             Box::new(queries.iter()),
             &HistoryParams {
-                domain: Some("domain2.com".to_owned()),
+                domain: Some(Pattern::parse("domain2.com").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -113,7 +118,27 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_domain(
             Box::new(queries.iter()),
             &HistoryParams {
-                domain: Some("2.c".to_owned()),
+                domain: Some(Pattern::parse("2.c").unwrap()),
+                ..HistoryParams::default()
+            },
+            &test_memory(),
+            &ShmLockGuard::Test,
+        )
+        .unwrap()
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Only return queries of domains matching the glob
+    #[test]
+    fn glob() {
+        let queries = test_queries();
+        let expected_queries = vec![&queries[3]];
+        let filtered_queries: Vec<&FtlQuery> = filter_domain(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                domain: Some(Pattern::parse("*2.?om").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -132,12 +157,13 @@ mod test {
         use crate::databases::ftl::queries::dsl::*;
 
         let params = HistoryParams {
-            domain: Some("goog".to_owned()),
+            domain: Some(Pattern::parse("goog").unwrap()),
             ..HistoryParams::default()
         };
 
-        let db_query = filter_domain_db(queries.into_boxed(), &params);
-        let filtered_queries = execute_query(&connect_to_ftl_test_db(), db_query).unwrap();
+        let db = connect_to_ftl_test_db();
+        let db_query = filter_domain_db(queries.into_boxed(), &params, &db).unwrap();
+        let filtered_queries = execute_query(&db, db_query).unwrap();
 
         assert_eq!(filtered_queries.len(), 1);
         assert_eq!(filtered_queries[0].domain, "google.com");
@@ -17,6 +17,7 @@ mod exclude_domains;
 mod private;
 mod query_type;
 mod reply;
+mod response_time;
 mod setup_vars;
 mod status;
 mod time;
@@ -24,5 +25,6 @@ mod upstream;
 
 pub use self::{
     blocked::*, client::*, dnssec::*, domain::*, exclude_clients::*, exclude_domains::*,
-    private::*, query_type::*, reply::*, setup_vars::*, status::*, time::*, upstream::*,
+    private::*, query_type::*, reply::*, response_time::*, setup_vars::*, status::*, time::*,
+    upstream::*,
 };
@@ -9,23 +9,27 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::ftl::queries, ftl::FtlQuery, routes::stats::history::endpoints::HistoryParams,
+    databases::ftl::queries,
+    ftl::FtlQuery,
+    routes::stats::history::endpoints::{HistoryParams, ValueList},
 };
 use diesel::{prelude::*, sqlite::Sqlite};
 
-/// Only show queries with the specified query type
+/// Only show queries with any of the specified query types
 pub fn filter_query_type<'a>(
     queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
     params: &HistoryParams,
 ) -> Box<dyn Iterator<Item = &'a FtlQuery> + 'a> {
-    if let Some(query_type) = params.query_type {
-        Box::new(queries_iter.filter(move |query| query.query_type == query_type))
+    if let Some(ref query_types) = params.query_type {
+        let query_types = query_types.0.clone();
+        Box::new(queries_iter.filter(move |query| query_types.contains(&query.query_type)))
     } else {
         queries_iter
     }
 }
 
-/// Only show queries with the specified query type in database results
+/// Only show queries with any of the specified query types in database
+/// results
 pub fn filter_query_type_db<'a>(
     db_query: queries::BoxedQuery<'a, Sqlite>,
     params: &HistoryParams,
@@ -33,8 +37,13 @@ pub fn filter_query_type_db<'a>(
     // Use the Diesel DSL of this table for easy querying
     use self::queries::dsl::*;
 
-    if let Some(search_query_type) = params.query_type {
-        db_query.filter(query_type.eq(search_query_type as i32))
+    if let Some(ref search_query_types) = params.query_type {
+        let values: Vec<i32> = search_query_types
+            .0
+            .iter()
+            .map(|&value| value as i32)
+            .collect();
+        db_query.filter(query_type.eq_any(values))
     } else {
         db_query
     }
@@ -47,7 +56,9 @@ mod test {
         databases::ftl::connect_to_ftl_test_db,
         ftl::{FtlQuery, FtlQueryType},
         routes::stats::history::{
-            database::execute_query, endpoints::HistoryParams, testing::test_queries,
+            database::execute_query,
+            endpoints::{HistoryParams, ValueList},
+            testing::test_queries,
         },
     };
     use diesel::prelude::*;
@@ -60,7 +71,33 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_query_type(
             Box::new(queries.iter()),
             &HistoryParams {
-                query_type: Some(FtlQueryType::A),
+                query_type: Some(ValueList(vec![FtlQueryType::A])),
+                ..HistoryParams::default()
+            },
+        )
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Only return queries with any of the specified query types
+    #[test]
+    fn list() {
+        let queries = test_queries();
+        let expected_queries = vec![
+            &queries[0],
+            &queries[1],
+            &queries[3],
+            &queries[4],
+            &queries[5],
+            &queries[6],
+            &queries[7],
+            &queries[8],
+        ];
+        let filtered_queries: Vec<&FtlQuery> = filter_query_type(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                query_type: Some(ValueList(vec![FtlQueryType::A, FtlQueryType::AAAA])),
                 ..HistoryParams::default()
             },
         )
@@ -77,7 +114,7 @@ mod test {
 
         let expected_query_type = FtlQueryType::PTR;
         let params = HistoryParams {
-            query_type: Some(expected_query_type),
+            query_type: Some(ValueList(vec![expected_query_type])),
             ..HistoryParams::default()
         };
 
@@ -88,4 +125,21 @@ mod test {
             assert_eq!(query.query_type, expected_query_type as i32);
         }
     }
+
+    /// Queries with any of the input query types are returned. This is a
+    /// database filter.
+    #[test]
+    fn database_list() {
+        use crate::databases::ftl::queries::dsl::*;
+
+        let params = HistoryParams {
+            query_type: Some(ValueList(vec![FtlQueryType::A, FtlQueryType::AAAA])),
+            ..HistoryParams::default()
+        };
+
+        let db_query = filter_query_type_db(queries.into_boxed(), &params);
+        let filtered_queries = execute_query(&connect_to_ftl_test_db(), db_query).unwrap();
+
+        assert_eq!(filtered_queries.len(), 71);
+    }
 }
@@ -0,0 +1,89 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Response Time Filter
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{ftl::FtlQuery, routes::stats::history::endpoints::HistoryParams};
+
+/// Check if the parameters filter by response time. The database does not
+/// store response times, so database queries can not match the filter.
+pub fn filters_response_time(params: &HistoryParams) -> bool {
+    params.min_response_time.is_some() || params.max_response_time.is_some()
+}
+
+/// Only show queries with a response time within the `min_response_time` and
+/// `max_response_time` bounds. Queries without a response are not shown when
+/// filtering by response time.
+pub fn filter_response_time<'a>(
+    queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
+    params: &HistoryParams,
+) -> Box<dyn Iterator<Item = &'a FtlQuery> + 'a> {
+    if !filters_response_time(params) {
+        return queries_iter;
+    }
+
+    let min = params.min_response_time.unwrap_or(u32::MIN);
+    let max = params.max_response_time.unwrap_or(u32::MAX);
+
+    Box::new(queries_iter.filter(move |query| {
+        query
+            .get_response_time()
+            .map(|response_time| response_time >= min && response_time <= max)
+            .unwrap_or(false)
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::filter_response_time;
+    use crate::{
+        ftl::FtlQuery,
+        routes::stats::history::{endpoints::HistoryParams, testing::test_queries},
+    };
+
+    /// Only return queries with response times within the bounds
+    #[test]
+    fn bounds() {
+        let mut queries = test_queries();
+        queries[0].response_time = 5;
+        queries[1].response_time = 20;
+        queries[2].response_time = 50;
+
+        let expected_queries = vec![&queries[1], &queries[2]];
+        let filtered_queries: Vec<&FtlQuery> = filter_response_time(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                min_response_time: Some(10),
+                max_response_time: Some(50),
+                ..HistoryParams::default()
+            },
+        )
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Queries without a response are not returned
+    #[test]
+    fn no_response() {
+        let mut queries = test_queries();
+        queries[0].response_time = 18_000_000;
+
+        let filtered_queries: Vec<&FtlQuery> = filter_response_time(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                max_response_time: Some(100),
+                ..HistoryParams::default()
+            },
+        )
+        .collect();
+
+        assert_eq!(filtered_queries.len(), 8);
+        assert!(!filtered_queries.contains(&&queries[0]));
+    }
+}
@@ -9,23 +9,26 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::ftl::queries, ftl::FtlQuery, routes::stats::history::endpoints::HistoryParams,
+    databases::ftl::queries,
+    ftl::FtlQuery,
+    routes::stats::history::endpoints::{HistoryParams, ValueList},
 };
 use diesel::{prelude::*, sqlite::Sqlite};
 
-/// Only show queries with the specific status
+/// Only show queries with any of the specified statuses
 pub fn filter_status<'a>(
     queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
     params: &HistoryParams,
 ) -> Box<dyn Iterator<Item = &'a FtlQuery> + 'a> {
-    if let Some(status) = params.status {
-        Box::new(queries_iter.filter(move |query| query.status == status))
+    if let Some(ref statuses) = params.status {
+        let statuses = statuses.0.clone();
+        Box::new(queries_iter.filter(move |query| statuses.contains(&query.status)))
     } else {
         queries_iter
     }
 }
 
-/// Only show queries with the specific status in database results
+/// Only show queries with any of the specified statuses in database results
 pub fn filter_status_db<'a>(
     db_query: queries::BoxedQuery<'a, Sqlite>,
     params: &HistoryParams,
@@ -33,8 +36,13 @@ pub fn filter_status_db<'a>(
     // Use the Diesel DSL of this table for easy querying
     use self::queries::dsl::*;
 
-    if let Some(search_status) = params.status {
-        db_query.filter(status.eq(search_status as i32))
+    if let Some(ref search_statuses) = params.status {
+        let values: Vec<i32> = search_statuses
+            .0
+            .iter()
+            .map(|&value| value as i32)
+            .collect();
+        db_query.filter(status.eq_any(values))
     } else {
         db_query
     }
@@ -47,7 +55,9 @@ mod test {
         databases::ftl::connect_to_ftl_test_db,
         ftl::{FtlQuery, FtlQueryStatus},
         routes::stats::history::{
-            database::execute_query, endpoints::HistoryParams, testing::test_queries,
+            database::execute_query,
+            endpoints::{HistoryParams, ValueList},
+            testing::test_queries,
         },
     };
     use diesel::prelude::*;
@@ -60,7 +70,27 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_status(
             Box::new(queries.iter()),
             &HistoryParams {
-                status: Some(FtlQueryStatus::Gravity),
+                status: Some(ValueList(vec![FtlQueryStatus::Gravity])),
+                ..HistoryParams::default()
+            },
+        )
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Only return queries with any of the specified statuses
+    #[test]
+    fn list() {
+        let queries = test_queries();
+        let expected_queries = vec![&queries[3], &queries[5]];
+        let filtered_queries: Vec<&FtlQuery> = filter_status(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                status: Some(ValueList(vec![
+                    FtlQueryStatus::Gravity,
+                    FtlQueryStatus::Wildcard,
+                ])),
                 ..HistoryParams::default()
             },
         )
@@ -77,7 +107,7 @@ mod test {
 
         let expected_status = FtlQueryStatus::Forward;
         let params = HistoryParams {
-            status: Some(expected_status),
+            status: Some(ValueList(vec![expected_status])),
             ..HistoryParams::default()
         };
 
@@ -88,4 +118,24 @@ mod test {
             assert_eq!(query.status, expected_status as i32);
         }
     }
+
+    /// Queries with any of the input statuses are returned. This is a database
+    /// filter.
+    #[test]
+    fn database_list() {
+        use crate::databases::ftl::queries::dsl::*;
+
+        let params = HistoryParams {
+            status: Some(ValueList(vec![
+                FtlQueryStatus::Forward,
+                FtlQueryStatus::Cache,
+            ])),
+            ..HistoryParams::default()
+        };
+
+        let db_query = filter_status_db(queries.into_boxed(), &params);
+        let filtered_queries = execute_query(&connect_to_ftl_test_db(), db_query).unwrap();
+
+        assert_eq!(filtered_queries.len(), 54);
+    }
 }
@@ -9,15 +9,19 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::ftl::queries,
+    databases::ftl::{queries, FtlDatabase},
     ftl::{FtlMemory, FtlQuery, FtlQueryStatus, ShmLockGuard},
-    routes::stats::history::endpoints::HistoryParams,
+    routes::stats::history::{
+        endpoints::HistoryParams,
+        pattern::{filter_patterns_db, Pattern},
+    },
     util::Error,
 };
-use diesel::{prelude::*, sqlite::Sqlite};
-use std::{collections::HashSet, iter};
+use diesel::sqlite::Sqlite;
+use std::{collections::HashSet, iter, slice};
 
-/// Only show queries from the specified upstream
+/// Only show queries from upstreams matching the upstream pattern. The
+/// special values `blocklist` and `cache` show blocked and cached queries.
 pub fn filter_upstream<'a>(
     queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
     params: &HistoryParams,
@@ -25,14 +29,19 @@ pub fn filter_upstream<'a>(
     ftl_lock: &ShmLockGuard<'a>,
 ) -> Result<Box<dyn Iterator<Item = &'a FtlQuery> + 'a>, Error> {
     if let Some(ref upstream) = params.upstream {
-        if upstream == "blocklist" {
+        let special = match upstream {
+            Pattern::Substring(special) => special.as_str(),
+            _ => "",
+        };
+
+        if special == "blocklist" {
             Ok(Box::new(queries_iter.filter(|query| match query.status {
                 FtlQueryStatus::Gravity | FtlQueryStatus::Blacklist | FtlQueryStatus::Wildcard => {
                     true
                 }
                 _ => false,
             })))
-        } else if upstream == "cache" {
+        } else if special == "cache" {
             Ok(Box::new(
                 queries_iter.filter(|query| query.status == FtlQueryStatus::Cache),
             ))
@@ -50,7 +59,7 @@ pub fn filter_upstream<'a>(
                     let ip = item.get_ip(&strings);
                     let name = item.get_name(&strings).unwrap_or_default();
 
-                    if ip.contains(upstream) || name.contains(upstream) {
+                    if upstream.is_match(ip) || upstream.is_match(name) {
                         Some(i)
                     } else {
                         None
@@ -71,18 +80,22 @@ pub fn filter_upstream<'a>(
     }
 }
 
-/// Only show queries from the specified upstream in database results
+/// Only show queries from upstreams matching the upstream pattern in
+/// database results
 pub fn filter_upstream_db<'a>(
     db_query: queries::BoxedQuery<'a, Sqlite>,
     params: &HistoryParams,
-) -> queries::BoxedQuery<'a, Sqlite> {
-    // Use the Diesel DSL of this table for easy querying
-    use self::queries::dsl::*;
-
+    db: &FtlDatabase,
+) -> Result<queries::BoxedQuery<'a, Sqlite>, Error> {
     if let Some(ref search_upstream) = params.upstream {
-        db_query.filter(upstream.like(format!("%{}%", search_upstream)))
+        filter_patterns_db(
+            db_query,
+            db,
+            queries::upstream,
+            slice::from_ref(search_upstream),
+        )
     } else {
-        db_query
+        Ok(db_query)
     }
 }
 
@@ -95,6 +108,7 @@ mod test {
         routes::stats::history::{
             database::execute_query,
             endpoints::HistoryParams,
+            pattern::Pattern,
             testing::{test_memory, test_queries},
         },
     };
@@ -108,7 +122,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_upstream(
             Box::new(queries.iter()),
             &HistoryParams {
-                upstream: Some("8.8.4.4".to_owned()),
+                upstream: Some(Pattern::parse("8.8.4.4").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -129,7 +143,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_upstream(
             Box::new(queries.iter()),
             &HistoryParams {
-                upstream: Some("8.4.".to_owned()),
+                upstream: Some(Pattern::parse("8.4.").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -149,7 +163,7 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_upstream(
             Box::new(queries.iter()),
             &HistoryParams {
-                upstream: Some("google-public-dns-b.google.com".to_owned()),
+                upstream: Some(Pattern::parse("google-public-dns-b.google.com").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -170,7 +184,27 @@ mod test {
         let filtered_queries: Vec<&FtlQuery> = filter_upstream(
             Box::new(queries.iter()),
             &HistoryParams {
-                upstream: Some("b.google".to_owned()),
+                upstream: Some(Pattern::parse("b.google").unwrap()),
+                ..HistoryParams::default()
+            },
+            &test_memory(),
+            &ShmLockGuard::Test,
+        )
+        .unwrap()
+        .collect();
+
+        assert_eq!(filtered_queries, expected_queries);
+    }
+
+    /// Only return queries with an upstream matching the regular expression
+    #[test]
+    fn regex() {
+        let queries = test_queries();
+        let expected_queries = vec![&queries[7]];
+        let filtered_queries: Vec<&FtlQuery> = filter_upstream(
+            Box::new(queries.iter()),
+            &HistoryParams {
+                upstream: Some(Pattern::parse(r"re:dns-[b-z]\.").unwrap()),
                 ..HistoryParams::default()
             },
             &test_memory(),
@@ -189,12 +223,13 @@ mod test {
         use crate::databases::ftl::queries::dsl::*;
 
         let params = HistoryParams {
-            upstream: Some("8.8.8".to_owned()),
+            upstream: Some(Pattern::parse("8.8.8").unwrap()),
             ..HistoryParams::default()
         };
 
-        let db_query = filter_upstream_db(queries.into_boxed(), &params);
-        let filtered_queries = execute_query(&connect_to_ftl_test_db(), db_query).unwrap();
+        let db = connect_to_ftl_test_db();
+        let db_query = filter_upstream_db(queries.into_boxed(), &params, &db).unwrap();
+        let filtered_queries = execute_query(&db, db_query).unwrap();
 
         for query in filtered_queries {
             assert_eq!(query.upstream, Some("8.8.8.8".to_owned()));
@@ -11,18 +11,21 @@
 use super::{
     endpoints::{HistoryCursor, HistoryParams},
     filters::*,
-    map_query_to_json::map_query_to_json,
+    map_query_to_json::{map_db_query_to_json, map_query_to_json},
     skip_to_cursor::skip_to_cursor,
 };
 use crate::{
     databases::ftl::FtlDatabase,
     env::Env,
-    ftl::{FtlMemory, FtlQuery},
+    ftl::{FtlMemory, FtlQuery, ShmLockGuard},
     routes::stats::history::{database::load_queries_from_database, HistoryReply, QueryReply},
     settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
     util::Error,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    cmp::Reverse,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// Get the query history according to the specified parameters
 pub fn get_history(
@@ -73,23 +76,32 @@ pub fn get_history(
     let queries_iter = skip_to_cursor(queries_iter, &params);
 
     // Apply filters
-    let queries_iter = filter_private_queries(queries_iter);
-    let queries_iter = filter_setup_vars_setting(queries_iter, env)?;
-    let queries_iter = filter_time_from(queries_iter, &params);
-    let queries_iter = filter_time_until(queries_iter, &params);
-    let queries_iter = filter_query_type(queries_iter, &params);
-    let queries_iter = filter_upstream(queries_iter, &params, ftl_memory, &lock)?;
-    let queries_iter = filter_domain(queries_iter, &params, ftl_memory, &lock)?;
-    let queries_iter = filter_client(queries_iter, &params, ftl_memory, &lock)?;
-    let queries_iter = filter_status(queries_iter, &params);
-    let queries_iter = filter_blocked(queries_iter, &params);
-    let queries_iter = filter_dnssec(queries_iter, &params);
-    let queries_iter = filter_reply(queries_iter, &params);
-    let queries_iter = filter_excluded_domains(queries_iter, env, ftl_memory, &lock)?;
-    let queries_iter = filter_excluded_clients(queries_iter, env, ftl_memory, &lock)?;
+    let queries_iter = filter_queries(queries_iter, &params, env, ftl_memory, &lock)?;
 
     // Get the limit
     let limit = params.limit.unwrap_or(100);
+    let details = params.details.unwrap_or(false);
+
+    // The slowest queries are sorted by response time instead of by time, so
+    // there is no cursor and the database is not searched
+    if params.slowest.unwrap_or(false) {
+        let mut history: Vec<&FtlQuery> = queries_iter
+            .filter(|query| query.get_response_time().is_some())
+            .collect();
+
+        // The sort is stable, so queries with the same response time stay
+        // sorted by most recent
+        history.sort_by_key(|query| Reverse(query.get_response_time()));
+
+        return Ok(HistoryReply {
+            history: history
+                .into_iter()
+                .take(limit)
+                .map(map_query_to_json(ftl_memory, &lock, details)?)
+                .collect(),
+            cursor: None,
+        });
+    }
 
     // Apply the limit (plus one to get the cursor) and collect the queries
     let history: Vec<&FtlQuery> = queries_iter.take(limit + 1).collect();
@@ -135,7 +147,7 @@ pub fn get_history(
             // Only take up to the limit this time, not including the last query,
             // because it was just used to get the cursor
             .take(limit)
-            .map(map_query_to_json(ftl_memory, &lock)?)
+            .map(map_query_to_json(ftl_memory, &lock, details)?)
             .collect();
 
     // If there are not enough queries to reach the limit (next cursor is null),
@@ -149,7 +161,7 @@ pub fn get_history(
         let (db_queries, cursor) = load_queries_from_database(db, last_db_id, &params, env, limit)?;
 
         // Map the queries into JSON
-        let db_queries = db_queries.into_iter().map(Into::into);
+        let db_queries = db_queries.into_iter().map(map_db_query_to_json(details));
 
         // Update the cursor
         next_cursor = cursor.map(|cursor| cursor.as_base64().unwrap());
@@ -166,8 +178,36 @@ pub fn get_history(
     })
 }
 
+/// Apply the privacy rules and the filters in the parameters to in-memory
+/// queries. The cursor and limit are not applied.
+pub fn filter_queries<'a>(
+    queries_iter: Box<dyn Iterator<Item = &'a FtlQuery> + 'a>,
+    params: &HistoryParams,
+    env: &Env,
+    ftl_memory: &FtlMemory,
+    lock: &ShmLockGuard<'a>,
+) -> Result<Box<dyn Iterator<Item = &'a FtlQuery> + 'a>, Error> {
+    let queries_iter = filter_private_queries(queries_iter);
+    let queries_iter = filter_setup_vars_setting(queries_iter, env)?;
+    let queries_iter = filter_time_from(queries_iter, params);
+    let queries_iter = filter_time_until(queries_iter, params);
+    let queries_iter = filter_query_type(queries_iter, params);
+    let queries_iter = filter_upstream(queries_iter, params, ftl_memory, lock)?;
+    let queries_iter = filter_domain(queries_iter, params, ftl_memory, lock)?;
+    let queries_iter = filter_client(queries_iter, params, ftl_memory, lock)?;
+    let queries_iter = filter_status(queries_iter, params);
+    let queries_iter = filter_blocked(queries_iter, params);
+    let queries_iter = filter_dnssec(queries_iter, params);
+    let queries_iter = filter_reply(queries_iter, params);
+    let queries_iter = filter_response_time(queries_iter, params);
+    let queries_iter = filter_excluded_domains(queries_iter, env, ftl_memory, lock)?;
+    let queries_iter = filter_excluded_clients(queries_iter, env, ftl_memory, lock)?;
+
+    Ok(queries_iter)
+}
+
 /// Check if the timespan is completely within the last 24 hours
-fn is_within_24_hours(from: Option<u64>, until: Option<u64>) -> bool {
+pub fn is_within_24_hours(from: Option<u64>, until: Option<u64>) -> bool {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Current time is older than epoch")
@@ -194,11 +234,14 @@ mod test {
     use crate::{
         databases::ftl::connect_to_ftl_test_db,
         env::PiholeFile,
-        ftl::ShmLockGuard,
+        ftl::{FtlMemory, FtlSettings, ShmLockGuard},
         routes::stats::history::{
             get_history::get_history,
             map_query_to_json::map_query_to_json,
-            testing::{test_memory, test_queries},
+            testing::{
+                test_clients, test_counters, test_domains, test_memory, test_queries, test_strings,
+                test_upstreams,
+            },
             HistoryParams, HistoryReply, QueryReply,
         },
         testing::TestEnvBuilder,
@@ -217,7 +260,7 @@ mod test {
         let history: Vec<QueryReply> = expected_queries
             .iter()
             .rev()
-            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test).unwrap())
+            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap())
             .collect();
 
         let env = TestEnvBuilder::new()
@@ -254,7 +297,7 @@ mod test {
             .iter()
             .rev()
             .take(5)
-            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test).unwrap())
+            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap())
             .collect();
 
         let env = TestEnvBuilder::new()
@@ -313,6 +356,7 @@ mod test {
                 dnssec: 5,
                 reply: 0,
                 response_time: 0,
+                details: None,
             },
             QueryReply {
                 timestamp: 177_180,
@@ -323,6 +367,7 @@ mod test {
                 dnssec: 5,
                 reply: 0,
                 response_time: 0,
+                details: None,
             },
         ];
 
@@ -346,4 +391,75 @@ mod test {
 
         assert_eq!(actual, expected);
     }
+
+    /// The slowest queries are returned first, without a cursor
+    #[test]
+    fn slowest() {
+        let mut queries = test_queries();
+        queries[1].response_time = 300;
+        queries[4].response_time = 200;
+        queries[6].response_time = 300;
+
+        let ftl_memory = FtlMemory::Test {
+            clients: test_clients(),
+            counters: test_counters(),
+            domains: test_domains(),
+            over_time: Vec::new(),
+            strings: test_strings(),
+            queries: queries.clone(),
+            upstreams: test_upstreams(),
+            settings: FtlSettings::default(),
+        };
+
+        let history: Vec<QueryReply> = vec![&queries[6], &queries[1], &queries[4]]
+            .into_iter()
+            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap())
+            .collect();
+
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, "")
+            .build();
+
+        let params = HistoryParams {
+            slowest: Some(true),
+            limit: Some(3),
+            ..HistoryParams::default()
+        };
+
+        let expected = HistoryReply {
+            history,
+            cursor: None,
+        };
+
+        let actual = get_history(&ftl_memory, &env, params, &connect_to_ftl_test_db()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    /// Database queries do not have response times, so they are not loaded
+    /// when filtering by response time
+    #[test]
+    fn response_time_database() {
+        let env = TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, "")
+            .build();
+
+        let params = HistoryParams {
+            from: Some(177_180),
+            until: Some(177_181),
+            min_response_time: Some(0),
+            ..HistoryParams::default()
+        };
+
+        let expected = HistoryReply {
+            history: Vec::new(),
+            cursor: None,
+        };
+
+        let actual = get_history(&test_memory(), &env, params, &connect_to_ftl_test_db()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
 }
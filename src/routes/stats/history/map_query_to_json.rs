@@ -9,10 +9,11 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
+    databases::ftl::FtlDbQuery,
     ftl::{FtlMemory, FtlQuery, ShmLockGuard},
     routes::stats::{
         common::{HIDDEN_CLIENT, HIDDEN_DOMAIN},
-        history::QueryReply,
+        history::{QueryDetails, QueryReply, UpstreamReply},
     },
     settings::FtlPrivacyLevel,
     util::Error,
@@ -20,12 +21,15 @@ use crate::{
 
 /// Create a function to map `FtlQuery` structs to JSON `Value` structs. The
 /// queries' privacy levels will be taken into account when exposing their data.
+/// If `details` is true, the `QueryDetails` of the queries are included.
 pub fn map_query_to_json<'a>(
     ftl_memory: &'a FtlMemory,
     ftl_lock: &ShmLockGuard<'a>,
+    details: bool,
 ) -> Result<impl Fn(&FtlQuery) -> QueryReply + 'a, Error> {
     let domains = ftl_memory.domains(ftl_lock)?;
     let clients = ftl_memory.clients(ftl_lock)?;
+    let upstreams = ftl_memory.upstreams(ftl_lock)?;
     let strings = ftl_memory.strings(ftl_lock)?;
 
     Ok(move |query: &FtlQuery| {
@@ -36,24 +40,56 @@ pub fn map_query_to_json<'a>(
             HIDDEN_DOMAIN
         };
 
-        // Get the client depending on the privacy level
-        let client = if query.privacy_level < FtlPrivacyLevel::HideDomainsAndClients {
-            let client = clients[query.client_id as usize];
+        // Get the client IP and name depending on the privacy level
+        let (client_ip, client_name) =
+            if query.privacy_level < FtlPrivacyLevel::HideDomainsAndClients {
+                let client = clients[query.client_id as usize];
 
-            // Try to get the client name first, but if it doesn't exist use the IP
-            client
-                .get_name(&strings)
-                .unwrap_or_else(|| client.get_ip(&strings))
-        } else {
-            HIDDEN_CLIENT
-        };
+                (client.get_ip(&strings), client.get_name(&strings))
+            } else {
+                (HIDDEN_CLIENT, None)
+            };
+
+        // Try to get the client name first, but if it doesn't exist use the IP
+        let client = client_name.unwrap_or(client_ip);
+
+        // Queries which have not received a response have a response time of 0
+        let response_time = query.get_response_time().unwrap_or(0);
+
+        let query_details = if details {
+            // The upstream is only known if the query was forwarded
+            let upstream = if query.is_forwarded() {
+                let upstream = &upstreams[query.upstream_id as usize];
 
-        // Check if response was received (response time should be smaller than 30min)
-        let response_time = if query.response_time < 18_000_000 {
-            query.response_time
+                Some(UpstreamReply {
+                    ip: upstream.get_ip(&strings).to_owned(),
+                    name: upstream.get_name(&strings).map(str::to_owned),
+                })
+            } else {
+                None
+            };
+
+            // The regex match state is hidden along with the domain
+            let regex_match = if query.privacy_level < FtlPrivacyLevel::HideDomains {
+                Some(domains[query.domain_id as usize].regex_match as u8)
+            } else {
+                None
+            };
+
+            Some(QueryDetails {
+                client_ip: client_ip.to_owned(),
+                client_name: client_name.map(str::to_owned),
+                upstream,
+                regex_match,
+                db_id: if query.database_id != 0 {
+                    Some(query.database_id)
+                } else {
+                    None
+                },
+            })
         } else {
-            0
-        } as u32;
+            None
+        };
 
         QueryReply {
             timestamp: query.timestamp as u64,
@@ -64,18 +100,46 @@ pub fn map_query_to_json<'a>(
             dnssec: query.dnssec_type as u8,
             reply: query.reply_type as u8,
             response_time,
+            details: query_details,
         }
     })
 }
 
+/// Create a function to map `FtlDbQuery` structs to JSON `Value` structs. If
+/// `details` is true, the `QueryDetails` known by the database are included.
+pub fn map_db_query_to_json(details: bool) -> impl Fn(FtlDbQuery) -> QueryReply {
+    move |query: FtlDbQuery| {
+        let query_details = if details {
+            Some(QueryDetails {
+                client_ip: query.client.clone(),
+                client_name: None,
+                upstream: query
+                    .upstream
+                    .clone()
+                    .map(|ip| UpstreamReply { ip, name: None }),
+                regex_match: None,
+                db_id: Some(query.id as i64),
+            })
+        } else {
+            None
+        };
+
+        QueryReply {
+            details: query_details,
+            ..query.into()
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::map_query_to_json;
+    use super::{map_db_query_to_json, map_query_to_json};
     use crate::{
+        databases::ftl::FtlDbQuery,
         ftl::ShmLockGuard,
         routes::stats::history::{
             testing::{test_memory, test_queries},
-            QueryReply,
+            QueryDetails, QueryReply, UpstreamReply,
         },
         settings::FtlPrivacyLevel,
     };
@@ -85,7 +149,7 @@ mod test {
     fn test_map_query_to_json() {
         let query = test_queries()[0];
         let ftl_memory = test_memory();
-        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test).unwrap();
+        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap();
         let mapped_query = map_function(&query);
 
         assert_eq!(
@@ -98,7 +162,8 @@ mod test {
                 client: "client1".to_owned(),
                 dnssec: 1,
                 reply: 3,
-                response_time: 1
+                response_time: 1,
+                details: None
             }
         );
     }
@@ -108,7 +173,7 @@ mod test {
     fn private_domains() {
         let mut query = test_queries()[0];
         let ftl_memory = test_memory();
-        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test).unwrap();
+        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap();
 
         query.privacy_level = FtlPrivacyLevel::HideDomains;
         let mapped_query = map_function(&query);
@@ -123,7 +188,8 @@ mod test {
                 client: "client1".to_owned(),
                 dnssec: 1,
                 reply: 3,
-                response_time: 1
+                response_time: 1,
+                details: None
             }
         );
     }
@@ -133,7 +199,7 @@ mod test {
     fn private_clients() {
         let mut query = test_queries()[0];
         let ftl_memory = test_memory();
-        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test).unwrap();
+        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap();
 
         query.privacy_level = FtlPrivacyLevel::HideDomainsAndClients;
         let mapped_query = map_function(&query);
@@ -148,8 +214,83 @@ mod test {
                 client: "0.0.0.0".to_owned(),
                 dnssec: 1,
                 reply: 3,
-                response_time: 1
+                response_time: 1,
+                details: None
             }
         );
     }
+
+    /// When details are requested, the client IP and name, upstream, regex
+    /// match state, and database ID are included
+    #[test]
+    fn details() {
+        let query = test_queries()[0];
+        let ftl_memory = test_memory();
+        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test, true).unwrap();
+
+        assert_eq!(
+            map_function(&query).details,
+            Some(QueryDetails {
+                client_ip: "192.168.1.10".to_owned(),
+                client_name: Some("client1".to_owned()),
+                upstream: Some(UpstreamReply {
+                    ip: "8.8.8.8".to_owned(),
+                    name: Some("google-public-dns-a.google.com".to_owned())
+                }),
+                regex_match: Some(2),
+                db_id: Some(95)
+            })
+        );
+    }
+
+    /// Queries which were not forwarded have no upstream, and private details
+    /// are hidden
+    #[test]
+    fn details_private() {
+        let mut query = test_queries()[3];
+        let ftl_memory = test_memory();
+        let map_function = map_query_to_json(&ftl_memory, &ShmLockGuard::Test, true).unwrap();
+
+        query.privacy_level = FtlPrivacyLevel::HideDomainsAndClients;
+
+        assert_eq!(
+            map_function(&query).details,
+            Some(QueryDetails {
+                client_ip: "0.0.0.0".to_owned(),
+                client_name: None,
+                upstream: None,
+                regex_match: None,
+                db_id: Some(98)
+            })
+        );
+    }
+
+    /// Database queries only have the details which are stored in the
+    /// database
+    #[test]
+    fn details_database() {
+        let query = FtlDbQuery {
+            id: 1,
+            timestamp: 1,
+            query_type: 1,
+            status: 2,
+            domain: "example.com".to_owned(),
+            client: "10.1.1.1".to_owned(),
+            upstream: Some("8.8.8.8".to_owned()),
+        };
+
+        assert_eq!(
+            map_db_query_to_json(true)(query).details,
+            Some(QueryDetails {
+                client_ip: "10.1.1.1".to_owned(),
+                client_name: None,
+                upstream: Some(UpstreamReply {
+                    ip: "8.8.8.8".to_owned(),
+                    name: None
+                }),
+                regex_match: None,
+                db_id: Some(1)
+            })
+        );
+    }
 }
@@ -10,12 +10,15 @@
 
 mod database;
 mod endpoints;
+mod export;
 mod filters;
 mod get_history;
 mod map_query_to_json;
+mod pattern;
 mod skip_to_cursor;
+mod stream;
 
 #[cfg(test)]
 mod testing;
 
-pub use self::endpoints::*;
+pub use self::{endpoints::*, export::*, stream::*};
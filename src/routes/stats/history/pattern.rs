@@ -0,0 +1,279 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// History Filter Patterns
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::ftl::{queries, FtlDatabase},
+    util::{Error, ErrorKind},
+};
+use diesel::{
+    expression::{BoxableExpression, NonAggregate},
+    prelude::*,
+    query_builder::QueryFragment,
+    sql_types::{Bool, IntoNullable, Nullable, Text},
+    sqlite::Sqlite,
+    Column,
+};
+use failure::ResultExt;
+use regex::Regex;
+use rocket::form::{self, FromFormField, ValueField};
+use std::{collections::HashMap, sync::Mutex};
+
+/// A pattern which domains, clients, and upstreams are matched against.
+/// Values starting with `re:` are regular expressions, values containing `*`
+/// or `?` are globs, and any other value matches as a substring.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Substring(String),
+    /// The glob, and the regular expression used to match it in memory
+    Glob(String, Regex),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parse a pattern from a filter value
+    pub fn parse(value: &str) -> Result<Self, regex::Error> {
+        if let Some(regex) = value.strip_prefix("re:") {
+            Ok(Pattern::Regex(Regex::new(regex)?))
+        } else if value.contains(|c| c == '*' || c == '?') {
+            let regex = value
+                .split('*')
+                .map(|part| {
+                    part.split('?')
+                        .map(regex::escape)
+                        .collect::<Vec<_>>()
+                        .join(".")
+                })
+                .collect::<Vec<_>>()
+                .join(".*");
+
+            Ok(Pattern::Glob(
+                value.to_owned(),
+                Regex::new(&format!("^{}$", regex))?,
+            ))
+        } else {
+            Ok(Pattern::Substring(value.to_owned()))
+        }
+    }
+
+    /// Check if the value matches the pattern
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            Pattern::Substring(substring) => value.contains(substring.as_str()),
+            Pattern::Glob(_, regex) | Pattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+
+    /// Get an SQL condition which is true if the column matches the pattern.
+    /// The pattern is bound as a parameter.
+    fn sql_condition<C>(&self, column: C) -> PatternCondition
+    where
+        C: PatternColumn,
+        C::SqlType: IntoNullable<Nullable = Nullable<Text>>,
+    {
+        match self {
+            Pattern::Substring(substring) => {
+                // Escape the LIKE wildcards so the substring is matched as is
+                let escaped = substring
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+
+                Box::new(sql_like(format!("%{}%", escaped), column.nullable(), "\\"))
+            }
+            // A literal "[" is written as "[[]" in SQLite globs
+            Pattern::Glob(glob, _) => {
+                Box::new(sql_glob(glob.replace('[', "[[]"), column.nullable()))
+            }
+            Pattern::Regex(regex) => Box::new(regexp(regex.as_str().to_owned(), column.nullable())),
+        }
+    }
+}
+
+impl<'v> FromFormField<'v> for Pattern {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        Pattern::parse(field.value)
+            .map_err(|_| form::Error::validation("Invalid regular expression").into())
+    }
+}
+
+/// A condition on the queries table, built from a pattern
+type PatternCondition = Box<dyn BoxableExpression<queries::table, Sqlite, SqlType = Bool>>;
+
+/// A text column of the queries table which patterns can be matched against
+pub trait PatternColumn:
+    Column<Table = queries::table>
+    + SelectableExpression<queries::table>
+    + NonAggregate
+    + QueryFragment<Sqlite>
+    + 'static
+{
+}
+
+impl<C> PatternColumn for C where
+    C: Column<Table = queries::table>
+        + SelectableExpression<queries::table>
+        + NonAggregate
+        + QueryFragment<Sqlite>
+        + 'static
+{
+}
+
+sql_function! {
+    #[sql_name = "like"]
+    fn sql_like(pattern: Text, value: Nullable<Text>, escape: Text) -> Bool;
+}
+
+sql_function! {
+    #[sql_name = "glob"]
+    fn sql_glob(pattern: Text, value: Nullable<Text>) -> Bool;
+}
+
+sql_function! {
+    /// SQLite calls this function for the `REGEXP` operator, but does not
+    /// implement it
+    fn regexp(pattern: Text, value: Nullable<Text>) -> Bool;
+}
+
+/// Register the `regexp` function on the connection. Each regular expression
+/// is compiled once and reused for every row.
+fn register_regexp(db: &SqliteConnection) -> Result<(), Error> {
+    let cache: Mutex<HashMap<String, Option<Regex>>> = Mutex::new(HashMap::new());
+
+    regexp::register_impl(db, move |pattern: String, value: Option<String>| {
+        let mut cache = cache.lock().unwrap();
+        let regex = cache
+            .entry(pattern)
+            .or_insert_with_key(|pattern| Regex::new(pattern).ok());
+
+        match (regex, value) {
+            (Some(regex), Some(value)) => regex.is_match(&value),
+            _ => false,
+        }
+    })
+    .context(ErrorKind::FtlDatabase)?;
+
+    Ok(())
+}
+
+/// Only show database queries where the column matches at least one of the
+/// patterns. The column must be a text column.
+pub fn filter_patterns_db<'a, C>(
+    db_query: queries::BoxedQuery<'a, Sqlite>,
+    db: &FtlDatabase,
+    column: C,
+    patterns: &[Pattern],
+) -> Result<queries::BoxedQuery<'a, Sqlite>, Error>
+where
+    C: PatternColumn + Copy,
+    C::SqlType: IntoNullable<Nullable = Nullable<Text>>,
+{
+    if patterns
+        .iter()
+        .any(|pattern| matches!(pattern, Pattern::Regex(_)))
+    {
+        register_regexp(db)?;
+    }
+
+    let condition = patterns
+        .iter()
+        .map(|pattern| pattern.sql_condition(column))
+        .reduce(|conditions, condition| Box::new(conditions.or(condition)));
+
+    Ok(match condition {
+        Some(condition) => db_query.filter(condition),
+        // No pattern can match
+        None => db_query.filter(false.into_sql::<Bool>()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{filter_patterns_db, Pattern};
+    use crate::{
+        databases::ftl::connect_to_ftl_test_db, routes::stats::history::database::execute_query,
+    };
+    use diesel::prelude::*;
+
+    /// Values are matched as substrings unless they are globs or regular
+    /// expressions
+    #[test]
+    fn parse_and_match() {
+        let substring = Pattern::parse("google").unwrap();
+        assert!(substring.is_match("www.google.com"));
+        assert!(!substring.is_match("example.com"));
+
+        let glob = Pattern::parse("*.google.?om").unwrap();
+        assert!(glob.is_match("www.google.com"));
+        assert!(!glob.is_match("google.com"));
+        assert!(!glob.is_match("www.google.com.evil"));
+
+        let regex = Pattern::parse(r"re:^(www\.)?example\.(com|org)$").unwrap();
+        assert!(regex.is_match("example.org"));
+        assert!(!regex.is_match("example.net"));
+
+        assert!(Pattern::parse("re:(").is_err());
+    }
+
+    /// Globs do not treat regular expression characters specially
+    #[test]
+    fn glob_escaping() {
+        let glob = Pattern::parse("a+b.*").unwrap();
+
+        assert!(glob.is_match("a+b.com"));
+        assert!(!glob.is_match("aab.com"));
+    }
+
+    /// Substrings are bound as parameters, and LIKE wildcards in them are
+    /// matched literally
+    #[test]
+    fn database_substring() {
+        use crate::databases::ftl::queries::dsl::*;
+
+        let db = connect_to_ftl_test_db();
+
+        for pattern in &["it's", "g_ogle", "%"] {
+            let patterns = vec![Pattern::parse(pattern).unwrap()];
+            let db_query = filter_patterns_db(queries.into_boxed(), &db, domain, &patterns);
+
+            assert_eq!(execute_query(&db, db_query.unwrap()).unwrap().len(), 0);
+        }
+
+        let patterns = vec![Pattern::parse("oogle").unwrap()];
+        let db_query = filter_patterns_db(queries.into_boxed(), &db, domain, &patterns);
+        let filtered_queries = execute_query(&db, db_query.unwrap()).unwrap();
+
+        assert_eq!(filtered_queries.len(), 1);
+        assert_eq!(filtered_queries[0].domain, "google.com");
+    }
+
+    /// Globs and regular expressions can be used on the database
+    #[test]
+    fn database() {
+        use crate::databases::ftl::queries::dsl::*;
+
+        let db = connect_to_ftl_test_db();
+        let patterns = vec![
+            Pattern::parse("goo*.com").unwrap(),
+            Pattern::parse(r"re:^10\.1\.").unwrap(),
+        ];
+
+        let db_query = filter_patterns_db(queries.into_boxed(), &db, domain, &patterns[..1]);
+        let filtered_queries = execute_query(&db, db_query.unwrap()).unwrap();
+
+        assert_eq!(filtered_queries.len(), 1);
+        assert_eq!(filtered_queries[0].domain, "google.com");
+
+        let db_query = filter_patterns_db(queries.into_boxed(), &db, client, &patterns[1..]);
+        let filtered_queries = execute_query(&db, db_query.unwrap()).unwrap();
+
+        assert_eq!(filtered_queries.len(), 1);
+        assert_eq!(filtered_queries[0].client, "10.1.1.1");
+    }
+}
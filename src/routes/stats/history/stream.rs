@@ -0,0 +1,264 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Live Query Stream Endpoint
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::Env,
+    ftl::{FtlMemory, FtlQuery},
+    routes::{
+        auth::CanReadStats,
+        stats::history::{
+            get_history::filter_queries, map_query_to_json::map_query_to_json, HistoryParams,
+            QueryReply,
+        },
+    },
+    services::PiholeModule,
+    settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
+    util::Error,
+};
+use rocket::{
+    response::stream::{Event, EventStream},
+    tokio::time::sleep,
+    State,
+};
+use shaku::HasComponent;
+use std::time::Duration;
+
+/// How often the stream checks FTL for new queries
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stream queries as Server-Sent Events as FTL records them. Each event is a
+/// query in the same format as `/stats/history`, and the same filters are
+/// supported, except for the cursor, limit, and `slowest`. Only queries
+/// recorded after the stream starts are sent.
+#[get("/stats/history/stream?<params..>")]
+pub fn history_stream<'r>(
+    _auth: CanReadStats,
+    ftl_memory: &'r State<FtlMemory>,
+    module: &'r State<Box<PiholeModule>>,
+    params: HistoryParams,
+) -> EventStream![Event + 'r] {
+    let ftl_memory: &FtlMemory = ftl_memory;
+    let env: &Env = module.resolve_ref();
+
+    EventStream! {
+        let mut last_id = None;
+
+        loop {
+            // The shared memory lock is only held while checking for queries,
+            // not while waiting
+            match get_new_queries(ftl_memory, env, &params, &mut last_id) {
+                Ok(queries) => {
+                    for query in queries {
+                        yield Event::json(&query);
+                    }
+                }
+                Err(e) => {
+                    yield Event::data(e.to_string()).event("error");
+                    break;
+                }
+            }
+
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Get the queries which FTL recorded after the query with the ID `last_id`,
+/// oldest first, and update `last_id` to the newest query. If `last_id` is
+/// `None`, no queries are returned, so that only queries recorded after this
+/// call are returned by the next one. The privacy rules and filters of the
+/// history endpoint are applied.
+pub fn get_new_queries(
+    ftl_memory: &FtlMemory,
+    env: &Env,
+    params: &HistoryParams,
+    last_id: &mut Option<i32>,
+) -> Result<Vec<QueryReply>, Error> {
+    let lock = ftl_memory.lock()?;
+    let counters = ftl_memory.counters(&lock)?;
+    let queries = ftl_memory.queries(&lock)?;
+
+    // Skip the uninitialized queries (FTL allocates more than it uses)
+    let queries: &[FtlQuery] = &queries[..queries.len().min(counters.total_queries as usize)];
+    let newest_id = queries.last().map(|query| query.id);
+
+    let since = match *last_id {
+        // If the newest query is older, FTL was restarted and reused the IDs,
+        // so start over from the newest query
+        Some(id) if newest_id.map_or(true, |newest_id| newest_id >= id) => id,
+        _ => {
+            *last_id = newest_id;
+            return Ok(Vec::new());
+        }
+    };
+    *last_id = newest_id;
+
+    // Check if query details are private
+    if FtlConfEntry::PrivacyLevel.read_as::<FtlPrivacyLevel>(env)? >= FtlPrivacyLevel::Maximum {
+        return Ok(Vec::new());
+    }
+
+    // The queries are stored oldest first, so the new queries are at the end
+    let new_count = queries
+        .iter()
+        .rev()
+        .take_while(|query| query.id > since)
+        .count();
+    let queries_iter = Box::new(queries[queries.len() - new_count..].iter());
+
+    let queries_iter = filter_queries(queries_iter, params, env, ftl_memory, &lock)?;
+
+    Ok(queries_iter
+        .map(map_query_to_json(
+            ftl_memory,
+            &lock,
+            params.details.unwrap_or(false),
+        )?)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::get_new_queries;
+    use crate::{
+        env::{Env, PiholeFile},
+        ftl::{FtlMemory, ShmLockGuard},
+        routes::stats::history::{
+            map_query_to_json::map_query_to_json,
+            testing::{test_memory, test_queries},
+            HistoryParams, QueryReply,
+        },
+        testing::TestEnvBuilder,
+    };
+
+    fn env(ftl_config: &str) -> Env {
+        TestEnvBuilder::new()
+            .file(PiholeFile::SetupVars, "")
+            .file(PiholeFile::FtlConfig, ftl_config)
+            .build()
+    }
+
+    /// Map the test queries with the given IDs to their replies
+    fn expected(ids: &[i32]) -> Vec<QueryReply> {
+        let ftl_memory = test_memory();
+
+        test_queries()
+            .iter()
+            .filter(|query| ids.contains(&query.id))
+            .map(map_query_to_json(&ftl_memory, &ShmLockGuard::Test, false).unwrap())
+            .collect()
+    }
+
+    /// The first call only finds the newest query
+    #[test]
+    fn first_call() {
+        let mut last_id = None;
+
+        let queries = get_new_queries(
+            &test_memory(),
+            &env(""),
+            &HistoryParams::default(),
+            &mut last_id,
+        )
+        .unwrap();
+
+        assert_eq!(queries, Vec::new());
+        assert_eq!(last_id, Some(9));
+    }
+
+    /// Queries after the last ID are returned oldest first, without private
+    /// queries
+    #[test]
+    fn new_queries() {
+        let mut last_id = Some(6);
+
+        let queries = get_new_queries(
+            &test_memory(),
+            &env(""),
+            &HistoryParams::default(),
+            &mut last_id,
+        )
+        .unwrap();
+
+        assert_eq!(queries, expected(&[7, 8]));
+        assert_eq!(last_id, Some(9));
+    }
+
+    /// The history filters are applied
+    #[test]
+    fn filters() {
+        let mut last_id = Some(0);
+        let params = HistoryParams {
+            blocked: Some(true),
+            ..HistoryParams::default()
+        };
+
+        let queries = get_new_queries(&test_memory(), &env(""), &params, &mut last_id).unwrap();
+
+        assert_eq!(queries, expected(&[4, 6, 7, 8]));
+    }
+
+    /// If the last ID is newer than every query, FTL was restarted and the
+    /// stream starts over
+    #[test]
+    fn restarted() {
+        let mut last_id = Some(100);
+
+        let queries = get_new_queries(
+            &test_memory(),
+            &env(""),
+            &HistoryParams::default(),
+            &mut last_id,
+        )
+        .unwrap();
+
+        assert_eq!(queries, Vec::new());
+        assert_eq!(last_id, Some(9));
+    }
+
+    /// Maximum privacy shows no queries
+    #[test]
+    fn privacy_max() {
+        let mut last_id = Some(0);
+
+        let queries = get_new_queries(
+            &test_memory(),
+            &env("PRIVACYLEVEL=3"),
+            &HistoryParams::default(),
+            &mut last_id,
+        )
+        .unwrap();
+
+        assert_eq!(queries, Vec::new());
+        assert_eq!(last_id, Some(9));
+    }
+
+    /// A query counter larger than the stored queries is not trusted
+    #[test]
+    fn counter_too_large() {
+        let mut ftl_memory = test_memory();
+        let mut last_id = Some(6);
+
+        if let FtlMemory::Test { counters, .. } = &mut ftl_memory {
+            counters.total_queries = 100;
+        }
+
+        let queries = get_new_queries(
+            &ftl_memory,
+            &env(""),
+            &HistoryParams::default(),
+            &mut last_id,
+        )
+        .unwrap();
+
+        assert_eq!(queries, expected(&[7, 8]));
+        assert_eq!(last_id, Some(9));
+    }
+}
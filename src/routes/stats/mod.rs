@@ -8,9 +8,11 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+pub mod client_details;
 pub mod clients;
 pub mod common;
 pub mod database;
+pub mod domain_details;
 pub mod history;
 pub mod over_time_clients;
 pub mod over_time_history;
@@ -19,4 +21,5 @@ pub mod recent_blocked;
 pub mod summary;
 pub mod top_clients;
 pub mod top_domains;
+pub mod upstream_latency;
 pub mod upstreams;
@@ -12,7 +12,7 @@ use crate::{
     env::Env,
     ftl::{ClientReply, FtlMemory},
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::{
             clients::{filter_ftl_clients, ClientParams},
             common::get_current_over_time_slot,
@@ -31,7 +31,7 @@ pub use over_time_clients as route;
 /// Get the client queries over time
 #[get("/stats/overTime/clients")]
 pub fn over_time_clients(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
 ) -> Reply {
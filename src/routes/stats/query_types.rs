@@ -10,7 +10,7 @@
 
 use crate::{
     ftl::{FtlMemory, FtlQueryType},
-    routes::auth::User,
+    routes::auth::CanReadStats,
     util::{reply_result, Error, Reply},
 };
 use rocket::State;
@@ -19,7 +19,7 @@ pub use query_types as route;
 
 /// Get the query types
 #[get("/stats/query_types")]
-pub fn query_types(_auth: User, ftl_memory: &State<FtlMemory>) -> Reply {
+pub fn query_types(_auth: CanReadStats, ftl_memory: &State<FtlMemory>) -> Reply {
     reply_result(query_types_impl(ftl_memory))
 }
 
@@ -11,7 +11,7 @@
 use crate::{
     env::Env,
     ftl::FtlMemory,
-    routes::auth::User,
+    routes::auth::CanReadStats,
     services::PiholeModule,
     settings::{ConfigEntry, FtlConfEntry, FtlPrivacyLevel},
     util::{reply_data, Reply},
@@ -24,7 +24,7 @@ pub use recent_blocked as route;
 /// Get the `num` most recently blocked domains
 #[get("/stats/recent_blocked?<params..>")]
 pub fn recent_blocked(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
     params: RecentBlockedParams,
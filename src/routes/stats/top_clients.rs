@@ -12,7 +12,7 @@ use crate::{
     env::Env,
     ftl::{FtlClient, FtlMemory},
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::common::{remove_excluded_clients, remove_hidden_clients},
     },
     services::PiholeModule,
@@ -27,7 +27,7 @@ pub use top_clients as route;
 /// Get the top clients
 #[get("/stats/top_clients?<params..>")]
 pub fn top_clients(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
     params: TopClientParams,
@@ -12,7 +12,7 @@ use crate::{
     env::Env,
     ftl::{FtlDomain, FtlMemory},
     routes::{
-        auth::User,
+        auth::CanReadStats,
         stats::common::{remove_excluded_domains, remove_hidden_domains},
     },
     services::{domain_audit::DomainAuditRepository, PiholeModule},
@@ -28,7 +28,7 @@ pub use top_domains as route;
 /// Return the top domains
 #[get("/stats/top_domains?<params..>")]
 pub fn top_domains(
-    _auth: User,
+    _auth: CanReadStats,
     ftl_memory: &State<FtlMemory>,
     env: Inject<PiholeModule, Env>,
     params: TopDomainParams,
@@ -0,0 +1,357 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Upstream Latency Endpoint
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    ftl::{FtlMemory, FtlUpstream},
+    routes::{auth::CanReadStats, stats::common::get_current_over_time_slot},
+    util::{reply_result, Error, Reply},
+};
+use rocket::State;
+use std::collections::HashMap;
+
+pub use upstream_latency as route;
+
+/// Get the response time and failure statistics of each upstream, calculated
+/// from the queries in memory. Response times are in tenths of a millisecond.
+#[get("/stats/upstreams/latency")]
+pub fn upstream_latency(_auth: CanReadStats, ftl_memory: &State<FtlMemory>) -> Reply {
+    reply_result(get_upstream_latency(ftl_memory))
+}
+
+/// Represents the reply structure for upstream latency
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct UpstreamLatencyReply {
+    pub upstreams: Vec<UpstreamLatencyItem>,
+}
+
+/// Represents the reply structure for the latency of an upstream
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct UpstreamLatencyItem {
+    pub name: String,
+    pub ip: String,
+    pub count: usize,
+    pub failed_count: usize,
+    /// The fraction of queries sent to the upstream which failed
+    pub failure_rate: f64,
+    /// The response time statistics, or `None` if the upstream has not
+    /// answered any queries
+    pub response_time: Option<ResponseTimeStats>,
+    pub over_time: Vec<UpstreamOverTimeItem>,
+}
+
+/// Response time statistics of answered queries
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct ResponseTimeStats {
+    pub count: usize,
+    pub mean: f64,
+    pub p50: u32,
+    pub p95: u32,
+    pub p99: u32,
+}
+
+impl ResponseTimeStats {
+    /// Calculate the statistics of the response times, or `None` if there are
+    /// none
+    fn new(mut response_times: Vec<u32>) -> Option<Self> {
+        if response_times.is_empty() {
+            return None;
+        }
+
+        response_times.sort_unstable();
+        let sum: u64 = response_times.iter().map(|&time| time as u64).sum();
+
+        Some(ResponseTimeStats {
+            count: response_times.len(),
+            mean: sum as f64 / response_times.len() as f64,
+            p50: percentile(&response_times, 50),
+            p95: percentile(&response_times, 95),
+            p99: percentile(&response_times, 99),
+        })
+    }
+}
+
+/// The queries sent to an upstream in an overTime interval
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct UpstreamOverTimeItem {
+    pub timestamp: u64,
+    pub queries: usize,
+    /// The number of queries which did not receive a response
+    pub unanswered: usize,
+    pub mean_response_time: Option<f64>,
+}
+
+/// The queries sent to an upstream in an overTime slot
+#[derive(Default)]
+struct SlotCounts {
+    queries: usize,
+    answered: usize,
+    total_response_time: u64,
+}
+
+/// Get the value at the percentile of the sorted values, using the
+/// nearest-rank method. The values must not be empty.
+fn percentile(sorted_values: &[u32], percentile: usize) -> u32 {
+    let rank = (percentile * sorted_values.len()).div_ceil(100);
+
+    sorted_values[rank.max(1) - 1]
+}
+
+/// Get the latency and failure statistics of the upstreams
+fn get_upstream_latency(ftl_memory: &FtlMemory) -> Result<UpstreamLatencyReply, Error> {
+    let lock = ftl_memory.lock()?;
+    let counters = ftl_memory.counters(&lock)?;
+    let strings = ftl_memory.strings(&lock)?;
+    let ftl_upstreams = ftl_memory.upstreams(&lock)?;
+    let queries = ftl_memory.queries(&lock)?;
+    let over_time = ftl_memory.over_time(&lock)?;
+
+    // Collect the response times of the forwarded queries, by upstream and
+    // overTime slot
+    let mut response_times: HashMap<usize, Vec<u32>> = HashMap::new();
+    let mut slot_counts: HashMap<(usize, usize), SlotCounts> = HashMap::new();
+
+    for query in queries
+        .iter()
+        .take(counters.total_queries as usize)
+        .filter(|query| query.is_forwarded())
+    {
+        let upstream_id = query.upstream_id as usize;
+        let slot = slot_counts
+            .entry((upstream_id, query.time_index as usize))
+            .or_default();
+        slot.queries += 1;
+
+        // Only completed queries have a final response time
+        if let Some(response_time) = query.get_response_time().filter(|_| query.is_complete) {
+            response_times
+                .entry(upstream_id)
+                .or_default()
+                .push(response_time);
+            slot.answered += 1;
+            slot.total_response_time += response_time as u64;
+        }
+    }
+
+    // Get the overTime slots, skipping the slots without any data
+    let slots: Vec<(usize, u64)> = over_time
+        .iter()
+        .take(get_current_over_time_slot(&over_time) + 1)
+        .enumerate()
+        .skip_while(|(_, time)| time.total_queries <= 0 && time.blocked_queries <= 0)
+        .map(|(i, time)| (i, time.timestamp as u64))
+        .collect();
+
+    // Get an array of valid upstream references (FTL allocates more than it
+    // uses), sorted by count (descending)
+    let mut upstreams: Vec<(usize, &FtlUpstream)> = ftl_upstreams
+        .iter()
+        .take(counters.total_upstreams as usize)
+        .enumerate()
+        // Remove upstreams with a zero count
+        .filter(|(_, upstream)| upstream.query_count > 0)
+        .collect();
+    upstreams.sort_by(|(_, a), (_, b)| b.query_count.cmp(&a.query_count));
+
+    // Map the upstreams into the output format
+    let upstreams = upstreams
+        .into_iter()
+        .map(|(upstream_id, upstream)| {
+            let over_time = slots
+                .iter()
+                .map(|&(i, timestamp)| {
+                    let counts = slot_counts.remove(&(upstream_id, i)).unwrap_or_default();
+
+                    UpstreamOverTimeItem {
+                        timestamp,
+                        queries: counts.queries,
+                        unanswered: counts.queries - counts.answered,
+                        mean_response_time: if counts.answered > 0 {
+                            Some(counts.total_response_time as f64 / counts.answered as f64)
+                        } else {
+                            None
+                        },
+                    }
+                })
+                .collect();
+
+            UpstreamLatencyItem {
+                name: upstream.get_name(&strings).unwrap_or_default().to_owned(),
+                ip: upstream.get_ip(&strings).to_owned(),
+                count: upstream.query_count as usize,
+                failed_count: upstream.failed_count as usize,
+                failure_rate: upstream.failed_count as f64 / upstream.query_count as f64,
+                response_time: ResponseTimeStats::new(
+                    response_times.remove(&upstream_id).unwrap_or_default(),
+                ),
+                over_time,
+            }
+        })
+        .collect();
+
+    Ok(UpstreamLatencyReply { upstreams })
+}
+
+#[cfg(test)]
+mod test {
+    use super::percentile;
+    use crate::{
+        ftl::{
+            FtlCounters, FtlMemory, FtlOverTime, FtlQuery, FtlQueryStatus, FtlSettings, FtlUpstream,
+        },
+        testing::TestBuilder,
+    };
+    use std::collections::HashMap;
+
+    /// Make a query sent to the upstream in the overTime slot
+    fn query(
+        id: i32,
+        status: FtlQueryStatus,
+        upstream_id: i32,
+        time_index: u32,
+        response_time: libc::c_ulong,
+    ) -> FtlQuery {
+        FtlQuery {
+            id,
+            timestamp: time_index as libc::time_t,
+            time_index,
+            response_time,
+            upstream_id,
+            status,
+            ..FtlQuery::default()
+        }
+    }
+
+    /// Three upstreams, one of them unused. The first upstream answered three
+    /// queries and failed to answer one. The second upstream answered one
+    /// query. A cached query is not counted.
+    fn test_data() -> FtlMemory {
+        let mut strings = HashMap::new();
+        strings.insert(1, "8.8.8.8".to_owned());
+        strings.insert(2, "google-public-dns-a.google.com".to_owned());
+        strings.insert(3, "8.8.4.4".to_owned());
+        strings.insert(4, "1.1.1.1".to_owned());
+
+        FtlMemory::Test {
+            upstreams: vec![
+                FtlUpstream::new(4, 1, 1, Some(2)),
+                FtlUpstream::new(1, 0, 3, None),
+                FtlUpstream::new(0, 0, 4, None),
+            ],
+            over_time: vec![
+                FtlOverTime::new(0, 0, 0, 0, 0, [0; 7]),
+                FtlOverTime::new(1, 4, 0, 1, 3, [0; 7]),
+                FtlOverTime::new(2, 2, 0, 0, 2, [0; 7]),
+            ],
+            queries: vec![
+                query(1, FtlQueryStatus::Forward, 0, 1, 10),
+                query(2, FtlQueryStatus::Forward, 0, 1, 30),
+                query(3, FtlQueryStatus::Forward, 0, 1, 20),
+                query(4, FtlQueryStatus::Cache, 0, 1, 1),
+                query(5, FtlQueryStatus::Forward, 0, 2, 18_000_000),
+                query(6, FtlQueryStatus::Forward, 1, 2, 5),
+            ],
+            strings,
+            counters: FtlCounters {
+                total_queries: 6,
+                total_upstreams: 3,
+                ..FtlCounters::default()
+            },
+            clients: Vec::new(),
+            domains: Vec::new(),
+            settings: FtlSettings::default(),
+        }
+    }
+
+    /// The response time statistics, failure rate, and overTime data are
+    /// returned for each used upstream
+    #[test]
+    fn upstream_latency() {
+        TestBuilder::new()
+            .endpoint("/admin/api/stats/upstreams/latency")
+            .ftl_memory(test_data())
+            .expect_json(json!({
+                "upstreams": [
+                    {
+                        "name": "google-public-dns-a.google.com",
+                        "ip": "8.8.8.8",
+                        "count": 4,
+                        "failed_count": 1,
+                        "failure_rate": 0.25,
+                        "response_time": {
+                            "count": 3,
+                            "mean": 20.0,
+                            "p50": 20,
+                            "p95": 30,
+                            "p99": 30
+                        },
+                        "over_time": [
+                            {
+                                "timestamp": 1,
+                                "queries": 3,
+                                "unanswered": 0,
+                                "mean_response_time": 20.0
+                            },
+                            {
+                                "timestamp": 2,
+                                "queries": 1,
+                                "unanswered": 1,
+                                "mean_response_time": null
+                            }
+                        ]
+                    },
+                    {
+                        "name": "",
+                        "ip": "8.8.4.4",
+                        "count": 1,
+                        "failed_count": 0,
+                        "failure_rate": 0.0,
+                        "response_time": {
+                            "count": 1,
+                            "mean": 5.0,
+                            "p50": 5,
+                            "p95": 5,
+                            "p99": 5
+                        },
+                        "over_time": [
+                            {
+                                "timestamp": 1,
+                                "queries": 0,
+                                "unanswered": 0,
+                                "mean_response_time": null
+                            },
+                            {
+                                "timestamp": 2,
+                                "queries": 1,
+                                "unanswered": 0,
+                                "mean_response_time": 5.0
+                            }
+                        ]
+                    }
+                ]
+            }))
+            .test();
+    }
+
+    /// Percentiles use the nearest-rank method
+    #[test]
+    fn percentiles() {
+        let values: Vec<u32> = (1..=100).collect();
+
+        assert_eq!(percentile(&values, 50), 50);
+        assert_eq!(percentile(&values, 95), 95);
+        assert_eq!(percentile(&values, 99), 99);
+        assert_eq!(percentile(&[7], 50), 7);
+        assert_eq!(percentile(&[1, 2, 3], 50), 2);
+    }
+}
@@ -10,7 +10,7 @@
 
 use crate::{
     ftl::{FtlMemory, FtlUpstream},
-    routes::auth::User,
+    routes::auth::CanReadStats,
     util::{reply_data, Reply},
 };
 use rocket::State;
@@ -19,7 +19,7 @@ pub use upstreams as route;
 
 /// Get the upstreams
 #[get("/stats/upstreams")]
-pub fn upstreams(_auth: User, ftl_memory: &State<FtlMemory>) -> Reply {
+pub fn upstreams(_auth: CanReadStats, ftl_memory: &State<FtlMemory>) -> Reply {
     let lock = ftl_memory.lock()?;
     let ftl_upstreams = ftl_memory.upstreams(&lock)?;
     let strings = ftl_memory.strings(&lock)?;
@@ -0,0 +1,74 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Account Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::services::{api_tokens::TokenScope, totp::TotpRecord};
+
+/// The name of the built-in account, which logs in with the web password from
+/// setupVars
+pub const PRIMARY_ACCOUNT: &str = "admin";
+
+/// The role of an account, which determines what it can access
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Full access to the API
+    Admin,
+    /// Read-only access to statistics and settings
+    Viewer,
+}
+
+impl Role {
+    /// Check if the role grants the same access as the token scope. Admins
+    /// have every scope, while viewers can only read statistics.
+    pub fn has_scope(self, scope: TokenScope) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Viewer => scope == TokenScope::StatsRead,
+        }
+    }
+}
+
+/// A named account which can log in to the API
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Account {
+    /// Get the built-in admin account
+    pub fn primary() -> Account {
+        Account {
+            name: PRIMARY_ACCOUNT.to_owned(),
+            role: Role::Admin,
+        }
+    }
+}
+
+/// An account as it is stored on disk. The primary account is only stored
+/// when it has two-factor authentication data, and has no password hash since
+/// its password is in setupVars.
+#[derive(Serialize, Deserialize)]
+pub struct AccountRecord {
+    #[serde(flatten)]
+    pub account: Account,
+    /// The Argon2 hash of the account's password
+    pub hash: String,
+    /// The account's two-factor authentication data, if it has enrolled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<TotpRecord>,
+}
+
+impl AccountRecord {
+    /// Check if this is the record of the primary account
+    pub fn is_primary(&self) -> bool {
+        self.account.name == PRIMARY_ACCOUNT
+    }
+}
@@ -0,0 +1,91 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Password Hashing
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    settings::ARGON2_PREFIX,
+    util::{Error, ErrorKind},
+};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::rngs::OsRng;
+use subtle::ConstantTimeEq;
+
+/// Hash the password with Argon2 and a random salt. The result is a PHC
+/// string, which includes the parameters and salt.
+pub fn hash_password(password: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| Error::from(ErrorKind::Unknown))
+}
+
+/// Check the password against the stored value. The stored value is either an
+/// Argon2 hash, or a legacy value which the password must match exactly. Both
+/// comparisons are done in constant time.
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    if is_hashed(stored) {
+        PasswordHash::new(stored)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    } else {
+        password.as_bytes().ct_eq(stored.as_bytes()).into()
+    }
+}
+
+/// Check if the stored value is an Argon2 hash instead of a legacy value
+pub fn is_hashed(stored: &str) -> bool {
+    stored.starts_with(ARGON2_PREFIX)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash_password, is_hashed, verify_password};
+
+    /// A hashed password can be verified, but not with the wrong password
+    #[test]
+    fn hash_and_verify() {
+        let hash = hash_password("hunter2").unwrap();
+
+        assert!(is_hashed(&hash));
+        assert!(verify_password("hunter2", &hash));
+        assert!(!verify_password("hunter3", &hash));
+    }
+
+    /// The same password hashes differently each time because of the salt
+    #[test]
+    fn salted() {
+        assert_ne!(
+            hash_password("hunter2").unwrap(),
+            hash_password("hunter2").unwrap()
+        );
+    }
+
+    /// Legacy values must match exactly
+    #[test]
+    fn legacy() {
+        assert!(verify_password("test_key", "test_key"));
+        assert!(!verify_password("test_ke", "test_key"));
+        assert!(!verify_password("TEST_KEY", "test_key"));
+    }
+
+    /// A malformed hash never matches
+    #[test]
+    fn malformed_hash() {
+        assert!(!verify_password("$argon2id$", "$argon2id$"));
+    }
+}
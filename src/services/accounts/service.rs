@@ -0,0 +1,447 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Account Service
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::{Env, PiholeFile},
+    services::{
+        accounts::{hash_password, verify_password, Account, AccountRecord, Role, PRIMARY_ACCOUNT},
+        api_tokens::is_valid_name,
+        totp::TotpRecord,
+    },
+    util::{Error, ErrorKind},
+};
+use failure::ResultExt;
+use shaku::Provider;
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Held while the stored accounts are changed, so concurrent requests do not
+/// overwrite each other's changes
+static ACCOUNTS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Describes interactions with the named accounts. The primary account is
+/// managed through setupVars and is not included, except for its two-factor
+/// authentication data.
+#[cfg_attr(test, mockall::automock)]
+pub trait AccountService: Send {
+    /// Get all of the named accounts
+    fn list(&self) -> Result<Vec<Account>, Error>;
+
+    /// Create a new account
+    fn create(&self, name: &str, role: Role, password: &str) -> Result<Account, Error>;
+
+    /// Delete the account with the given name
+    fn delete(&self, name: &str) -> Result<(), Error>;
+
+    /// Find the account with the given name, including the primary account
+    fn get(&self, name: &str) -> Result<Option<Account>, Error>;
+
+    /// Find the account if the password is correct
+    fn verify(&self, name: &str, password: &str) -> Result<Option<Account>, Error>;
+
+    /// Change the password of an account after checking the old one
+    fn change_password(
+        &self,
+        name: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<(), Error>;
+
+    /// Get the two-factor authentication data of an account, including the
+    /// primary account
+    fn get_totp(&self, name: &str) -> Result<Option<TotpRecord>, Error>;
+
+    /// Replace the two-factor authentication data of an account, including
+    /// the primary account
+    fn set_totp(&self, name: &str, totp: Option<TotpRecord>) -> Result<(), Error>;
+}
+
+/// The implementation of `AccountService`, which stores the accounts in a JSON
+/// file
+#[derive(Provider)]
+#[shaku(interface = AccountService)]
+pub struct AccountServiceImpl {
+    #[shaku(inject)]
+    env: Arc<Env>,
+}
+
+impl AccountService for AccountServiceImpl {
+    fn list(&self) -> Result<Vec<Account>, Error> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .filter(|record| !record.is_primary())
+            .map(|record| record.account)
+            .collect())
+    }
+
+    fn create(&self, name: &str, role: Role, password: &str) -> Result<Account, Error> {
+        if !is_valid_name(name) || password.is_empty() {
+            return Err(Error::from(ErrorKind::BadRequest));
+        }
+
+        let _lock = lock();
+        let mut records = self.read_records()?;
+
+        if name == PRIMARY_ACCOUNT || records.iter().any(|record| record.account.name == name) {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+
+        let account = Account {
+            name: name.to_owned(),
+            role,
+        };
+
+        records.push(AccountRecord {
+            account: account.clone(),
+            hash: hash_password(password)?,
+            totp: None,
+        });
+        self.write_records(&records)?;
+
+        Ok(account)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), Error> {
+        // The primary account can not be deleted
+        if name == PRIMARY_ACCOUNT {
+            return Err(Error::from(ErrorKind::BadRequest));
+        }
+
+        let _lock = lock();
+        let mut records = self.read_records()?;
+        let len_before = records.len();
+
+        records.retain(|record| record.account.name != name);
+
+        if records.len() == len_before {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        self.write_records(&records)
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Account>, Error> {
+        if name == PRIMARY_ACCOUNT {
+            return Ok(Some(Account::primary()));
+        }
+
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .find(|record| record.account.name == name && !record.is_primary())
+            .map(|record| record.account))
+    }
+
+    fn verify(&self, name: &str, password: &str) -> Result<Option<Account>, Error> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .find(|record| record.account.name == name && !record.is_primary())
+            .filter(|record| verify_password(password, &record.hash))
+            .map(|record| record.account))
+    }
+
+    fn change_password(
+        &self,
+        name: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<(), Error> {
+        if new_password.is_empty() {
+            return Err(Error::from(ErrorKind::BadRequest));
+        }
+
+        let _lock = lock();
+        let mut records = self.read_records()?;
+        let record = records
+            .iter_mut()
+            .find(|record| record.account.name == name && !record.is_primary())
+            .ok_or(ErrorKind::NotFound)?;
+
+        if !verify_password(old_password, &record.hash) {
+            return Err(Error::from(ErrorKind::Unauthorized));
+        }
+
+        record.hash = hash_password(new_password)?;
+        self.write_records(&records)
+    }
+
+    fn get_totp(&self, name: &str) -> Result<Option<TotpRecord>, Error> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .find(|record| record.account.name == name)
+            .and_then(|record| record.totp))
+    }
+
+    fn set_totp(&self, name: &str, totp: Option<TotpRecord>) -> Result<(), Error> {
+        let _lock = lock();
+        let mut records = self.read_records()?;
+
+        match records
+            .iter_mut()
+            .find(|record| record.account.name == name)
+        {
+            Some(record) => record.totp = totp,
+            // The primary account is only stored once it has data
+            None if name == PRIMARY_ACCOUNT => {
+                if totp.is_none() {
+                    return Ok(());
+                }
+
+                records.push(AccountRecord {
+                    account: Account::primary(),
+                    hash: String::new(),
+                    totp,
+                });
+            }
+            None => return Err(Error::from(ErrorKind::NotFound)),
+        }
+
+        // The primary account is not stored without data
+        records.retain(|record| !record.is_primary() || record.totp.is_some());
+
+        self.write_records(&records)
+    }
+}
+
+/// Take the lock on the stored accounts. The accounts are still usable if
+/// another request panicked while holding the lock.
+fn lock() -> MutexGuard<'static, ()> {
+    ACCOUNTS_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl AccountServiceImpl {
+    /// Read the stored accounts. If the file does not exist, there are no
+    /// named accounts.
+    fn read_records(&self) -> Result<Vec<AccountRecord>, Error> {
+        if !self.env.file_exists(PiholeFile::Accounts) {
+            return Ok(Vec::new());
+        }
+
+        let mut data = String::new();
+        self.env
+            .read_file(PiholeFile::Accounts)?
+            .read_to_string(&mut data)
+            .context(ErrorKind::FileRead(
+                self.env.file_location(PiholeFile::Accounts).to_owned(),
+            ))?;
+
+        if data.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let records = serde_json::from_str(&data).context(ErrorKind::FileRead(
+            self.env.file_location(PiholeFile::Accounts).to_owned(),
+        ))?;
+
+        Ok(records)
+    }
+
+    /// Overwrite the stored accounts
+    fn write_records(&self, records: &[AccountRecord]) -> Result<(), Error> {
+        let file_location = self.env.file_location(PiholeFile::Accounts).to_owned();
+        let mut file = self.env.write_file(PiholeFile::Accounts, false)?;
+        let data =
+            serde_json::to_string(records).context(ErrorKind::FileWrite(file_location.clone()))?;
+
+        file.write_all(data.as_bytes())
+            .context(ErrorKind::FileWrite(file_location))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AccountService, AccountServiceImpl};
+    use crate::{
+        env::PiholeFile,
+        services::{
+            accounts::{Account, Role},
+            api_tokens::TokenScope,
+            totp::TotpRecord,
+        },
+        testing::TestEnvBuilder,
+        util::ErrorKind,
+    };
+    use std::sync::Arc;
+
+    fn service(accounts: &str) -> AccountServiceImpl {
+        AccountServiceImpl {
+            env: Arc::new(
+                TestEnvBuilder::new()
+                    .file(PiholeFile::Accounts, accounts)
+                    .build(),
+            ),
+        }
+    }
+
+    fn viewer() -> Account {
+        Account {
+            name: "viewer".to_owned(),
+            role: Role::Viewer,
+        }
+    }
+
+    /// A created account can log in with its password
+    #[test]
+    fn create_and_verify() {
+        let service = service("");
+
+        assert_eq!(
+            service.create("viewer", Role::Viewer, "hunter2").unwrap(),
+            viewer()
+        );
+        assert_eq!(service.list().unwrap(), vec![viewer()]);
+        assert_eq!(service.verify("viewer", "hunter2").unwrap(), Some(viewer()));
+        assert_eq!(service.verify("viewer", "hunter3").unwrap(), None);
+        assert_eq!(service.verify("nobody", "hunter2").unwrap(), None);
+    }
+
+    /// Accounts are found by name, and the primary account always exists
+    #[test]
+    fn get() {
+        let service = service("");
+        service.create("viewer", Role::Viewer, "hunter2").unwrap();
+
+        assert_eq!(service.get("viewer").unwrap(), Some(viewer()));
+        assert_eq!(service.get("admin").unwrap(), Some(Account::primary()));
+        assert_eq!(service.get("nobody").unwrap(), None);
+    }
+
+    /// Account names must be unique, and the primary account name is reserved
+    #[test]
+    fn create_duplicate() {
+        let service = service("");
+        service.create("viewer", Role::Viewer, "hunter2").unwrap();
+
+        for name in ["viewer", "admin"] {
+            assert_eq!(
+                service
+                    .create(name, Role::Admin, "hunter2")
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::AlreadyExists
+            );
+        }
+    }
+
+    /// Accounts need a valid name and a password
+    #[test]
+    fn create_invalid() {
+        let service = service("");
+
+        for (name, password) in [("", "hunter2"), ("has space", "hunter2"), ("viewer", "")] {
+            assert_eq!(
+                service
+                    .create(name, Role::Viewer, password)
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::BadRequest
+            );
+        }
+    }
+
+    /// Deleting an account removes it, but the primary account can not be
+    /// deleted
+    #[test]
+    fn delete() {
+        let service = service("");
+        service.create("viewer", Role::Viewer, "hunter2").unwrap();
+
+        service.delete("viewer").unwrap();
+
+        assert_eq!(service.list().unwrap(), Vec::new());
+        assert_eq!(
+            service.delete("viewer").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            service.delete("admin").unwrap_err().kind(),
+            ErrorKind::BadRequest
+        );
+    }
+
+    /// Changing the password requires the old password
+    #[test]
+    fn change_password() {
+        let service = service("");
+        service.create("viewer", Role::Viewer, "hunter2").unwrap();
+
+        assert_eq!(
+            service
+                .change_password("viewer", "wrong", "hunter3")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Unauthorized
+        );
+        service
+            .change_password("viewer", "hunter2", "hunter3")
+            .unwrap();
+
+        assert_eq!(service.verify("viewer", "hunter2").unwrap(), None);
+        assert_eq!(service.verify("viewer", "hunter3").unwrap(), Some(viewer()));
+    }
+
+    fn totp_record() -> TotpRecord {
+        serde_json::from_value(json!({
+            "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+            "confirmed": true,
+            "last_step": 0,
+            "recovery_codes": []
+        }))
+        .unwrap()
+    }
+
+    /// Two-factor authentication data is stored per account, and the primary
+    /// account is stored without being listed or able to log in
+    #[test]
+    fn totp_per_account() {
+        let service = service("");
+        service.create("viewer", Role::Viewer, "hunter2").unwrap();
+
+        service.set_totp("admin", Some(totp_record())).unwrap();
+
+        assert_eq!(service.get_totp("admin").unwrap(), Some(totp_record()));
+        assert_eq!(service.get_totp("viewer").unwrap(), None);
+        assert_eq!(service.list().unwrap(), vec![viewer()]);
+        assert_eq!(service.verify("admin", "").unwrap(), None);
+        assert_eq!(
+            service
+                .change_password("admin", "", "hunter3")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+
+        service.set_totp("viewer", Some(totp_record())).unwrap();
+        service.set_totp("admin", None).unwrap();
+
+        assert_eq!(service.get_totp("admin").unwrap(), None);
+        assert_eq!(service.get_totp("viewer").unwrap(), Some(totp_record()));
+        assert_eq!(service.verify("viewer", "hunter2").unwrap(), Some(viewer()));
+        assert_eq!(
+            service.set_totp("nobody", None).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Viewers can only read statistics
+    #[test]
+    fn viewer_scopes() {
+        assert!(Role::Viewer.has_scope(TokenScope::StatsRead));
+        assert!(!Role::Viewer.has_scope(TokenScope::ListsWrite));
+        assert!(Role::Admin.has_scope(TokenScope::DnsStatus));
+    }
+}
@@ -0,0 +1,44 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// An adlist (blocklist source) which gravity downloads domains from
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Adlist {
+    pub id: i32,
+    pub address: String,
+    pub enabled: bool,
+    pub comment: Option<String>,
+    pub date_added: i32,
+    pub date_modified: i32,
+    /// The IDs of the groups the adlist is assigned to
+    pub groups: Vec<i32>,
+}
+
+/// The properties of a new adlist
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdlistInput {
+    pub address: String,
+    pub comment: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Changes to an existing adlist. Properties which are not given are left
+/// unchanged, and an empty comment removes the comment.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AdlistPatch {
+    pub enabled: Option<bool>,
+    pub comment: Option<String>,
+}
+
+/// Adlists are enabled unless specified otherwise
+fn default_enabled() -> bool {
+    true
+}
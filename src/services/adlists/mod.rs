@@ -0,0 +1,16 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+mod adlist;
+mod repository;
+mod status;
+mod status_repository;
+
+pub use self::{adlist::*, repository::*, status::*, status_repository::*};
@@ -0,0 +1,319 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Database Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::{
+        gravity::{adlist, adlist_by_group, GravityDatabase},
+        map_gravity_insert_error,
+    },
+    services::adlists::{Adlist, AdlistInput, AdlistPatch},
+    util::{Error, ErrorKind},
+};
+use diesel::{delete, insert_into, prelude::*, result::Error as DieselError, update};
+use failure::ResultExt;
+use shaku::Provider;
+use std::collections::HashMap;
+
+/// An adlist as it is stored in the database, without its groups
+#[derive(Queryable)]
+struct AdlistRecord {
+    id: i32,
+    address: String,
+    enabled: bool,
+    date_added: i32,
+    date_modified: i32,
+    comment: Option<String>,
+}
+
+impl AdlistRecord {
+    /// Combine the record with its group assignments
+    fn into_adlist(self, groups: Vec<i32>) -> Adlist {
+        Adlist {
+            id: self.id,
+            address: self.address,
+            enabled: self.enabled,
+            comment: self.comment,
+            date_added: self.date_added,
+            date_modified: self.date_modified,
+            groups,
+        }
+    }
+}
+
+/// Describes interactions with the adlist data store
+#[cfg_attr(test, mockall::automock)]
+pub trait AdlistRepository: Send {
+    /// Get all of the adlists
+    fn get_all(&self) -> Result<Vec<Adlist>, Error>;
+
+    /// Get a single adlist
+    fn get(&self, adlist_id: i32) -> Result<Adlist, Error>;
+
+    /// Add an adlist. The address is not validated.
+    fn add(&self, input: &AdlistInput) -> Result<Adlist, Error>;
+
+    /// Change the enabled status or comment of an adlist
+    fn update(&self, adlist_id: i32, patch: &AdlistPatch) -> Result<(), Error>;
+
+    /// Delete an adlist and its group assignments
+    fn remove(&self, adlist_id: i32) -> Result<(), Error>;
+}
+
+/// The implementation of `AdlistRepository`
+#[derive(Provider)]
+#[shaku(interface = AdlistRepository)]
+pub struct AdlistRepositoryImpl {
+    #[shaku(provide)]
+    db: Box<GravityDatabase>,
+}
+
+impl AdlistRepository for AdlistRepositoryImpl {
+    fn get_all(&self) -> Result<Vec<Adlist>, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let records: Vec<AdlistRecord> = adlist::table
+            .order(adlist::id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+        let assignments: Vec<(i32, i32)> = adlist_by_group::table
+            .order((adlist_by_group::adlist_id, adlist_by_group::group_id))
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        // Group the assignments by adlist
+        let mut groups: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (adlist_id, group_id) in assignments {
+            groups.entry(adlist_id).or_default().push(group_id);
+        }
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                let adlist_groups = groups.remove(&record.id).unwrap_or_default();
+                record.into_adlist(adlist_groups)
+            })
+            .collect())
+    }
+
+    fn get(&self, adlist_id: i32) -> Result<Adlist, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let record: AdlistRecord = adlist::table
+            .find(adlist_id)
+            .first(db)
+            .optional()
+            .context(ErrorKind::GravityDatabase)?
+            .ok_or(ErrorKind::NotFound)?;
+        let groups = adlist_by_group::table
+            .filter(adlist_by_group::adlist_id.eq(adlist_id))
+            .select(adlist_by_group::group_id)
+            .order(adlist_by_group::group_id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        Ok(record.into_adlist(groups))
+    }
+
+    fn add(&self, input: &AdlistInput) -> Result<Adlist, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        // SQLite does not return the new ID, so find it in the same transaction
+        let adlist_id = db
+            .transaction(|| {
+                insert_into(adlist::table)
+                    .values((
+                        adlist::address.eq(&input.address),
+                        adlist::enabled.eq(input.enabled),
+                        adlist::comment.eq(&input.comment),
+                    ))
+                    .execute(db)?;
+
+                adlist::table
+                    .select(adlist::id)
+                    .order(adlist::id.desc())
+                    .first::<i32>(db)
+            })
+            .map_err(map_gravity_insert_error)?;
+
+        self.get(adlist_id)
+    }
+
+    fn update(&self, adlist_id: i32, patch: &AdlistPatch) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        // Make sure the adlist exists, since an empty patch updates nothing
+        self.get(adlist_id)?;
+
+        db.transaction::<_, DieselError, _>(|| {
+            if let Some(enabled) = patch.enabled {
+                update(adlist::table.find(adlist_id))
+                    .set(adlist::enabled.eq(enabled))
+                    .execute(db)?;
+            }
+
+            if let Some(comment) = &patch.comment {
+                let comment = if comment.is_empty() {
+                    None
+                } else {
+                    Some(comment)
+                };
+
+                update(adlist::table.find(adlist_id))
+                    .set(adlist::comment.eq(comment))
+                    .execute(db)?;
+            }
+
+            Ok(())
+        })
+        .context(ErrorKind::GravityDatabase)?;
+
+        Ok(())
+    }
+
+    fn remove(&self, adlist_id: i32) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        // The group assignments reference the adlist, so they are removed
+        // first
+        let deleted = db
+            .transaction(|| {
+                delete(adlist_by_group::table.filter(adlist_by_group::adlist_id.eq(adlist_id)))
+                    .execute(db)?;
+
+                delete(adlist::table.find(adlist_id)).execute(db)
+            })
+            .context(ErrorKind::GravityDatabase)?;
+
+        if deleted == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdlistRepository, AdlistRepositoryImpl};
+    use crate::{
+        databases::gravity::connect_to_gravity_test_db,
+        services::adlists::{AdlistInput, AdlistPatch},
+        util::ErrorKind,
+    };
+
+    fn repo() -> AdlistRepositoryImpl {
+        AdlistRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        }
+    }
+
+    /// All adlists are retrieved with their groups
+    #[test]
+    fn get_all() {
+        let adlists = repo().get_all().unwrap();
+
+        assert_eq!(adlists.len(), 2);
+        assert_eq!(
+            adlists[0].address,
+            "https://raw.githubusercontent.com/StevenBlack/hosts/master/hosts"
+        );
+        assert_eq!(adlists[0].date_added, 1557712118);
+        assert_eq!(adlists[0].groups, vec![1]);
+        assert_eq!(adlists[1].groups, Vec::<i32>::new());
+    }
+
+    /// A missing adlist is not found
+    #[test]
+    fn get_missing() {
+        assert_eq!(repo().get(10).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    /// A new adlist is given the next ID
+    #[test]
+    fn add() {
+        let adlist = repo()
+            .add(&AdlistInput {
+                address: "https://example.com/hosts".to_owned(),
+                comment: Some("Example".to_owned()),
+                enabled: false,
+            })
+            .unwrap();
+
+        assert_eq!(adlist.id, 3);
+        assert_eq!(adlist.address, "https://example.com/hosts");
+        assert_eq!(adlist.comment, Some("Example".to_owned()));
+        assert!(!adlist.enabled);
+        assert_eq!(adlist.groups, Vec::<i32>::new());
+    }
+
+    /// Adding an existing address is an error
+    #[test]
+    fn add_duplicate() {
+        assert_eq!(
+            repo()
+                .add(&AdlistInput {
+                    address: "https://mirror1.malwaredomains.com/files/justdomains".to_owned(),
+                    comment: None,
+                    enabled: true,
+                })
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    /// Only the given properties are changed, and an empty comment removes the
+    /// comment
+    #[test]
+    fn update() {
+        let repo = repo();
+
+        repo.update(
+            1,
+            &AdlistPatch {
+                enabled: Some(false),
+                comment: None,
+            },
+        )
+        .unwrap();
+        let adlist = repo.get(1).unwrap();
+        assert!(!adlist.enabled);
+        assert_eq!(
+            adlist.comment,
+            Some("Migrated from /etc/pihole/adlists.list".to_owned())
+        );
+
+        repo.update(
+            1,
+            &AdlistPatch {
+                enabled: None,
+                comment: Some(String::new()),
+            },
+        )
+        .unwrap();
+        assert_eq!(repo.get(1).unwrap().comment, None);
+
+        assert_eq!(
+            repo.update(10, &AdlistPatch::default()).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Removing an adlist also removes its group assignments
+    #[test]
+    fn remove() {
+        let repo = repo();
+
+        repo.remove(1).unwrap();
+
+        assert_eq!(repo.get_all().unwrap().len(), 1);
+        assert_eq!(repo.remove(1).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
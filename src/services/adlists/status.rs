@@ -0,0 +1,84 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Health Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::services::adlists::Adlist;
+
+/// How well an adlist worked during the last gravity update
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdlistHealth {
+    /// The adlist was downloaded and has domains
+    Ok,
+    /// The adlist was downloaded, but has no domains or is mostly invalid
+    /// lines (for example an HTML error page)
+    Degraded,
+    /// The adlist could not be downloaded
+    Failed,
+}
+
+impl AdlistHealth {
+    /// Judge the health of an adlist from the outcome of its update
+    pub fn judge(domains: usize, invalid_lines: usize, failed: bool) -> Self {
+        if failed {
+            AdlistHealth::Failed
+        } else if domains == 0 || invalid_lines > domains {
+            AdlistHealth::Degraded
+        } else {
+            AdlistHealth::Ok
+        }
+    }
+}
+
+/// The outcome of the last time an adlist was fetched
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdlistStatus {
+    pub health: AdlistHealth,
+    /// When the adlist was last fetched
+    pub last_fetched: u64,
+    /// The HTTP status of the response, if the adlist was fetched over HTTP
+    pub http_status: Option<u16>,
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the response, if the server sent one
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// The SHA-256 checksum of the adlist's contents
+    pub checksum: Option<String>,
+    /// If the contents are the same as the previous fetch
+    pub unchanged: bool,
+    pub domains: usize,
+    pub invalid_lines: usize,
+    /// The first few invalid lines
+    pub invalid_samples: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// An adlist along with its status. The status is `None` if the adlist has
+/// not been fetched yet.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdlistDetails {
+    #[serde(flatten)]
+    pub adlist: Adlist,
+    pub status: Option<AdlistStatus>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::AdlistHealth;
+
+    /// Failed downloads, empty lists, and lists of mostly invalid lines are
+    /// flagged
+    #[test]
+    fn judge() {
+        assert_eq!(AdlistHealth::judge(100, 1, false), AdlistHealth::Ok);
+        assert_eq!(AdlistHealth::judge(100, 0, true), AdlistHealth::Failed);
+        assert_eq!(AdlistHealth::judge(0, 0, false), AdlistHealth::Degraded);
+        assert_eq!(AdlistHealth::judge(2, 30, false), AdlistHealth::Degraded);
+    }
+}
@@ -0,0 +1,156 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Status Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::{Env, PiholeFile},
+    services::adlists::AdlistStatus,
+    util::{Error, ErrorKind},
+};
+use failure::ResultExt;
+use shaku::Provider;
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    sync::Arc,
+};
+
+/// Describes interactions with the stored adlist statuses, keyed by adlist ID
+#[cfg_attr(test, mockall::automock)]
+pub trait AdlistStatusRepository: Send {
+    /// Get the status of every adlist which has been fetched
+    fn get_all(&self) -> Result<BTreeMap<i32, AdlistStatus>, Error>;
+
+    /// Replace all of the statuses
+    fn save(&self, statuses: &BTreeMap<i32, AdlistStatus>) -> Result<(), Error>;
+
+    /// Forget the status of an adlist. It is not an error if the adlist has no
+    /// status.
+    fn remove(&self, adlist_id: i32) -> Result<(), Error>;
+}
+
+/// The implementation of `AdlistStatusRepository`, which stores the statuses
+/// in a JSON file. The gravity database is owned by Pi-hole's core, so the
+/// statuses are not added to it.
+#[derive(Provider)]
+#[shaku(interface = AdlistStatusRepository)]
+pub struct AdlistStatusRepositoryImpl {
+    #[shaku(inject)]
+    env: Arc<Env>,
+}
+
+impl AdlistStatusRepository for AdlistStatusRepositoryImpl {
+    fn get_all(&self) -> Result<BTreeMap<i32, AdlistStatus>, Error> {
+        if !self.env.file_exists(PiholeFile::AdlistStatus) {
+            return Ok(BTreeMap::new());
+        }
+
+        let mut data = String::new();
+        self.env
+            .read_file(PiholeFile::AdlistStatus)?
+            .read_to_string(&mut data)
+            .context(ErrorKind::FileRead(
+                self.env.file_location(PiholeFile::AdlistStatus).to_owned(),
+            ))?;
+
+        if data.trim().is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let statuses = serde_json::from_str(&data).context(ErrorKind::FileRead(
+            self.env.file_location(PiholeFile::AdlistStatus).to_owned(),
+        ))?;
+
+        Ok(statuses)
+    }
+
+    fn save(&self, statuses: &BTreeMap<i32, AdlistStatus>) -> Result<(), Error> {
+        let file_location = self.env.file_location(PiholeFile::AdlistStatus).to_owned();
+        let mut file = self.env.write_file(PiholeFile::AdlistStatus, false)?;
+        let data =
+            serde_json::to_string(statuses).context(ErrorKind::FileWrite(file_location.clone()))?;
+
+        file.write_all(data.as_bytes())
+            .context(ErrorKind::FileWrite(file_location))?;
+
+        Ok(())
+    }
+
+    fn remove(&self, adlist_id: i32) -> Result<(), Error> {
+        let mut statuses = self.get_all()?;
+
+        if statuses.remove(&adlist_id).is_none() {
+            return Ok(());
+        }
+
+        self.save(&statuses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdlistStatusRepository, AdlistStatusRepositoryImpl};
+    use crate::{
+        env::PiholeFile,
+        services::adlists::{AdlistHealth, AdlistStatus},
+        testing::TestEnvBuilder,
+    };
+    use std::{collections::BTreeMap, sync::Arc};
+
+    fn repo(statuses: &str) -> AdlistStatusRepositoryImpl {
+        AdlistStatusRepositoryImpl {
+            env: Arc::new(
+                TestEnvBuilder::new()
+                    .file(PiholeFile::AdlistStatus, statuses)
+                    .build(),
+            ),
+        }
+    }
+
+    fn status() -> AdlistStatus {
+        AdlistStatus {
+            health: AdlistHealth::Failed,
+            last_fetched: 1557712118,
+            http_status: Some(404),
+            etag: None,
+            last_modified: None,
+            checksum: None,
+            unchanged: false,
+            domains: 0,
+            invalid_lines: 0,
+            invalid_samples: Vec::new(),
+            error: Some("Failed to download the adlist: HTTP status 404".to_owned()),
+        }
+    }
+
+    /// If the status file does not exist, no adlist has a status
+    #[test]
+    fn get_all_missing_file() {
+        let repo = AdlistStatusRepositoryImpl {
+            env: Arc::new(TestEnvBuilder::new().build()),
+        };
+
+        assert_eq!(repo.get_all().unwrap(), BTreeMap::new());
+        repo.remove(1).unwrap();
+    }
+
+    /// Saved statuses are read back, and can be removed
+    #[test]
+    fn save_and_remove() {
+        let repo = repo("");
+        let statuses: BTreeMap<i32, AdlistStatus> =
+            vec![(1, status()), (2, status())].into_iter().collect();
+
+        repo.save(&statuses).unwrap();
+        assert_eq!(repo.get_all().unwrap(), statuses);
+
+        repo.remove(1).unwrap();
+        assert_eq!(repo.get_all().unwrap().keys().collect::<Vec<_>>(), vec![&2]);
+    }
+}
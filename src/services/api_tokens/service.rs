@@ -0,0 +1,324 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// API Token Service
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    env::{Env, PiholeFile},
+    services::api_tokens::{ApiToken, ApiTokenRecord, TokenScope},
+    util::{Error, ErrorKind},
+};
+use failure::ResultExt;
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use shaku::Provider;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The number of random bytes used to generate a token secret
+const SECRET_BYTES: usize = 32;
+
+/// The maximum length of a token name
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Describes interactions with the API tokens, which grant scoped access to
+/// the API without a session
+#[cfg_attr(test, mockall::automock)]
+pub trait ApiTokenService: Send {
+    /// Get all of the tokens
+    fn list(&self) -> Result<Vec<ApiToken>, Error>;
+
+    /// Create a new token with the given scopes. The secret is returned along
+    /// with the token, and can not be retrieved again.
+    fn create(&self, name: &str, scopes: &[TokenScope]) -> Result<(String, ApiToken), Error>;
+
+    /// Delete the token with the given name
+    fn delete(&self, name: &str) -> Result<(), Error>;
+
+    /// Find the token which has the given secret
+    fn verify(&self, secret: &str) -> Result<Option<ApiToken>, Error>;
+}
+
+/// The implementation of `ApiTokenService`, which stores the tokens in a JSON
+/// file
+#[derive(Provider)]
+#[shaku(interface = ApiTokenService)]
+pub struct ApiTokenServiceImpl {
+    #[shaku(inject)]
+    env: Arc<Env>,
+}
+
+impl ApiTokenService for ApiTokenServiceImpl {
+    fn list(&self) -> Result<Vec<ApiToken>, Error> {
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .map(|record| record.token)
+            .collect())
+    }
+
+    fn create(&self, name: &str, scopes: &[TokenScope]) -> Result<(String, ApiToken), Error> {
+        if !is_valid_name(name) || scopes.is_empty() {
+            return Err(Error::from(ErrorKind::BadRequest));
+        }
+
+        let mut records = self.read_records()?;
+
+        if records.iter().any(|record| record.token.name == name) {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+
+        // Remove duplicate scopes, keeping the original order
+        let mut unique_scopes = Vec::new();
+        for scope in scopes {
+            if !unique_scopes.contains(scope) {
+                unique_scopes.push(*scope);
+            }
+        }
+
+        let secret = generate_secret();
+        let token = ApiToken {
+            name: name.to_owned(),
+            scopes: unique_scopes,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Current time is older than epoch")
+                .as_secs(),
+        };
+
+        records.push(ApiTokenRecord {
+            token: token.clone(),
+            hash: hash_secret(&secret),
+        });
+        self.write_records(&records)?;
+
+        Ok((secret, token))
+    }
+
+    fn delete(&self, name: &str) -> Result<(), Error> {
+        let mut records = self.read_records()?;
+        let len_before = records.len();
+
+        records.retain(|record| record.token.name != name);
+
+        if records.len() == len_before {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        self.write_records(&records)
+    }
+
+    fn verify(&self, secret: &str) -> Result<Option<ApiToken>, Error> {
+        let hash = hash_secret(secret);
+
+        Ok(self
+            .read_records()?
+            .into_iter()
+            .find(|record| record.hash == hash)
+            .map(|record| record.token))
+    }
+}
+
+impl ApiTokenServiceImpl {
+    /// Read the stored tokens. If the file does not exist, there are no
+    /// tokens.
+    fn read_records(&self) -> Result<Vec<ApiTokenRecord>, Error> {
+        if !self.env.file_exists(PiholeFile::ApiTokens) {
+            return Ok(Vec::new());
+        }
+
+        let mut data = String::new();
+        self.env
+            .read_file(PiholeFile::ApiTokens)?
+            .read_to_string(&mut data)
+            .context(ErrorKind::FileRead(
+                self.env.file_location(PiholeFile::ApiTokens).to_owned(),
+            ))?;
+
+        if data.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let records = serde_json::from_str(&data).context(ErrorKind::FileRead(
+            self.env.file_location(PiholeFile::ApiTokens).to_owned(),
+        ))?;
+
+        Ok(records)
+    }
+
+    /// Overwrite the stored tokens
+    fn write_records(&self, records: &[ApiTokenRecord]) -> Result<(), Error> {
+        let file_location = self.env.file_location(PiholeFile::ApiTokens).to_owned();
+        let mut file = self.env.write_file(PiholeFile::ApiTokens, false)?;
+        let data =
+            serde_json::to_string(records).context(ErrorKind::FileWrite(file_location.clone()))?;
+
+        file.write_all(data.as_bytes())
+            .context(ErrorKind::FileWrite(file_location))?;
+
+        Ok(())
+    }
+}
+
+/// Check if the name is non-empty, not too long, and only uses letters,
+/// numbers, dashes, and underscores
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Generate a random token secret
+fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+
+    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Hash a secret with SHA-256 and encode it as hex. Secrets are random, so a
+/// fast hash without a salt is enough.
+pub fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash_secret, ApiTokenService, ApiTokenServiceImpl};
+    use crate::{
+        env::PiholeFile,
+        services::api_tokens::{ApiToken, TokenScope},
+        testing::TestEnvBuilder,
+        util::ErrorKind,
+    };
+    use std::sync::Arc;
+
+    const TEST_TOKENS: &str = "[{\"name\":\"automation\",\"scopes\":[\"lists:write\"],\
+                               \"created\":1000,\"hash\":\"cc0af97287543b65da2c7e1476426021826cab166f1e063ed012b855ff819656\"}]";
+
+    fn service(tokens: &str) -> ApiTokenServiceImpl {
+        ApiTokenServiceImpl {
+            env: Arc::new(
+                TestEnvBuilder::new()
+                    .file(PiholeFile::ApiTokens, tokens)
+                    .build(),
+            ),
+        }
+    }
+
+    fn automation_token() -> ApiToken {
+        ApiToken {
+            name: "automation".to_owned(),
+            scopes: vec![TokenScope::ListsWrite],
+            created: 1000,
+        }
+    }
+
+    /// The secret is hashed with SHA-256
+    #[test]
+    fn hash() {
+        assert_eq!(
+            hash_secret("test_token"),
+            "cc0af97287543b65da2c7e1476426021826cab166f1e063ed012b855ff819656"
+        );
+    }
+
+    /// Stored tokens are listed without their hashes
+    #[test]
+    fn list() {
+        assert_eq!(
+            service(TEST_TOKENS).list().unwrap(),
+            vec![automation_token()]
+        );
+    }
+
+    /// If the token file does not exist, there are no tokens
+    #[test]
+    fn list_missing_file() {
+        let service = ApiTokenServiceImpl {
+            env: Arc::new(TestEnvBuilder::new().build()),
+        };
+
+        assert_eq!(service.list().unwrap(), Vec::new());
+    }
+
+    /// A token is found by its secret
+    #[test]
+    fn verify() {
+        let service = service(TEST_TOKENS);
+
+        assert_eq!(
+            service.verify("test_token").unwrap(),
+            Some(automation_token())
+        );
+        assert_eq!(service.verify("not_a_token").unwrap(), None);
+    }
+
+    /// A created token can be verified with the returned secret, but the
+    /// secret itself is not stored
+    #[test]
+    fn create() {
+        let service = service("");
+        let (secret, token) = service
+            .create("backup", &[TokenScope::StatsRead, TokenScope::StatsRead])
+            .unwrap();
+
+        assert_eq!(token.scopes, vec![TokenScope::StatsRead]);
+        assert_eq!(service.verify(&secret).unwrap(), Some(token.clone()));
+        assert_eq!(service.list().unwrap(), vec![token]);
+    }
+
+    /// Token names must be unique
+    #[test]
+    fn create_duplicate() {
+        let error = service(TEST_TOKENS)
+            .create("automation", &[TokenScope::StatsRead])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    }
+
+    /// Tokens need a valid name and at least one scope
+    #[test]
+    fn create_invalid() {
+        let service = service("");
+
+        for (name, scopes) in [
+            ("", &[TokenScope::StatsRead][..]),
+            ("has space", &[TokenScope::StatsRead][..]),
+            ("no_scopes", &[][..]),
+        ] {
+            assert_eq!(
+                service.create(name, scopes).unwrap_err().kind(),
+                ErrorKind::BadRequest
+            );
+        }
+    }
+
+    /// Deleting a token removes it from the file
+    #[test]
+    fn delete() {
+        let service = service(TEST_TOKENS);
+
+        service.delete("automation").unwrap();
+
+        assert_eq!(service.list().unwrap(), Vec::new());
+        assert_eq!(
+            service.delete("automation").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
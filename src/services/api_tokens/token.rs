@@ -0,0 +1,51 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// API Token Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// The permissions which can be granted to an API token
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TokenScope {
+    /// Read statistics and the query history
+    #[serde(rename = "stats:read")]
+    StatsRead,
+    /// Add and remove domains from the lists
+    #[serde(rename = "lists:write")]
+    ListsWrite,
+    /// Change the Pi-hole settings
+    #[serde(rename = "settings:write")]
+    SettingsWrite,
+    /// Enable and disable blocking
+    #[serde(rename = "dns:status")]
+    DnsStatus,
+}
+
+/// A named API token. The secret is never stored, only its hash.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ApiToken {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// When the token was created (Unix timestamp)
+    pub created: u64,
+}
+
+impl ApiToken {
+    /// Check if the token has been granted the scope
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// An API token as it is stored on disk
+#[derive(Serialize, Deserialize)]
+pub struct ApiTokenRecord {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    /// The hex-encoded SHA-256 hash of the token secret
+    pub hash: String,
+}
@@ -0,0 +1,17 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Domain Audit Log Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// An audited domain
+#[derive(Queryable, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub domain: String,
+    pub date_added: i32,
+}
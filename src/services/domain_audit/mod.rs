@@ -8,6 +8,7 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+mod entry;
 mod repository;
 
-pub use self::repository::*;
+pub use self::{entry::*, repository::*};
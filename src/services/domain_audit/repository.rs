@@ -10,23 +10,37 @@
 
 use crate::{
     databases::gravity::GravityDatabase,
+    services::domain_audit::AuditEntry,
     util::{Error, ErrorKind},
 };
-use diesel::{expression::exists::exists, insert_into, prelude::*, select};
+use diesel::{
+    delete, expression::exists::exists, insert_into, prelude::*, result::Error as DieselError,
+    select,
+};
 use failure::ResultExt;
 use shaku::Provider;
 
 /// Describes interactions with the domain audit data store
 #[cfg_attr(test, mockall::automock)]
-pub trait DomainAuditRepository {
+pub trait DomainAuditRepository: Send {
     /// Check if the domain is contained in the audit table
     fn contains(&self, domain: &str) -> Result<bool, Error>;
 
     /// Get all audited domains
     fn get_all(&self) -> Result<Vec<String>, Error>;
 
+    /// Get all audited domains with their IDs and when they were audited
+    fn get_entries(&self) -> Result<Vec<AuditEntry>, Error>;
+
     /// Add a domain to the audit table
     fn add(&self, domain: &str) -> Result<(), Error>;
+
+    /// Add many domains to the audit table in a single transaction. For each
+    /// domain, returns if it was added (`false` if it was already audited).
+    fn add_all(&self, domains: &[String]) -> Result<Vec<bool>, Error>;
+
+    /// Remove a domain from the audit table
+    fn remove(&self, domain: &str) -> Result<(), Error>;
 }
 
 /// The implementation of `DomainAuditRepository`
@@ -59,6 +73,17 @@ impl DomainAuditRepository for DomainAuditRepositoryImpl {
             .map_err(Error::from)
     }
 
+    fn get_entries(&self) -> Result<Vec<AuditEntry>, Error> {
+        use crate::databases::gravity::domain_audit::dsl::*;
+        let db = &self.db as &SqliteConnection;
+
+        domain_audit
+            .order(id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)
+            .map_err(Error::from)
+    }
+
     fn add(&self, input_domain: &str) -> Result<(), Error> {
         use crate::databases::gravity::domain_audit::dsl::*;
         let db = &self.db as &SqliteConnection;
@@ -70,6 +95,51 @@ impl DomainAuditRepository for DomainAuditRepositoryImpl {
 
         Ok(())
     }
+
+    fn add_all(&self, domains: &[String]) -> Result<Vec<bool>, Error> {
+        use crate::databases::gravity::domain_audit::dsl::*;
+        let db = &self.db as &SqliteConnection;
+
+        db.transaction::<_, DieselError, _>(|| {
+            let mut added = Vec::with_capacity(domains.len());
+
+            for input_domain in domains {
+                // Domains which are already audited (or earlier in the input)
+                // are skipped
+                let audited: bool =
+                    select(exists(domain_audit.filter(domain.eq(input_domain)))).get_result(db)?;
+
+                if audited {
+                    added.push(false);
+                    continue;
+                }
+
+                insert_into(domain_audit)
+                    .values(domain.eq(input_domain))
+                    .execute(db)?;
+                added.push(true);
+            }
+
+            Ok(added)
+        })
+        .context(ErrorKind::GravityDatabase)
+        .map_err(Error::from)
+    }
+
+    fn remove(&self, input_domain: &str) -> Result<(), Error> {
+        use crate::databases::gravity::domain_audit::dsl::*;
+        let db = &self.db as &SqliteConnection;
+
+        let deleted = delete(domain_audit.filter(domain.eq(input_domain)))
+            .execute(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        if deleted == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +147,7 @@ mod tests {
     use crate::{
         databases::gravity::connect_to_gravity_test_db,
         services::domain_audit::{DomainAuditRepository, DomainAuditRepositoryImpl},
+        util::ErrorKind,
     };
 
     /// If the audit table contains the domain, true will be returned
@@ -116,4 +187,60 @@ mod tests {
 
         assert!(repo.contains("new.audited.domain").unwrap());
     }
+
+    /// All audited domains are retrieved with their dates
+    #[test]
+    fn get_entries() {
+        let db = connect_to_gravity_test_db();
+        let repo = DomainAuditRepositoryImpl { db };
+
+        let entries = repo.get_entries().unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, 1);
+        assert_eq!(entries[0].domain, "audited.domain");
+        assert!(entries[0].date_added > 0);
+    }
+
+    /// After adding many, the database will contain each new domain, and
+    /// audited domains are skipped
+    #[test]
+    fn add_all_success() {
+        let db = connect_to_gravity_test_db();
+        let repo = DomainAuditRepositoryImpl { db };
+
+        let added = repo
+            .add_all(&[
+                "new.audited.domain".to_owned(),
+                "audited.domain".to_owned(),
+                "new.audited.domain".to_owned(),
+            ])
+            .unwrap();
+
+        assert_eq!(added, vec![true, false, false]);
+        assert!(repo.contains("new.audited.domain").unwrap());
+    }
+
+    /// After removing, the database will not contain the domain
+    #[test]
+    fn remove_success() {
+        let db = connect_to_gravity_test_db();
+        let repo = DomainAuditRepositoryImpl { db };
+
+        repo.remove("audited.domain").unwrap();
+
+        assert!(!repo.contains("audited.domain").unwrap());
+    }
+
+    /// Removing a domain which is not audited is an error
+    #[test]
+    fn remove_not_found() {
+        let db = connect_to_gravity_test_db();
+        let repo = DomainAuditRepositoryImpl { db };
+
+        assert_eq!(
+            repo.remove("not.audited.domain").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
 }
@@ -0,0 +1,371 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Downloader
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    services::{adlists::AdlistStatus, gravity::ParsedAdlist},
+    settings::ValueType,
+    util::{Error, ErrorKind},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    time::Duration,
+};
+
+/// How long to wait for an adlist server before giving up
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Lines longer than this many bytes are counted as invalid without being
+/// kept in memory, such as when the server sends a binary file
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// A downloaded adlist
+#[derive(Debug, PartialEq, Eq)]
+pub struct DownloadedAdlist {
+    /// The contents of the adlist, or `None` if the server replied that it
+    /// has not changed since the previous download
+    pub content: Option<AdlistContent>,
+    /// The HTTP status, if the adlist was downloaded over HTTP
+    pub http_status: Option<u16>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// The contents of an adlist, which are parsed as they are downloaded
+#[derive(Debug, PartialEq, Eq)]
+pub struct AdlistContent {
+    pub parsed: ParsedAdlist,
+    /// The SHA-256 checksum of the contents, encoded as hex
+    pub checksum: String,
+}
+
+/// Why an adlist could not be downloaded
+#[derive(Debug)]
+pub struct DownloadError {
+    /// The HTTP status, if the server responded with an error
+    pub http_status: Option<u16>,
+    pub error: Error,
+}
+
+impl DownloadError {
+    fn new(reason: String) -> Self {
+        DownloadError {
+            http_status: None,
+            error: Error::from(ErrorKind::AdlistDownload(reason)),
+        }
+    }
+}
+
+/// Download and parse an adlist. The same HTTP(S) addresses as adlists can be
+/// added with are supported, along with `file://` addresses, which are mostly
+/// useful for testing. If the status of the previous download is given, its
+/// `ETag` and `Last-Modified` headers are sent so the server can reply that
+/// the adlist has not changed.
+pub fn download_adlist(
+    address: &str,
+    previous: Option<&AdlistStatus>,
+) -> Result<DownloadedAdlist, DownloadError> {
+    if let Some(path) = address.strip_prefix("file://") {
+        return File::open(path)
+            .and_then(|file| read_adlist(BufReader::new(file)))
+            .map(|content| DownloadedAdlist {
+                content: Some(content),
+                http_status: None,
+                etag: None,
+                last_modified: None,
+            })
+            .map_err(|e| DownloadError::new(e.to_string()));
+    }
+
+    if !ValueType::Url.is_valid(address) {
+        return Err(DownloadError::new("unsupported address".to_owned()));
+    }
+
+    let mut request = ureq::get(address).timeout(DOWNLOAD_TIMEOUT);
+    let previous_etag = previous.and_then(|status| status.etag.as_deref());
+    let previous_last_modified = previous.and_then(|status| status.last_modified.as_deref());
+
+    if let Some(etag) = previous_etag {
+        request = request.set("If-None-Match", etag);
+    }
+
+    if let Some(last_modified) = previous_last_modified {
+        request = request.set("If-Modified-Since", last_modified);
+    }
+
+    let response = request.call().map_err(|e| match e {
+        ureq::Error::Status(status, _) => DownloadError {
+            http_status: Some(status),
+            ..DownloadError::new(format!("HTTP status {}", status))
+        },
+        ureq::Error::Transport(transport) => DownloadError::new(transport.to_string()),
+    })?;
+
+    let http_status = Some(response.status());
+    let etag = response.header("ETag").or(previous_etag).map(str::to_owned);
+    let last_modified = response
+        .header("Last-Modified")
+        .or(previous_last_modified)
+        .map(str::to_owned);
+
+    if response.status() == 304 {
+        return Ok(DownloadedAdlist {
+            content: None,
+            http_status,
+            etag,
+            last_modified,
+        });
+    }
+
+    // Adlists can be large, so they are parsed as they are downloaded
+    // instead of being loaded into memory
+    let content =
+        read_adlist(BufReader::new(response.into_reader())).map_err(|e| DownloadError {
+            http_status,
+            ..DownloadError::new(e.to_string())
+        })?;
+
+    Ok(DownloadedAdlist {
+        content: Some(content),
+        http_status,
+        etag,
+        last_modified,
+    })
+}
+
+/// Parse an adlist one line at a time, and compute the checksum of its
+/// contents. Lines which are not valid UTF-8 are parsed lossily, and lines
+/// which are too long are invalid.
+fn read_adlist<R: BufRead>(mut reader: R) -> io::Result<AdlistContent> {
+    let mut parsed = ParsedAdlist::default();
+    let mut hasher = Sha256::new();
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+
+        if read_line(&mut reader, &mut line)? == 0 {
+            break;
+        }
+
+        hasher.update(&line);
+
+        if line.len() <= MAX_LINE_LENGTH || line.ends_with(b"\n") {
+            parsed.add_line(&String::from_utf8_lossy(&line));
+            continue;
+        }
+
+        // Skip the rest of the line, but keep it in the checksum
+        parsed.add_invalid_line(&String::from_utf8_lossy(&line));
+
+        loop {
+            line.clear();
+
+            if read_line(&mut reader, &mut line)? == 0 {
+                break;
+            }
+
+            hasher.update(&line);
+
+            if line.ends_with(b"\n") {
+                break;
+            }
+        }
+    }
+
+    Ok(AdlistContent {
+        parsed,
+        checksum: hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    })
+}
+
+/// Read up to the end of the line, but no more than one byte past the maximum
+/// line length
+fn read_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>) -> io::Result<usize> {
+    reader
+        .by_ref()
+        .take(MAX_LINE_LENGTH as u64 + 1)
+        .read_until(b'\n', line)
+}
+
+/// Serve a single HTTP response on a local port, for testing downloads. The
+/// address and a handle which returns the request are returned.
+#[cfg(test)]
+pub fn serve_once(response: &'static str) -> (String, std::thread::JoinHandle<String>) {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}/hosts", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = String::new();
+
+        // Read the request up to the blank line after the headers
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+
+            if line.trim().is_empty() {
+                break;
+            }
+
+            request.push_str(&line);
+        }
+
+        stream.write_all(response.as_bytes()).unwrap();
+        request
+    });
+
+    (address, handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        download_adlist, read_adlist, serve_once, AdlistContent, DownloadedAdlist, MAX_LINE_LENGTH,
+    };
+    use crate::{
+        services::{
+            adlists::{AdlistHealth, AdlistStatus},
+            gravity::parse_adlist,
+        },
+        util::ErrorKind,
+    };
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// Local files are read from `file://` addresses
+    #[test]
+    fn local_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "example.com").unwrap();
+
+        let address = format!("file://{}", file.path().display());
+
+        assert_eq!(
+            download_adlist(&address, None).unwrap(),
+            DownloadedAdlist {
+                content: Some(read_adlist("example.com\n".as_bytes()).unwrap()),
+                http_status: None,
+                etag: None,
+                last_modified: None
+            }
+        );
+    }
+
+    /// The adlist is parsed line by line, and the checksum covers all of the
+    /// contents
+    #[test]
+    fn read_lines() {
+        let content = "# Comment\r\n0.0.0.0 ads.example.com\r\n<html>\nexample.net";
+
+        assert_eq!(
+            read_adlist(content.as_bytes()).unwrap(),
+            AdlistContent {
+                parsed: parse_adlist(content),
+                checksum: "164fc8c942734a3a0429410e8a6353ee3eca676dce275de941b6f814288c6440"
+                    .to_owned()
+            }
+        );
+    }
+
+    /// Lines which are too long are invalid, and the lines after them are
+    /// still parsed
+    #[test]
+    fn long_line() {
+        let long_line = "a".repeat(MAX_LINE_LENGTH * 3);
+        let content = format!("ads.example.com\n{}\nexample.net\n", long_line);
+        let content = read_adlist(content.as_bytes()).unwrap();
+
+        assert_eq!(
+            content.parsed.domains,
+            vec!["ads.example.com".to_owned(), "example.net".to_owned()]
+        );
+        assert_eq!(content.parsed.invalid_lines, 1);
+        assert_eq!(content.parsed.invalid_samples, vec!["a".repeat(100)]);
+    }
+
+    /// An HTTP response is parsed as it is read
+    #[test]
+    fn http() {
+        let (address, handle) = serve_once(
+            "HTTP/1.1 200 OK\r\nETag: \"abc\"\r\nContent-Length: 16\r\n\r\nads.example.com\n",
+        );
+
+        assert_eq!(
+            download_adlist(&address, None).unwrap(),
+            DownloadedAdlist {
+                content: Some(read_adlist("ads.example.com\n".as_bytes()).unwrap()),
+                http_status: Some(200),
+                etag: Some("\"abc\"".to_owned()),
+                last_modified: None
+            }
+        );
+        handle.join().unwrap();
+    }
+
+    /// The validators of the previous download are sent, and a 304 response
+    /// has no content
+    #[test]
+    fn not_modified() {
+        let (address, handle) =
+            serve_once("HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n");
+        let previous = AdlistStatus {
+            health: AdlistHealth::Ok,
+            last_fetched: 1557712118,
+            http_status: Some(200),
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: Some("Mon, 13 May 2019 01:48:38 GMT".to_owned()),
+            checksum: None,
+            unchanged: false,
+            domains: 1,
+            invalid_lines: 0,
+            invalid_samples: Vec::new(),
+            error: None,
+        };
+
+        assert_eq!(
+            download_adlist(&address, Some(&previous)).unwrap(),
+            DownloadedAdlist {
+                content: None,
+                http_status: Some(304),
+                etag: previous.etag.clone(),
+                last_modified: previous.last_modified.clone()
+            }
+        );
+
+        let request = handle.join().unwrap().to_lowercase();
+        assert!(request.contains("if-none-match: \"abc\"\r\n"));
+        assert!(request.contains("if-modified-since: mon, 13 may 2019 01:48:38 gmt\r\n"));
+    }
+
+    /// Other schemes are not supported
+    #[test]
+    fn unsupported_scheme() {
+        assert_eq!(
+            download_adlist("ftp://example.com/hosts", None)
+                .unwrap_err()
+                .error
+                .kind(),
+            ErrorKind::AdlistDownload("unsupported address".to_owned())
+        );
+    }
+}
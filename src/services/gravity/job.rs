@@ -0,0 +1,327 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Background Gravity Update Jobs
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    services::gravity::GravityUpdateResult,
+    util::{Error, ErrorKind},
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How many finished jobs are remembered
+const MAX_FINISHED_JOBS: usize = 10;
+
+/// Something which happened during a gravity update
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GravityEvent {
+    /// An adlist is being downloaded
+    Downloading { adlist_id: i32, address: String },
+    /// An adlist was downloaded and parsed
+    Parsed {
+        adlist_id: i32,
+        domains: usize,
+        invalid_lines: usize,
+    },
+    /// An adlist could not be downloaded
+    Failed { adlist_id: i32, error: String },
+    /// Gravity was rebuilt
+    Complete { result: GravityUpdateResult },
+    /// The update stopped because of an error
+    Error { error: String },
+}
+
+/// The state of a gravity update job
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A gravity update running in the background
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct GravityJob {
+    pub id: u64,
+    pub status: JobStatus,
+    /// When the job was started
+    pub started: u64,
+    pub events: Vec<GravityEvent>,
+    pub result: Option<GravityUpdateResult>,
+    pub error: Option<String>,
+}
+
+/// The gravity update jobs. At most one job runs at a time, and only the last
+/// few finished jobs are kept. Clones share the same jobs. A job which panics
+/// while holding the lock does not stop the others from being used.
+#[derive(Clone, Default)]
+pub struct GravityJobs {
+    state: Arc<Mutex<JobsState>>,
+}
+
+#[derive(Default)]
+struct JobsState {
+    last_id: u64,
+    jobs: VecDeque<GravityJob>,
+}
+
+impl JobsState {
+    fn get_mut(&mut self, id: u64) -> Option<&mut GravityJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+}
+
+impl GravityJobs {
+    /// Start a new job and get its ID. If a job is already running, its ID is
+    /// returned instead, along with `false`.
+    pub fn start(&self) -> (u64, bool) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(job) = state
+            .jobs
+            .iter()
+            .find(|job| job.status == JobStatus::Running)
+        {
+            return (job.id, false);
+        }
+
+        // Forget the oldest finished jobs
+        while state.jobs.len() >= MAX_FINISHED_JOBS {
+            state.jobs.pop_front();
+        }
+
+        state.last_id += 1;
+        let id = state.last_id;
+        state.jobs.push_back(GravityJob {
+            id,
+            status: JobStatus::Running,
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            events: Vec::new(),
+            result: None,
+            error: None,
+        });
+
+        (id, true)
+    }
+
+    /// Get a copy of a job
+    pub fn get(&self, id: u64) -> Option<GravityJob> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        state.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Get the events of a job after the first `skip` events, and if the job
+    /// is still running
+    pub fn events_since(&self, id: u64, skip: usize) -> Option<(Vec<GravityEvent>, bool)> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = state.jobs.iter().find(|job| job.id == id)?;
+
+        Some((
+            job.events.iter().skip(skip).cloned().collect(),
+            job.status == JobStatus::Running,
+        ))
+    }
+
+    /// Get a handle for reporting the progress of a job
+    pub fn progress(&self, id: u64) -> JobProgress {
+        JobProgress {
+            jobs: self.clone(),
+            id,
+        }
+    }
+
+    /// Get a guard which marks the job as failed if it is dropped before the
+    /// job finishes, such as when the update panics
+    pub fn guard(&self, id: u64) -> JobGuard {
+        JobGuard {
+            jobs: self.clone(),
+            id,
+            finished: false,
+        }
+    }
+
+    /// Record the outcome of a job
+    pub fn finish(&self, id: u64, result: Result<GravityUpdateResult, Error>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = match state.get_mut(id) {
+            Some(job) => job,
+            None => return,
+        };
+
+        match result {
+            Ok(result) => {
+                job.status = JobStatus::Completed;
+                job.events.push(GravityEvent::Complete {
+                    result: result.clone(),
+                });
+                job.result = Some(result);
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.events.push(GravityEvent::Error {
+                    error: e.to_string(),
+                });
+                job.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Add an event to a job
+    fn report(&self, id: u64, event: GravityEvent) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(job) = state.get_mut(id) {
+            job.events.push(event);
+        }
+    }
+}
+
+/// Reports the progress of a single job
+pub struct JobProgress {
+    jobs: GravityJobs,
+    id: u64,
+}
+
+impl JobProgress {
+    /// Add an event to the job
+    pub fn report(&self, event: GravityEvent) {
+        self.jobs.report(self.id, event);
+    }
+}
+
+/// Finishes a job, or fails it if the job never finishes
+pub struct JobGuard {
+    jobs: GravityJobs,
+    id: u64,
+    finished: bool,
+}
+
+impl JobGuard {
+    /// Record the outcome of the job
+    pub fn finish(mut self, result: Result<GravityUpdateResult, Error>) {
+        self.finished = true;
+        self.jobs.finish(self.id, result);
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.jobs
+                .finish(self.id, Err(Error::from(ErrorKind::GravityError)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GravityEvent, GravityJobs, JobStatus, MAX_FINISHED_JOBS};
+    use crate::util::{Error, ErrorKind};
+
+    /// Only one job runs at a time
+    #[test]
+    fn one_running_job() {
+        let jobs = GravityJobs::default();
+
+        assert_eq!(jobs.start(), (1, true));
+        assert_eq!(jobs.start(), (1, false));
+
+        jobs.finish(1, Err(Error::from(ErrorKind::GravityDatabase)));
+        assert_eq!(jobs.start(), (2, true));
+    }
+
+    /// Events are recorded and the outcome is added as the last event
+    #[test]
+    fn events() {
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+
+        jobs.progress(id).report(GravityEvent::Failed {
+            adlist_id: 1,
+            error: "Failed to download the adlist: HTTP status 404".to_owned(),
+        });
+        assert_eq!(jobs.events_since(id, 0).unwrap().0.len(), 1);
+        assert!(jobs.events_since(id, 0).unwrap().1);
+
+        jobs.finish(id, Err(Error::from(ErrorKind::GravityDatabase)));
+
+        let job = jobs.get(id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(
+            jobs.events_since(id, 1).unwrap(),
+            (
+                vec![GravityEvent::Error {
+                    error: "Error while interacting with the Gravity database".to_owned()
+                }],
+                false
+            )
+        );
+    }
+
+    /// A job fails if its guard is dropped before the job finishes, such as
+    /// when the update panics
+    #[test]
+    fn guard_fails_unfinished_job() {
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+
+        let result = std::panic::catch_unwind(|| {
+            let _guard = jobs.guard(id);
+            panic!("update failed");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(jobs.get(id).unwrap().status, JobStatus::Failed);
+        assert_eq!(jobs.start(), (id + 1, true));
+    }
+
+    /// The jobs can still be used after a panic poisons the lock
+    #[test]
+    fn poisoned_lock() {
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+
+        let result = std::panic::catch_unwind(|| {
+            let _state = jobs.state.lock().unwrap();
+            panic!("update failed");
+        });
+
+        assert!(result.is_err());
+        jobs.progress(id).report(GravityEvent::Error {
+            error: "update failed".to_owned(),
+        });
+        assert_eq!(jobs.events_since(id, 0).unwrap().0.len(), 1);
+        jobs.finish(id, Err(Error::from(ErrorKind::GravityError)));
+        assert_eq!(jobs.get(id).unwrap().status, JobStatus::Failed);
+        assert_eq!(jobs.start(), (id + 1, true));
+    }
+
+    /// Old finished jobs are forgotten
+    #[test]
+    fn old_jobs_forgotten() {
+        let jobs = GravityJobs::default();
+
+        for _ in 0..=MAX_FINISHED_JOBS {
+            let (id, _) = jobs.start();
+            jobs.finish(id, Err(Error::from(ErrorKind::GravityDatabase)));
+        }
+
+        assert!(jobs.get(1).is_none());
+        assert!(jobs.get(MAX_FINISHED_JOBS as u64 + 1).is_some());
+    }
+}
@@ -0,0 +1,17 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Gravity Update Service
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+mod download;
+mod job;
+mod parser;
+mod repository;
+mod service;
+
+pub use self::{download::*, job::*, parser::*, repository::*, service::*};
@@ -0,0 +1,206 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Adlist Parser
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::settings::ValueType;
+use std::net::IpAddr;
+
+/// How many invalid lines are kept as samples
+const MAX_INVALID_SAMPLES: usize = 5;
+
+/// Invalid line samples are cut off after this many characters
+const MAX_SAMPLE_LENGTH: usize = 100;
+
+/// The domains found in an adlist
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedAdlist {
+    /// The valid domains, normalized to lowercase. Duplicates are kept.
+    pub domains: Vec<String>,
+    /// The number of lines which are not comments, but could not be parsed
+    pub invalid_lines: usize,
+    /// The first few invalid lines, to help find out what went wrong
+    pub invalid_samples: Vec<String>,
+}
+
+/// Parse an adlist. Each line can be in one of these formats:
+/// - Hosts file: `0.0.0.0 example.com` (multiple domains are allowed)
+/// - Plain domain: `example.com`
+/// - Adblock: `||example.com^`
+///
+/// Empty lines and comments (`#`, or `!` and `[` for Adblock lists) are
+/// skipped.
+pub fn parse_adlist(content: &str) -> ParsedAdlist {
+    let mut parsed = ParsedAdlist::default();
+
+    for line in content.lines() {
+        parsed.add_line(line);
+    }
+
+    parsed
+}
+
+impl ParsedAdlist {
+    /// Parse a single line of an adlist and add its domains. This allows an
+    /// adlist to be parsed as it is downloaded.
+    pub fn add_line(&mut self, line: &str) {
+        // Remove comments at the end of the line
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+            return;
+        }
+
+        match parse_line(line) {
+            Some(domains) => self.domains.extend(domains),
+            None => self.add_invalid_line(line),
+        }
+    }
+
+    /// Count a line which could not be parsed, and keep it as a sample if
+    /// there are not enough samples yet
+    pub fn add_invalid_line(&mut self, line: &str) {
+        self.invalid_lines += 1;
+
+        if self.invalid_samples.len() < MAX_INVALID_SAMPLES {
+            self.invalid_samples
+                .push(line.chars().take(MAX_SAMPLE_LENGTH).collect());
+        }
+    }
+}
+
+/// Parse the domains in a single line, or `None` if the line is invalid.
+/// Hosts file lines can have no blocked domains, such as `127.0.0.1
+/// localhost`.
+fn parse_line(line: &str) -> Option<Vec<String>> {
+    // Adblock style
+    if let Some(domain) = line
+        .strip_prefix("||")
+        .and_then(|rule| rule.strip_suffix('^'))
+    {
+        return normalize(domain).map(|domain| vec![domain]);
+    }
+
+    let mut tokens = line.split_whitespace();
+    let first = tokens.next()?;
+
+    // Hosts file style
+    if first.parse::<IpAddr>().is_ok() {
+        let domains: Vec<&str> = tokens.collect();
+
+        if domains.is_empty() {
+            return None;
+        }
+
+        // Local names such as localhost are not fully qualified domains, so
+        // they are skipped instead of being counted as invalid
+        return Some(domains.into_iter().filter_map(normalize).collect());
+    }
+
+    // Plain domain style
+    if tokens.next().is_some() {
+        return None;
+    }
+
+    normalize(first).map(|domain| vec![domain])
+}
+
+/// Normalize a domain to lowercase without a trailing dot, or `None` if it is
+/// not a valid fully qualified domain
+fn normalize(domain: &str) -> Option<String> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+
+    if ValueType::Domain.is_valid(&domain) {
+        Some(domain)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_adlist, ParsedAdlist};
+
+    /// Hosts files can have multiple domains per line, and local names are
+    /// skipped
+    #[test]
+    fn hosts_file() {
+        assert_eq!(
+            parse_adlist(
+                "# Title: Test hosts\n\
+                 127.0.0.1 localhost\n\
+                 0.0.0.0 ads.example.com tracker.example.com # Trackers\n\
+                 :: ipv6.example.com\n"
+            ),
+            ParsedAdlist {
+                domains: vec![
+                    "ads.example.com".to_owned(),
+                    "tracker.example.com".to_owned(),
+                    "ipv6.example.com".to_owned()
+                ],
+                invalid_lines: 0,
+                invalid_samples: Vec::new()
+            }
+        );
+    }
+
+    /// Plain domains are normalized
+    #[test]
+    fn plain_domains() {
+        assert_eq!(
+            parse_adlist("Ads.Example.com.\n\nexample.net\n"),
+            ParsedAdlist {
+                domains: vec!["ads.example.com".to_owned(), "example.net".to_owned()],
+                invalid_lines: 0,
+                invalid_samples: Vec::new()
+            }
+        );
+    }
+
+    /// Adblock style rules are parsed, and their comments and headers are
+    /// skipped
+    #[test]
+    fn adblock() {
+        assert_eq!(
+            parse_adlist("[Adblock Plus 2.0]\n! Title: Test\n||ads.example.com^\n"),
+            ParsedAdlist {
+                domains: vec!["ads.example.com".to_owned()],
+                invalid_lines: 0,
+                invalid_samples: Vec::new()
+            }
+        );
+    }
+
+    /// Lines which are not in any of the formats are counted, and the first
+    /// few are kept
+    #[test]
+    fn invalid_lines() {
+        assert_eq!(
+            parse_adlist("<html>\n||ads.example.com^$third-party\nnot a domain\nexample.com\n"),
+            ParsedAdlist {
+                domains: vec!["example.com".to_owned()],
+                invalid_lines: 3,
+                invalid_samples: vec![
+                    "<html>".to_owned(),
+                    "||ads.example.com^$third-party".to_owned(),
+                    "not a domain".to_owned()
+                ]
+            }
+        );
+    }
+
+    /// Only the first few invalid lines are kept, and long lines are cut off
+    #[test]
+    fn invalid_samples_limited() {
+        let content = format!("{}\n", "x".repeat(200)).repeat(10);
+        let parsed = parse_adlist(&content);
+
+        assert_eq!(parsed.invalid_lines, 10);
+        assert_eq!(parsed.invalid_samples, vec!["x".repeat(100); 5]);
+    }
+}
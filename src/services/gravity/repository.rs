@@ -0,0 +1,212 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Gravity Database Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::gravity::{adlist_domain, gravity, info, GravityDatabase},
+    util::{Error, ErrorKind},
+};
+use diesel::{delete, insert_into, prelude::*, replace_into};
+use failure::ResultExt;
+use shaku::Provider;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Describes interactions with the gravity table
+#[cfg_attr(test, mockall::automock)]
+pub trait GravityRepository: Send {
+    /// Replace all of the domains in the gravity table, along with the domains
+    /// of each adlist, and record when it was updated. This is done in a
+    /// single transaction, so FTL never sees a partially built table.
+    fn replace(
+        &self,
+        domains: &[String],
+        adlist_domains: &BTreeMap<i32, Vec<String>>,
+        timestamp: u64,
+    ) -> Result<(), Error>;
+
+    /// Get the IDs of the adlists which had domains in the last update
+    fn get_adlist_ids(&self) -> Result<HashSet<i32>, Error>;
+
+    /// Get the domains of an adlist from the last update, including
+    /// whitelisted domains
+    fn get_adlist_domains(&self, adlist_id: i32) -> Result<Vec<String>, Error>;
+}
+
+/// The implementation of `GravityRepository`
+#[derive(Provider)]
+#[shaku(interface = GravityRepository)]
+pub struct GravityRepositoryImpl {
+    #[shaku(provide)]
+    pub(super) db: Box<GravityDatabase>,
+}
+
+impl GravityRepository for GravityRepositoryImpl {
+    fn replace(
+        &self,
+        domains: &[String],
+        adlist_domains: &BTreeMap<i32, Vec<String>>,
+        timestamp: u64,
+    ) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let rows: Vec<_> = domains
+            .iter()
+            .map(|domain| gravity::domain.eq(domain))
+            .collect();
+
+        // Adlists can list a domain more than once
+        let adlist_rows: Vec<_> = adlist_domains
+            .iter()
+            .flat_map(|(id, domains)| {
+                domains
+                    .iter()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(move |domain| {
+                        (
+                            adlist_domain::domain.eq(domain),
+                            adlist_domain::adlist_id.eq(*id),
+                        )
+                    })
+            })
+            .collect();
+
+        db.transaction(|| {
+            delete(gravity::table).execute(db)?;
+            insert_into(gravity::table).values(&rows).execute(db)?;
+
+            delete(adlist_domain::table).execute(db)?;
+            insert_into(adlist_domain::table)
+                .values(&adlist_rows)
+                .execute(db)?;
+
+            replace_into(info::table)
+                .values((
+                    info::property.eq("updated"),
+                    info::value.eq(timestamp.to_string()),
+                ))
+                .execute(db)
+        })
+        .context(ErrorKind::GravityDatabase)?;
+
+        Ok(())
+    }
+
+    fn get_adlist_ids(&self) -> Result<HashSet<i32>, Error> {
+        let ids: Vec<i32> = adlist_domain::table
+            .select(adlist_domain::adlist_id)
+            .distinct()
+            .load(&self.db as &SqliteConnection)
+            .context(ErrorKind::GravityDatabase)?;
+
+        Ok(ids.into_iter().collect())
+    }
+
+    fn get_adlist_domains(&self, adlist_id: i32) -> Result<Vec<String>, Error> {
+        adlist_domain::table
+            .filter(adlist_domain::adlist_id.eq(adlist_id))
+            .select(adlist_domain::domain)
+            .order(adlist_domain::domain)
+            .load(&self.db as &SqliteConnection)
+            .context(ErrorKind::GravityDatabase)
+            .map_err(Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GravityRepository, GravityRepositoryImpl};
+    use crate::databases::gravity::{connect_to_gravity_test_db, gravity, info};
+    use diesel::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn repo() -> GravityRepositoryImpl {
+        GravityRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        }
+    }
+
+    /// The old domains are replaced and the update time is recorded
+    #[test]
+    fn replace() {
+        let repo = repo();
+        let adlist_domains: BTreeMap<i32, Vec<String>> = vec![
+            (
+                1,
+                vec![
+                    "example.net".to_owned(),
+                    "ads.example.com".to_owned(),
+                    "example.net".to_owned(),
+                ],
+            ),
+            (3, vec!["ads.example.com".to_owned(), "test.com".to_owned()]),
+        ]
+        .into_iter()
+        .collect();
+
+        repo.replace(
+            &["ads.example.com".to_owned(), "example.net".to_owned()],
+            &adlist_domains,
+            1557712118,
+        )
+        .unwrap();
+
+        let db = &repo.db as &SqliteConnection;
+        let domains: Vec<String> = gravity::table
+            .select(gravity::domain)
+            .order(gravity::domain)
+            .load(db)
+            .unwrap();
+        let updated: String = info::table
+            .find("updated")
+            .select(info::value)
+            .first(db)
+            .unwrap();
+
+        assert_eq!(
+            domains,
+            vec!["ads.example.com".to_owned(), "example.net".to_owned()]
+        );
+        assert_eq!(updated, "1557712118");
+        assert_eq!(
+            repo.get_adlist_ids().unwrap(),
+            vec![1, 3].into_iter().collect()
+        );
+        assert_eq!(
+            repo.get_adlist_domains(1).unwrap(),
+            vec!["ads.example.com".to_owned(), "example.net".to_owned()]
+        );
+        assert_eq!(
+            repo.get_adlist_domains(3).unwrap(),
+            vec!["ads.example.com".to_owned(), "test.com".to_owned()]
+        );
+    }
+
+    /// The domains of each adlist are read back, sorted
+    #[test]
+    fn get_adlist_domains() {
+        let repo = repo();
+
+        assert_eq!(
+            repo.get_adlist_ids().unwrap(),
+            vec![1, 2].into_iter().collect()
+        );
+        assert_eq!(
+            repo.get_adlist_domains(2).unwrap(),
+            vec![
+                "vr-private-kunden-de.tk".to_owned(),
+                "vr-private-kundes-de.tk".to_owned(),
+                "vra.outbrain.com".to_owned(),
+                "vra4.com".to_owned(),
+                "vriaj.com".to_owned()
+            ]
+        );
+        assert_eq!(repo.get_adlist_domains(3).unwrap(), Vec::<String>::new());
+    }
+}
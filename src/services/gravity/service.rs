@@ -0,0 +1,682 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Gravity Update Service
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    services::{
+        adlists::{AdlistHealth, AdlistRepository, AdlistStatus, AdlistStatusRepository},
+        gravity::{download_adlist, GravityEvent, GravityRepository, JobProgress, ParsedAdlist},
+        lists::{List, ListRepository},
+    },
+    util::Error,
+};
+use shaku::Provider;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The outcome of downloading and parsing a single adlist
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdlistUpdateResult {
+    pub id: i32,
+    pub address: String,
+    /// The number of valid domains in the adlist, including duplicates
+    pub domains: usize,
+    pub invalid_lines: usize,
+    /// Why the adlist could not be downloaded. A failed adlist keeps its
+    /// domains from the last update.
+    pub error: Option<String>,
+}
+
+/// The outcome of rebuilding gravity
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct GravityUpdateResult {
+    pub adlists: Vec<AdlistUpdateResult>,
+    /// The number of unique domains in gravity after the update
+    pub domains: usize,
+    /// The number of domains left out because they are whitelisted
+    pub whitelisted: usize,
+    /// When gravity was updated
+    pub timestamp: u64,
+}
+
+/// Describes how gravity (the table of blocked domains) is rebuilt
+#[cfg_attr(test, mockall::automock)]
+pub trait GravityService: Send {
+    /// Download every enabled adlist, and replace gravity with their domains,
+    /// excluding whitelisted domains. The progress of each adlist is reported
+    /// as it happens, and the status of each adlist is recorded. Adlists which
+    /// have not changed since the last update reuse their domains from then,
+    /// as do adlists which can not be downloaded. FTL is not notified.
+    fn update(&self, progress: &JobProgress) -> Result<GravityUpdateResult, Error>;
+}
+
+/// The implementation of `GravityService`
+#[derive(Provider)]
+#[shaku(interface = GravityService)]
+pub struct GravityServiceImpl {
+    #[shaku(provide)]
+    adlist_repo: Box<dyn AdlistRepository>,
+    #[shaku(provide)]
+    list_repo: Box<dyn ListRepository>,
+    #[shaku(provide)]
+    gravity_repo: Box<dyn GravityRepository>,
+    #[shaku(provide)]
+    status_repo: Box<dyn AdlistStatusRepository>,
+}
+
+impl GravityService for GravityServiceImpl {
+    fn update(&self, progress: &JobProgress) -> Result<GravityUpdateResult, Error> {
+        let mut domains = BTreeSet::new();
+        let mut results = Vec::new();
+        let adlists = self.adlist_repo.get_all()?;
+        let mut statuses = self.status_repo.get_all()?;
+        let stored_adlists = self.gravity_repo.get_adlist_ids()?;
+        let mut adlist_domains = BTreeMap::new();
+
+        // Forget the statuses of deleted adlists
+        statuses.retain(|id, _| adlists.iter().any(|adlist| adlist.id == *id));
+
+        for adlist in adlists {
+            if !adlist.enabled {
+                continue;
+            }
+
+            let mut result = AdlistUpdateResult {
+                id: adlist.id,
+                address: adlist.address,
+                domains: 0,
+                invalid_lines: 0,
+                error: None,
+            };
+
+            progress.report(GravityEvent::Downloading {
+                adlist_id: result.id,
+                address: result.address.clone(),
+            });
+
+            // The server can only reply that the adlist has not changed if
+            // its domains from the last update are still in gravity
+            let last_fetched = now();
+            let previous = statuses
+                .get(&adlist.id)
+                .filter(|_| stored_adlists.contains(&adlist.id));
+            let status = match download_adlist(&result.address, previous) {
+                Ok(download) => {
+                    let (parsed, checksum) = match download.content {
+                        Some(content) => (content.parsed, Some(content.checksum)),
+                        // The adlist has not changed, so reuse its domains
+                        // from the last update
+                        None => (
+                            ParsedAdlist {
+                                domains: self.gravity_repo.get_adlist_domains(adlist.id)?,
+                                invalid_lines: previous.map_or(0, |s| s.invalid_lines),
+                                invalid_samples: previous
+                                    .map(|s| s.invalid_samples.clone())
+                                    .unwrap_or_default(),
+                            },
+                            previous.and_then(|s| s.checksum.clone()),
+                        ),
+                    };
+                    let unchanged = checksum.is_some()
+                        && statuses.get(&adlist.id).and_then(|s| s.checksum.as_ref())
+                            == checksum.as_ref();
+
+                    result.domains = parsed.domains.len();
+                    result.invalid_lines = parsed.invalid_lines;
+                    domains.extend(parsed.domains.iter().cloned());
+                    adlist_domains.insert(adlist.id, parsed.domains);
+
+                    progress.report(GravityEvent::Parsed {
+                        adlist_id: result.id,
+                        domains: result.domains,
+                        invalid_lines: result.invalid_lines,
+                    });
+
+                    AdlistStatus {
+                        health: AdlistHealth::judge(result.domains, result.invalid_lines, false),
+                        last_fetched,
+                        http_status: download.http_status,
+                        etag: download.etag,
+                        last_modified: download.last_modified,
+                        checksum,
+                        unchanged,
+                        domains: result.domains,
+                        invalid_lines: result.invalid_lines,
+                        invalid_samples: parsed.invalid_samples,
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    // Keep the domains from the last update, so one
+                    // unreachable adlist does not unblock its domains
+                    let kept_domains = if stored_adlists.contains(&adlist.id) {
+                        self.gravity_repo.get_adlist_domains(adlist.id)?
+                    } else {
+                        Vec::new()
+                    };
+
+                    result.domains = kept_domains.len();
+                    result.error = Some(e.error.to_string());
+                    domains.extend(kept_domains.iter().cloned());
+                    adlist_domains.insert(adlist.id, kept_domains);
+
+                    progress.report(GravityEvent::Failed {
+                        adlist_id: result.id,
+                        error: e.error.to_string(),
+                    });
+
+                    // The metadata still describes the kept domains
+                    AdlistStatus {
+                        health: AdlistHealth::Failed,
+                        last_fetched,
+                        http_status: e.http_status,
+                        etag: previous.and_then(|s| s.etag.clone()),
+                        last_modified: previous.and_then(|s| s.last_modified.clone()),
+                        checksum: previous.and_then(|s| s.checksum.clone()),
+                        unchanged: false,
+                        domains: result.domains,
+                        invalid_lines: previous.map_or(0, |s| s.invalid_lines),
+                        invalid_samples: previous
+                            .map(|s| s.invalid_samples.clone())
+                            .unwrap_or_default(),
+                        error: Some(e.error.to_string()),
+                    }
+                }
+            };
+
+            statuses.insert(adlist.id, status);
+            results.push(result);
+        }
+
+        // Whitelisted domains are never blocked, so leave them out
+        let whitelist: HashSet<String> = self
+            .list_repo
+            .get(List::White)?
+            .into_iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.domain.to_lowercase())
+            .collect();
+        let total_domains = domains.len();
+        let domains: Vec<String> = domains
+            .into_iter()
+            .filter(|domain| !whitelist.contains(domain))
+            .collect();
+
+        // The statuses describe the new gravity, so they are only saved once
+        // it is in place
+        let timestamp = now();
+        self.gravity_repo
+            .replace(&domains, &adlist_domains, timestamp)?;
+        self.status_repo.save(&statuses)?;
+
+        Ok(GravityUpdateResult {
+            adlists: results,
+            domains: domains.len(),
+            whitelisted: total_domains - domains.len(),
+            timestamp,
+        })
+    }
+}
+
+/// Get the current Unix timestamp
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdlistUpdateResult, GravityService, GravityServiceImpl};
+    use crate::{
+        databases::gravity::{connect_to_gravity_test_db, gravity},
+        services::{
+            adlists::{
+                Adlist, AdlistHealth, AdlistStatus, MockAdlistRepository,
+                MockAdlistStatusRepository,
+            },
+            gravity::{
+                serve_once, GravityEvent, GravityJobs, GravityRepository, GravityRepositoryImpl,
+                MockGravityRepository,
+            },
+            lists::{List, ListEntry, MockListRepository},
+        },
+        util::Error,
+    };
+    use diesel::prelude::*;
+    use mockall::predicate::*;
+    use sha2::{Digest, Sha256};
+    use std::{
+        collections::{BTreeMap, HashSet},
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+    use tempfile::NamedTempFile;
+
+    fn adlist(id: i32, address: String, enabled: bool) -> Adlist {
+        Adlist {
+            id,
+            address,
+            enabled,
+            comment: None,
+            date_added: 1557712118,
+            date_modified: 1557712118,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Get the SHA-256 checksum of an adlist's contents, encoded as hex
+    fn checksum(content: &str) -> String {
+        Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn adlist_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", content).unwrap();
+        file
+    }
+
+    /// The domains of the enabled adlists are deduplicated and whitelisted
+    /// domains are removed
+    #[test]
+    fn update() {
+        let hosts = adlist_file("0.0.0.0 ads.example.com\n0.0.0.0 test.com\n");
+        let plain = adlist_file("ads.example.com\ntracker.example.com\n<html>\n");
+        let disabled = adlist_file("disabled.example.com\n");
+        let hosts_address = format!("file://{}", hosts.path().display());
+        let plain_address = format!("file://{}", plain.path().display());
+        let disabled_address = format!("file://{}", disabled.path().display());
+
+        let mut adlist_repo = MockAdlistRepository::new();
+        let mut list_repo = MockListRepository::new();
+        let mut gravity_repo = MockGravityRepository::new();
+        let mut status_repo = MockAdlistStatusRepository::new();
+
+        adlist_repo.expect_get_all().return_const(Ok(vec![
+            adlist(1, hosts_address.clone(), true),
+            adlist(2, plain_address.clone(), true),
+            adlist(3, disabled_address, false),
+        ]));
+        list_repo
+            .expect_get()
+            .with(eq(List::White))
+            .return_const(Ok(vec![ListEntry {
+                id: 1,
+                domain: "test.com".to_owned(),
+                enabled: true,
+                date_added: 1557712172,
+                date_modified: 1557712172,
+                comment: None,
+            }]));
+        gravity_repo
+            .expect_get_adlist_ids()
+            .return_const(Ok(HashSet::new()));
+        gravity_repo
+            .expect_replace()
+            .withf(|domains, adlist_domains, _| {
+                domains
+                    == [
+                        "ads.example.com".to_owned(),
+                        "tracker.example.com".to_owned(),
+                    ]
+                    && adlist_domains.keys().copied().collect::<Vec<_>>() == [1, 2]
+                    && adlist_domains[&1] == ["ads.example.com", "test.com"]
+                    && adlist_domains[&2] == ["ads.example.com", "tracker.example.com"]
+            })
+            .times(1)
+            .return_const(Ok(()));
+        status_repo
+            .expect_get_all()
+            .return_const(Ok(BTreeMap::new()));
+        status_repo.expect_save().times(1).return_const(Ok(()));
+
+        let service = GravityServiceImpl {
+            adlist_repo: Box::new(adlist_repo),
+            list_repo: Box::new(list_repo),
+            gravity_repo: Box::new(gravity_repo),
+            status_repo: Box::new(status_repo),
+        };
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+        let result = service.update(&jobs.progress(id)).unwrap();
+
+        assert_eq!(
+            result.adlists,
+            vec![
+                AdlistUpdateResult {
+                    id: 1,
+                    address: hosts_address.clone(),
+                    domains: 2,
+                    invalid_lines: 0,
+                    error: None
+                },
+                AdlistUpdateResult {
+                    id: 2,
+                    address: plain_address.clone(),
+                    domains: 2,
+                    invalid_lines: 1,
+                    error: None
+                }
+            ]
+        );
+        assert_eq!(result.domains, 2);
+        assert_eq!(result.whitelisted, 1);
+        assert_eq!(
+            jobs.get(id).unwrap().events,
+            vec![
+                GravityEvent::Downloading {
+                    adlist_id: 1,
+                    address: hosts_address
+                },
+                GravityEvent::Parsed {
+                    adlist_id: 1,
+                    domains: 2,
+                    invalid_lines: 0
+                },
+                GravityEvent::Downloading {
+                    adlist_id: 2,
+                    address: plain_address
+                },
+                GravityEvent::Parsed {
+                    adlist_id: 2,
+                    domains: 2,
+                    invalid_lines: 1
+                }
+            ]
+        );
+    }
+
+    /// The status of each fetched adlist is recorded, unchanged contents are
+    /// detected, and the statuses of deleted adlists are forgotten
+    #[test]
+    fn update_statuses() {
+        let content = "ads.example.com\n<html>\n";
+        let file = adlist_file(content);
+        let address = format!("file://{}", file.path().display());
+
+        let mut adlist_repo = MockAdlistRepository::new();
+        let mut list_repo = MockListRepository::new();
+        let mut gravity_repo = MockGravityRepository::new();
+        let mut status_repo = MockAdlistStatusRepository::new();
+
+        let old_status = AdlistStatus {
+            health: AdlistHealth::Ok,
+            last_fetched: 1557712118,
+            http_status: None,
+            etag: None,
+            last_modified: None,
+            checksum: Some(checksum(content)),
+            unchanged: false,
+            domains: 1,
+            invalid_lines: 1,
+            invalid_samples: vec!["<html>".to_owned()],
+            error: None,
+        };
+
+        adlist_repo
+            .expect_get_all()
+            .return_const(Ok(vec![adlist(1, address, true)]));
+        list_repo
+            .expect_get()
+            .with(eq(List::White))
+            .return_const(Ok(Vec::new()));
+        gravity_repo
+            .expect_get_adlist_ids()
+            .return_const(Ok(HashSet::new()));
+        gravity_repo.expect_replace().return_const(Ok(()));
+        status_repo.expect_get_all().return_const(Ok(vec![
+            (1, old_status.clone()),
+            (3, old_status.clone()),
+        ]
+        .into_iter()
+        .collect()));
+        status_repo
+            .expect_save()
+            .withf(move |statuses| {
+                let ok = &statuses[&1];
+
+                statuses.len() == 1
+                    && ok.last_fetched > old_status.last_fetched
+                    && AdlistStatus {
+                        last_fetched: old_status.last_fetched,
+                        unchanged: false,
+                        ..ok.clone()
+                    } == old_status
+                    && ok.unchanged
+            })
+            .times(1)
+            .return_const(Ok(()));
+
+        let service = GravityServiceImpl {
+            adlist_repo: Box::new(adlist_repo),
+            list_repo: Box::new(list_repo),
+            gravity_repo: Box::new(gravity_repo),
+            status_repo: Box::new(status_repo),
+        };
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+
+        service.update(&jobs.progress(id)).unwrap();
+    }
+
+    /// If the server replies that an adlist has not changed, its domains from
+    /// the last update are reused
+    #[test]
+    fn update_not_modified() {
+        let (address, handle) =
+            serve_once("HTTP/1.1 304 Not Modified\r\nETag: \"abc\"\r\nContent-Length: 0\r\n\r\n");
+
+        let mut adlist_repo = MockAdlistRepository::new();
+        let mut list_repo = MockListRepository::new();
+        let mut gravity_repo = MockGravityRepository::new();
+        let mut status_repo = MockAdlistStatusRepository::new();
+
+        let old_status = AdlistStatus {
+            health: AdlistHealth::Ok,
+            last_fetched: 1557712118,
+            http_status: Some(200),
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: None,
+            checksum: Some(checksum("ads.example.com\n")),
+            unchanged: false,
+            domains: 1,
+            invalid_lines: 0,
+            invalid_samples: Vec::new(),
+            error: None,
+        };
+
+        adlist_repo
+            .expect_get_all()
+            .return_const(Ok(vec![adlist(1, address, true)]));
+        list_repo
+            .expect_get()
+            .with(eq(List::White))
+            .return_const(Ok(Vec::new()));
+        gravity_repo
+            .expect_get_adlist_ids()
+            .return_const(Ok(vec![1].into_iter().collect()));
+        gravity_repo
+            .expect_get_adlist_domains()
+            .with(eq(1))
+            .return_const(Ok(vec!["ads.example.com".to_owned()]));
+        gravity_repo
+            .expect_replace()
+            .withf(|domains, adlist_domains, _| {
+                domains == ["ads.example.com".to_owned()]
+                    && adlist_domains[&1] == ["ads.example.com".to_owned()]
+            })
+            .times(1)
+            .return_const(Ok(()));
+        status_repo
+            .expect_get_all()
+            .return_const(Ok(vec![(1, old_status.clone())].into_iter().collect()));
+        status_repo
+            .expect_save()
+            .withf(move |statuses| {
+                let status = &statuses[&1];
+
+                status.http_status == Some(304)
+                    && status.health == AdlistHealth::Ok
+                    && status.unchanged
+                    && status.etag == old_status.etag
+                    && status.checksum == old_status.checksum
+                    && status.domains == 1
+            })
+            .times(1)
+            .return_const(Ok(()));
+
+        let service = GravityServiceImpl {
+            adlist_repo: Box::new(adlist_repo),
+            list_repo: Box::new(list_repo),
+            gravity_repo: Box::new(gravity_repo),
+            status_repo: Box::new(status_repo),
+        };
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+        let result = service.update(&jobs.progress(id)).unwrap();
+
+        assert_eq!(result.domains, 1);
+        assert!(handle
+            .join()
+            .unwrap()
+            .to_lowercase()
+            .contains("if-none-match: \"abc\""));
+    }
+
+    /// Share a real gravity repository with the test, so the gravity table
+    /// can be checked after the update
+    impl GravityRepository for Arc<Mutex<GravityRepositoryImpl>> {
+        fn replace(
+            &self,
+            domains: &[String],
+            adlist_domains: &BTreeMap<i32, Vec<String>>,
+            timestamp: u64,
+        ) -> Result<(), Error> {
+            self.lock()
+                .unwrap()
+                .replace(domains, adlist_domains, timestamp)
+        }
+
+        fn get_adlist_ids(&self) -> Result<HashSet<i32>, Error> {
+            self.lock().unwrap().get_adlist_ids()
+        }
+
+        fn get_adlist_domains(&self, adlist_id: i32) -> Result<Vec<String>, Error> {
+            self.lock().unwrap().get_adlist_domains(adlist_id)
+        }
+    }
+
+    /// If an adlist can not be downloaded, its domains from the last update
+    /// are kept and gravity is still rebuilt
+    #[test]
+    fn update_failed_download() {
+        let file = adlist_file("ads.example.com\n");
+        let address = format!("file://{}", file.path().display());
+        let missing_address = "file:///nonexistent/adlist.txt".to_owned();
+
+        let mut adlist_repo = MockAdlistRepository::new();
+        let mut list_repo = MockListRepository::new();
+        let mut status_repo = MockAdlistStatusRepository::new();
+        let gravity_repo = Arc::new(Mutex::new(GravityRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        }));
+
+        let old_status = AdlistStatus {
+            health: AdlistHealth::Ok,
+            last_fetched: 1557712118,
+            http_status: Some(200),
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: None,
+            checksum: Some("1234".to_owned()),
+            unchanged: false,
+            domains: 5,
+            invalid_lines: 1,
+            invalid_samples: vec!["<html>".to_owned()],
+            error: None,
+        };
+
+        adlist_repo.expect_get_all().return_const(Ok(vec![
+            adlist(1, address, true),
+            adlist(2, missing_address, true),
+        ]));
+        list_repo
+            .expect_get()
+            .with(eq(List::White))
+            .return_const(Ok(Vec::new()));
+        status_repo
+            .expect_get_all()
+            .return_const(Ok(vec![(2, old_status.clone())].into_iter().collect()));
+        status_repo
+            .expect_save()
+            .withf(move |statuses| {
+                let failed = &statuses[&2];
+
+                statuses.len() == 2
+                    && statuses[&1].health == AdlistHealth::Ok
+                    && failed.health == AdlistHealth::Failed
+                    && failed.error.is_some()
+                    && failed.domains == 5
+                    && failed.etag == old_status.etag
+                    && failed.checksum == old_status.checksum
+                    && failed.invalid_lines == old_status.invalid_lines
+                    && failed.invalid_samples == old_status.invalid_samples
+            })
+            .times(1)
+            .return_const(Ok(()));
+
+        let service = GravityServiceImpl {
+            adlist_repo: Box::new(adlist_repo),
+            list_repo: Box::new(list_repo),
+            gravity_repo: Box::new(gravity_repo.clone()),
+            status_repo: Box::new(status_repo),
+        };
+        let jobs = GravityJobs::default();
+        let (id, _) = jobs.start();
+        let result = service.update(&jobs.progress(id)).unwrap();
+        let error = result.adlists[1].error.clone().unwrap();
+
+        let repo = gravity_repo.lock().unwrap();
+        let domains: Vec<String> = gravity::table
+            .select(gravity::domain)
+            .order(gravity::domain)
+            .load(&repo.db as &SqliteConnection)
+            .unwrap();
+
+        assert_eq!(result.adlists[1].domains, 5);
+        assert_eq!(
+            domains,
+            vec![
+                "ads.example.com".to_owned(),
+                "vr-private-kunden-de.tk".to_owned(),
+                "vr-private-kundes-de.tk".to_owned(),
+                "vra.outbrain.com".to_owned(),
+                "vra4.com".to_owned(),
+                "vriaj.com".to_owned(),
+            ]
+        );
+        assert_eq!(
+            repo.get_adlist_domains(1).unwrap(),
+            vec!["ads.example.com".to_owned()]
+        );
+        assert_eq!(repo.get_adlist_domains(2).unwrap().len(), 5);
+        assert_eq!(
+            jobs.get(id).unwrap().events.last(),
+            Some(&GravityEvent::Failed {
+                adlist_id: 2,
+                error
+            })
+        );
+    }
+}
@@ -0,0 +1,43 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Group Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// A group from the gravity database. List entries and adlists which are
+/// assigned to a disabled group are not used.
+#[derive(Queryable, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub id: i32,
+    pub enabled: bool,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// The editable properties of a group
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GroupInput {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// The list entries and adlists assigned to a group
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupMembers {
+    pub whitelist: Vec<String>,
+    pub blacklist: Vec<String>,
+    pub regexlist: Vec<String>,
+    /// The IDs of the adlists
+    pub adlists: Vec<i32>,
+}
+
+/// Groups are enabled unless specified otherwise
+fn default_enabled() -> bool {
+    true
+}
@@ -0,0 +1,509 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Group Database Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::{
+        gravity::{
+            adlist, adlist_by_group, blacklist, blacklist_by_group, group, regex, regex_by_group,
+            whitelist, whitelist_by_group, GravityDatabase,
+        },
+        map_gravity_insert_error,
+    },
+    services::{
+        groups::{Group, GroupInput, GroupMembers},
+        lists::List,
+    },
+    util::{Error, ErrorKind},
+};
+use diesel::{delete, dsl::exists, insert_into, prelude::*, select, update};
+use failure::ResultExt;
+use shaku::Provider;
+
+/// Describes interactions with the groups and their members
+#[cfg_attr(test, mockall::automock)]
+pub trait GroupRepository: Send {
+    /// Get all of the groups
+    fn get_all(&self) -> Result<Vec<Group>, Error>;
+
+    /// Create a group
+    fn add(&self, input: &GroupInput) -> Result<Group, Error>;
+
+    /// Update the properties of a group
+    fn update(&self, group_id: i32, input: &GroupInput) -> Result<(), Error>;
+
+    /// Delete a group. Its members are unassigned, but not deleted.
+    fn remove(&self, group_id: i32) -> Result<(), Error>;
+
+    /// Get the list entries and adlists assigned to the group
+    fn get_members(&self, group_id: i32) -> Result<GroupMembers, Error>;
+
+    /// Assign a list entry to the group
+    fn add_domain(&self, group_id: i32, list: List, domain: &str) -> Result<(), Error>;
+
+    /// Unassign a list entry from the group
+    fn remove_domain(&self, group_id: i32, list: List, domain: &str) -> Result<(), Error>;
+
+    /// Assign an adlist to the group
+    fn add_adlist(&self, group_id: i32, adlist_id: i32) -> Result<(), Error>;
+
+    /// Unassign an adlist from the group
+    fn remove_adlist(&self, group_id: i32, adlist_id: i32) -> Result<(), Error>;
+}
+
+/// The implementation of `GroupRepository`
+#[derive(Provider)]
+#[shaku(interface = GroupRepository)]
+pub struct GroupRepositoryImpl {
+    #[shaku(provide)]
+    db: Box<GravityDatabase>,
+}
+
+impl GroupRepository for GroupRepositoryImpl {
+    fn get_all(&self) -> Result<Vec<Group>, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        group::table
+            .order(group::id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)
+            .map_err(Error::from)
+    }
+
+    fn add(&self, input: &GroupInput) -> Result<Group, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        // SQLite does not return the new ID, so find it in the same transaction
+        let group_id = db
+            .transaction(|| {
+                insert_into(group::table)
+                    .values((
+                        group::name.eq(&input.name),
+                        group::description.eq(&input.description),
+                        group::enabled.eq(input.enabled),
+                    ))
+                    .execute(db)?;
+
+                group::table
+                    .select(group::id)
+                    .order(group::id.desc())
+                    .first::<i32>(db)
+            })
+            .context(ErrorKind::GravityDatabase)?;
+
+        Ok(Group {
+            id: group_id,
+            enabled: input.enabled,
+            name: input.name.clone(),
+            description: input.description.clone(),
+        })
+    }
+
+    fn update(&self, group_id: i32, input: &GroupInput) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let updated = update(group::table.find(group_id))
+            .set((
+                group::name.eq(&input.name),
+                group::description.eq(&input.description),
+                group::enabled.eq(input.enabled),
+            ))
+            .execute(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        if updated == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, group_id: i32) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        // The assignments reference the group, so they are removed first
+        let deleted = db
+            .transaction(|| {
+                delete(whitelist_by_group::table.filter(whitelist_by_group::group_id.eq(group_id)))
+                    .execute(db)?;
+                delete(blacklist_by_group::table.filter(blacklist_by_group::group_id.eq(group_id)))
+                    .execute(db)?;
+                delete(regex_by_group::table.filter(regex_by_group::group_id.eq(group_id)))
+                    .execute(db)?;
+                delete(adlist_by_group::table.filter(adlist_by_group::group_id.eq(group_id)))
+                    .execute(db)?;
+
+                delete(group::table.find(group_id)).execute(db)
+            })
+            .context(ErrorKind::GravityDatabase)?;
+
+        if deleted == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+
+    fn get_members(&self, group_id: i32) -> Result<GroupMembers, Error> {
+        let db = &self.db as &SqliteConnection;
+        self.check_group(group_id)?;
+
+        let members = GroupMembers {
+            whitelist: whitelist::table
+                .inner_join(whitelist_by_group::table)
+                .filter(whitelist_by_group::group_id.eq(group_id))
+                .select(whitelist::domain)
+                .order(whitelist::id)
+                .load(db)
+                .context(ErrorKind::GravityDatabase)?,
+            blacklist: blacklist::table
+                .inner_join(blacklist_by_group::table)
+                .filter(blacklist_by_group::group_id.eq(group_id))
+                .select(blacklist::domain)
+                .order(blacklist::id)
+                .load(db)
+                .context(ErrorKind::GravityDatabase)?,
+            regexlist: regex::table
+                .inner_join(regex_by_group::table)
+                .filter(regex_by_group::group_id.eq(group_id))
+                .select(regex::domain)
+                .order(regex::id)
+                .load(db)
+                .context(ErrorKind::GravityDatabase)?,
+            adlists: adlist_by_group::table
+                .filter(adlist_by_group::group_id.eq(group_id))
+                .select(adlist_by_group::adlist_id)
+                .order(adlist_by_group::adlist_id)
+                .load(db)
+                .context(ErrorKind::GravityDatabase)?,
+        };
+
+        Ok(members)
+    }
+
+    fn add_domain(&self, group_id: i32, list: List, domain: &str) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+        self.check_group(group_id)?;
+        let entry_id = self.find_entry(list, domain)?;
+
+        match list {
+            List::White => insert_into(whitelist_by_group::table)
+                .values((
+                    whitelist_by_group::whitelist_id.eq(entry_id),
+                    whitelist_by_group::group_id.eq(group_id),
+                ))
+                .execute(db),
+            List::Black => insert_into(blacklist_by_group::table)
+                .values((
+                    blacklist_by_group::blacklist_id.eq(entry_id),
+                    blacklist_by_group::group_id.eq(group_id),
+                ))
+                .execute(db),
+            List::Regex | List::Wildcard => insert_into(regex_by_group::table)
+                .values((
+                    regex_by_group::regex_id.eq(entry_id),
+                    regex_by_group::group_id.eq(group_id),
+                ))
+                .execute(db),
+        }
+        .map_err(map_gravity_insert_error)?;
+
+        Ok(())
+    }
+
+    fn remove_domain(&self, group_id: i32, list: List, domain: &str) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+        let entry_id = self.find_entry(list, domain)?;
+
+        let deleted = match list {
+            List::White => delete(
+                whitelist_by_group::table
+                    .filter(whitelist_by_group::whitelist_id.eq(entry_id))
+                    .filter(whitelist_by_group::group_id.eq(group_id)),
+            )
+            .execute(db),
+            List::Black => delete(
+                blacklist_by_group::table
+                    .filter(blacklist_by_group::blacklist_id.eq(entry_id))
+                    .filter(blacklist_by_group::group_id.eq(group_id)),
+            )
+            .execute(db),
+            List::Regex | List::Wildcard => delete(
+                regex_by_group::table
+                    .filter(regex_by_group::regex_id.eq(entry_id))
+                    .filter(regex_by_group::group_id.eq(group_id)),
+            )
+            .execute(db),
+        }
+        .context(ErrorKind::GravityDatabase)?;
+
+        if deleted == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+
+    fn add_adlist(&self, group_id: i32, adlist_id: i32) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+        self.check_group(group_id)?;
+
+        let adlist_exists: bool = select(exists(adlist::table.find(adlist_id)))
+            .get_result(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        if !adlist_exists {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        insert_into(adlist_by_group::table)
+            .values((
+                adlist_by_group::adlist_id.eq(adlist_id),
+                adlist_by_group::group_id.eq(group_id),
+            ))
+            .execute(db)
+            .map_err(map_gravity_insert_error)?;
+
+        Ok(())
+    }
+
+    fn remove_adlist(&self, group_id: i32, adlist_id: i32) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let deleted = delete(
+            adlist_by_group::table
+                .filter(adlist_by_group::adlist_id.eq(adlist_id))
+                .filter(adlist_by_group::group_id.eq(group_id)),
+        )
+        .execute(db)
+        .context(ErrorKind::GravityDatabase)?;
+
+        if deleted == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+}
+
+impl GroupRepositoryImpl {
+    /// Check that the group exists, otherwise return `NotFound`
+    fn check_group(&self, group_id: i32) -> Result<(), Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let group_exists: bool = select(exists(group::table.find(group_id)))
+            .get_result(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        if group_exists {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    /// Find the ID of a list entry, or return `NotFound` if it is not on the
+    /// list
+    fn find_entry(&self, list: List, domain: &str) -> Result<i32, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        match list {
+            List::White => whitelist::table
+                .select(whitelist::id)
+                .filter(whitelist::domain.eq(domain))
+                .first(db)
+                .optional(),
+            List::Black => blacklist::table
+                .select(blacklist::id)
+                .filter(blacklist::domain.eq(domain))
+                .first(db)
+                .optional(),
+            List::Regex | List::Wildcard => regex::table
+                .select(regex::id)
+                .filter(regex::domain.eq(domain))
+                .first(db)
+                .optional(),
+        }
+        .context(ErrorKind::GravityDatabase)?
+        .ok_or_else(|| Error::from(ErrorKind::NotFound))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupRepository, GroupRepositoryImpl};
+    use crate::{
+        databases::gravity::connect_to_gravity_test_db,
+        services::{
+            groups::{Group, GroupInput, GroupMembers},
+            lists::List,
+        },
+        util::ErrorKind,
+    };
+
+    fn repo() -> GroupRepositoryImpl {
+        GroupRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        }
+    }
+
+    fn test_group() -> Group {
+        Group {
+            id: 1,
+            enabled: true,
+            name: "Test Group".to_owned(),
+            description: Some("A group for testing".to_owned()),
+        }
+    }
+
+    /// All groups are retrieved
+    #[test]
+    fn get_all() {
+        assert_eq!(repo().get_all().unwrap(), vec![test_group()]);
+    }
+
+    /// A new group is given the next ID
+    #[test]
+    fn add() {
+        let repo = repo();
+        let input = GroupInput {
+            name: "Kids".to_owned(),
+            description: None,
+            enabled: false,
+        };
+        let expected = Group {
+            id: 2,
+            enabled: false,
+            name: "Kids".to_owned(),
+            description: None,
+        };
+
+        assert_eq!(repo.add(&input).unwrap(), expected);
+        assert_eq!(repo.get_all().unwrap(), vec![test_group(), expected]);
+    }
+
+    /// Updating a group changes its properties
+    #[test]
+    fn update() {
+        let repo = repo();
+        let input = GroupInput {
+            name: "Renamed".to_owned(),
+            description: None,
+            enabled: false,
+        };
+
+        repo.update(1, &input).unwrap();
+
+        assert_eq!(
+            repo.get_all().unwrap(),
+            vec![Group {
+                id: 1,
+                enabled: false,
+                name: "Renamed".to_owned(),
+                description: None
+            }]
+        );
+        assert_eq!(
+            repo.update(10, &input).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Removing a group also removes its assignments
+    #[test]
+    fn remove() {
+        let repo = repo();
+
+        repo.remove(1).unwrap();
+
+        assert_eq!(repo.get_all().unwrap(), Vec::new());
+        assert_eq!(repo.remove(1).unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(repo.get_members(1).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    /// The assigned list entries and adlists are retrieved
+    #[test]
+    fn get_members() {
+        assert_eq!(
+            repo().get_members(1).unwrap(),
+            GroupMembers {
+                whitelist: vec!["test.com".to_owned()],
+                blacklist: Vec::new(),
+                regexlist: Vec::new(),
+                adlists: vec![1]
+            }
+        );
+    }
+
+    /// List entries can be assigned and unassigned
+    #[test]
+    fn add_remove_domain() {
+        let repo = repo();
+
+        repo.add_domain(1, List::Black, "example.com").unwrap();
+        assert_eq!(
+            repo.get_members(1).unwrap().blacklist,
+            vec!["example.com".to_owned()]
+        );
+
+        repo.remove_domain(1, List::Black, "example.com").unwrap();
+        assert_eq!(repo.get_members(1).unwrap().blacklist, Vec::<String>::new());
+    }
+
+    /// Assigning an entry twice is an error
+    #[test]
+    fn add_domain_duplicate() {
+        assert_eq!(
+            repo()
+                .add_domain(1, List::White, "test.com")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    /// Entries and groups which do not exist can not be assigned
+    #[test]
+    fn add_domain_not_found() {
+        let repo = repo();
+
+        assert_eq!(
+            repo.add_domain(1, List::White, "not.listed")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            repo.add_domain(10, List::Black, "example.com")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Adlists can be assigned and unassigned
+    #[test]
+    fn add_remove_adlist() {
+        let repo = repo();
+
+        repo.add_adlist(1, 2).unwrap();
+        assert_eq!(repo.get_members(1).unwrap().adlists, vec![1, 2]);
+
+        repo.remove_adlist(1, 1).unwrap();
+        assert_eq!(repo.get_members(1).unwrap().adlists, vec![2]);
+        assert_eq!(
+            repo.remove_adlist(1, 1).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            repo.add_adlist(1, 10).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
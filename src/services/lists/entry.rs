@@ -0,0 +1,86 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// List Entry Models
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::util::Error;
+
+/// An entry of the whitelist, blacklist, or regex list
+#[derive(Queryable, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ListEntry {
+    pub id: i32,
+    pub domain: String,
+    pub enabled: bool,
+    pub date_added: i32,
+    pub date_modified: i32,
+    pub comment: Option<String>,
+}
+
+/// Changes to an existing list entry. Properties which are not given are left
+/// unchanged, and an empty comment removes the comment.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListEntryPatch {
+    pub enabled: Option<bool>,
+    pub comment: Option<String>,
+}
+
+impl ListEntryPatch {
+    /// Check if the patch does not change anything
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_none() && self.comment.is_none()
+    }
+}
+
+/// The outcome of importing a single domain
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    /// The domain was added to the list
+    Added,
+    /// The domain was already on the list
+    Duplicate,
+    /// The domain is not valid for the list
+    Invalid,
+}
+
+/// The result of importing a single domain
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ImportResult {
+    pub domain: String,
+    pub status: ImportStatus,
+}
+
+/// Import many domains at once. The valid domains are validated before any are
+/// added, then given to `add_all`, which reports if each one was added. The
+/// result of each domain is returned in the order they were given.
+pub fn import_domains(
+    domains: &[String],
+    is_valid: impl Fn(&str) -> bool,
+    add_all: impl FnOnce(&[String]) -> Result<Vec<bool>, Error>,
+) -> Result<Vec<ImportResult>, Error> {
+    let valid_domains: Vec<String> = domains
+        .iter()
+        .filter(|domain| is_valid(domain))
+        .cloned()
+        .collect();
+    let mut added = add_all(&valid_domains)?.into_iter();
+
+    Ok(domains
+        .iter()
+        .map(|domain| ImportResult {
+            domain: domain.clone(),
+            status: if !is_valid(domain) {
+                ImportStatus::Invalid
+            } else if added.next().unwrap_or(false) {
+                ImportStatus::Added
+            } else {
+                ImportStatus::Duplicate
+            },
+        })
+        .collect())
+}
@@ -9,6 +9,13 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::settings::ValueType;
+use rocket::request::FromParam;
+
+/// The start of a wildcard stored as a regex
+const WILDCARD_PREFIX: &str = "(^|\\.)";
+
+/// The end of a wildcard stored as a regex
+const WILDCARD_SUFFIX: &str = "$";
 
 /// Represents the various Pi-hole domain lists
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -16,6 +23,11 @@ pub enum List {
     White,
     Black,
     Regex,
+    /// Wildcards such as `*.example.com`. They are stored in the regex table
+    /// as `(^|\.)example\.com$`, and the `regex_wildcard` table records which
+    /// regex entries are wildcards. The repositories expect the stored form,
+    /// see `List::stored_list` and `List::to_stored`.
+    Wildcard,
 }
 
 impl List {
@@ -23,8 +35,113 @@ impl List {
     pub fn accepts(self, domain: &str) -> bool {
         match self {
             List::Regex => ValueType::Regex.is_valid(domain),
+            List::Wildcard => ValueType::Wildcard.is_valid(domain),
             // Allow hostnames to be white/blacklist-ed
             _ => ValueType::Hostname.is_valid(domain),
         }
     }
+
+    /// Get the list whose table stores the entries of this list
+    pub fn stored_list(self) -> List {
+        match self {
+            List::Wildcard => List::Regex,
+            list => list,
+        }
+    }
+
+    /// Convert a domain into the form it is stored in. The domain should
+    /// already be accepted by the list. Wildcards are lowercased, since
+    /// domains are case insensitive.
+    pub fn to_stored(self, domain: &str) -> String {
+        match self {
+            List::Wildcard => format!(
+                "{}{}{}",
+                WILDCARD_PREFIX,
+                domain
+                    .trim_start_matches("*.")
+                    .to_lowercase()
+                    .replace('.', "\\."),
+                WILDCARD_SUFFIX
+            ),
+            _ => domain.to_owned(),
+        }
+    }
+
+    /// Convert a stored domain into the form used by this list. `None` is
+    /// returned if a wildcard is not stored in the canonical form, for example
+    /// if its regex was edited outside of the API.
+    pub fn from_stored(self, stored: &str) -> Option<String> {
+        match self {
+            List::Wildcard => {
+                let hostname = stored
+                    .strip_prefix(WILDCARD_PREFIX)?
+                    .strip_suffix(WILDCARD_SUFFIX)?
+                    .replace("\\.", ".");
+                let domain = format!("*.{}", hostname);
+
+                // Only the canonical form is a wildcard
+                if self.accepts(&domain) && self.to_stored(&domain) == stored {
+                    Some(domain)
+                } else {
+                    None
+                }
+            }
+            _ => Some(stored.to_owned()),
+        }
+    }
+}
+
+impl<'a> FromParam<'a> for List {
+    type Error = &'a str;
+
+    /// Parse the list from its name in the API, such as `whitelist`
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param {
+            "whitelist" => Ok(List::White),
+            "blacklist" => Ok(List::Black),
+            "regexlist" => Ok(List::Regex),
+            "wildcardlist" => Ok(List::Wildcard),
+            _ => Err(param),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::List;
+
+    /// Wildcards are stored as a regex matching the lowercase domain and its
+    /// subdomains
+    #[test]
+    fn wildcard_to_stored() {
+        assert_eq!(
+            List::Wildcard.to_stored("*.example.com"),
+            "(^|\\.)example\\.com$"
+        );
+        assert_eq!(
+            List::Wildcard.to_stored("*.Example.COM"),
+            "(^|\\.)example\\.com$"
+        );
+    }
+
+    /// Only regexes in the canonical wildcard form are read as wildcards
+    #[test]
+    fn wildcard_from_stored() {
+        assert_eq!(
+            List::Wildcard.from_stored("(^|\\.)example\\.com$"),
+            Some("*.example.com".to_owned())
+        );
+        assert_eq!(List::Wildcard.from_stored("(^|\\.)example.com$"), None);
+        assert_eq!(List::Wildcard.from_stored("^ads?\\.example\\.com$"), None);
+    }
+
+    /// Other lists are stored as they are
+    #[test]
+    fn regex_stored_as_is() {
+        assert_eq!(List::Regex.to_stored("^example$"), "^example$");
+        assert_eq!(
+            List::Regex.from_stored("(^|\\.)example\\.com$"),
+            Some("(^|\\.)example\\.com$".to_owned())
+        );
+    }
 }
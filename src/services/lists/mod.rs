@@ -8,8 +8,9 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+mod entry;
 mod list;
 mod repository;
 mod service;
 
-pub use self::{list::*, repository::*, service::*};
+pub use self::{entry::*, list::*, repository::*, service::*};
@@ -9,26 +9,52 @@
 // Please see LICENSE file for your rights under this license.
 
 use crate::{
-    databases::gravity::GravityDatabase,
-    services::lists::List,
+    databases::gravity::{regex_wildcard, GravityDatabase},
+    services::lists::{List, ListEntry, ListEntryPatch},
     util::{Error, ErrorKind},
 };
-use diesel::{delete, dsl::exists, insert_into, prelude::*, select};
+use diesel::{
+    delete,
+    dsl::{exists, Select},
+    insert_into,
+    prelude::*,
+    select, update,
+};
 use failure::ResultExt;
 use shaku::Provider;
 
-/// Describes interactions with the list data store
+/// Describes interactions with the list data store. Regexes and wildcards
+/// share the regex table, and are told apart by the `regex_wildcard` table.
 #[cfg_attr(test, mockall::automock)]
 pub trait ListRepository: Send {
-    /// Get all of the domains in the list
-    fn get(&self, list: List) -> Result<Vec<String>, Error>;
+    /// Get all of the entries in the list, including disabled entries
+    fn get(&self, list: List) -> Result<Vec<ListEntry>, Error>;
+
+    /// Get the entry for the domain
+    fn get_entry(&self, list: List, domain: &str) -> Result<ListEntry, Error>;
 
-    /// Check if the list contains the domain
+    /// Check if the list contains the domain, whether it is enabled or not
     fn contains(&self, list: List, domain: &str) -> Result<bool, Error>;
 
-    /// Add the domain to the list
+    /// Add the domain to the list. If the domain is already in the list's
+    /// table, such as a regex which is already a wildcard, `AlreadyExists` is
+    /// returned.
     fn add(&self, list: List, domain: &str) -> Result<(), Error>;
 
+    /// Add the domains to the list in a single transaction, optionally
+    /// removing each added domain from another list. For each domain, the
+    /// result is true if it was added and false if it was already in the
+    /// list's table.
+    fn add_all(
+        &self,
+        list: List,
+        domains: &[String],
+        remove_from: Option<List>,
+    ) -> Result<Vec<bool>, Error>;
+
+    /// Change the enabled status or comment of the domain's entry
+    fn update(&self, list: List, domain: &str, patch: &ListEntryPatch) -> Result<(), Error>;
+
     /// Remove the domain from the list
     fn remove(&self, list: List, domain: &str) -> Result<(), Error>;
 }
@@ -42,133 +68,353 @@ pub struct ListRepositoryImpl {
 }
 
 impl ListRepository for ListRepositoryImpl {
-    fn get(&self, list: List) -> Result<Vec<String>, Error> {
+    fn get(&self, list: List) -> Result<Vec<ListEntry>, Error> {
         let db = &self.db as &SqliteConnection;
 
         match list {
             List::White => {
                 use crate::databases::gravity::whitelist::dsl::*;
-                whitelist.select(domain).filter(enabled.eq(true)).load(db)
+                whitelist.order(id).load(db)
             }
             List::Black => {
                 use crate::databases::gravity::blacklist::dsl::*;
-                blacklist.select(domain).filter(enabled.eq(true)).load(db)
+                blacklist.order(id).load(db)
             }
             List::Regex => {
                 use crate::databases::gravity::regex::dsl::*;
-                regex.select(domain).filter(enabled.eq(true)).load(db)
+                regex.filter(id.ne_all(wildcard_ids())).order(id).load(db)
+            }
+            List::Wildcard => {
+                use crate::databases::gravity::regex::dsl::*;
+                regex.filter(id.eq_any(wildcard_ids())).order(id).load(db)
             }
         }
         .context(ErrorKind::GravityDatabase)
         .map_err(Error::from)
     }
 
-    fn contains(&self, list: List, input_domain: &str) -> Result<bool, Error> {
+    fn get_entry(&self, list: List, input_domain: &str) -> Result<ListEntry, Error> {
         let db = &self.db as &SqliteConnection;
 
         match list {
             List::White => {
                 use crate::databases::gravity::whitelist::dsl::*;
-                select(exists(
-                    whitelist
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
-                ))
-                .get_result(db)
+                whitelist
+                    .filter(domain.eq(input_domain))
+                    .first(db)
+                    .optional()
             }
             List::Black => {
                 use crate::databases::gravity::blacklist::dsl::*;
-                select(exists(
-                    blacklist
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
-                ))
-                .get_result(db)
+                blacklist
+                    .filter(domain.eq(input_domain))
+                    .first(db)
+                    .optional()
             }
             List::Regex => {
                 use crate::databases::gravity::regex::dsl::*;
-                select(exists(
-                    regex
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
-                ))
-                .get_result(db)
+                regex
+                    .filter(domain.eq(input_domain))
+                    .filter(id.ne_all(wildcard_ids()))
+                    .first(db)
+                    .optional()
+            }
+            List::Wildcard => {
+                use crate::databases::gravity::regex::dsl::*;
+                regex
+                    .filter(domain.eq(input_domain))
+                    .filter(id.eq_any(wildcard_ids()))
+                    .first(db)
+                    .optional()
             }
         }
+        .context(ErrorKind::GravityDatabase)?
+        .ok_or_else(|| Error::from(ErrorKind::NotFound))
+    }
+
+    fn contains(&self, list: List, domain: &str) -> Result<bool, Error> {
+        contains_query(&self.db, list, domain)
+            .context(ErrorKind::GravityDatabase)
+            .map_err(Error::from)
+    }
+
+    fn add(&self, list: List, domain: &str) -> Result<(), Error> {
+        if taken_query(&self.db, list, domain).context(ErrorKind::GravityDatabase)? {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+
+        insert_query(&self.db, list, domain).context(ErrorKind::GravityDatabase)?;
+
+        Ok(())
+    }
+
+    fn add_all(
+        &self,
+        list: List,
+        domains: &[String],
+        remove_from: Option<List>,
+    ) -> Result<Vec<bool>, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        db.transaction(|| {
+            let mut added = Vec::with_capacity(domains.len());
+
+            for domain in domains {
+                // Domains already in the list's table (or earlier in the
+                // input) are skipped
+                if taken_query(db, list, domain)? {
+                    added.push(false);
+                    continue;
+                }
+
+                insert_query(db, list, domain)?;
+
+                if let Some(other_list) = remove_from {
+                    delete_query(db, other_list, domain)?;
+                }
+
+                added.push(true);
+            }
+
+            Ok(added)
+        })
         .context(ErrorKind::GravityDatabase)
         .map_err(Error::from)
     }
 
-    fn add(&self, list: List, input_domain: &str) -> Result<(), Error> {
+    fn update(&self, list: List, input_domain: &str, patch: &ListEntryPatch) -> Result<(), Error> {
         let db = &self.db as &SqliteConnection;
 
-        match list {
+        // Diesel can not execute an update without changes
+        if patch.is_empty() {
+            return Ok(());
+        }
+
+        // An empty comment is stored as NULL
+        let new_comment = patch
+            .comment
+            .as_deref()
+            .map(|value| Some(value).filter(|value| !value.is_empty()));
+
+        let updated = match list {
             List::White => {
                 use crate::databases::gravity::whitelist::dsl::*;
-                insert_into(whitelist)
-                    .values(&(domain.eq(input_domain), enabled.eq(true)))
+                update(whitelist.filter(domain.eq(input_domain)))
+                    .set((
+                        patch.enabled.map(|value| enabled.eq(value)),
+                        new_comment.map(|value| comment.eq(value)),
+                    ))
                     .execute(db)
             }
             List::Black => {
                 use crate::databases::gravity::blacklist::dsl::*;
-                insert_into(blacklist)
-                    .values(&(domain.eq(input_domain), enabled.eq(true)))
+                update(blacklist.filter(domain.eq(input_domain)))
+                    .set((
+                        patch.enabled.map(|value| enabled.eq(value)),
+                        new_comment.map(|value| comment.eq(value)),
+                    ))
                     .execute(db)
             }
             List::Regex => {
                 use crate::databases::gravity::regex::dsl::*;
-                insert_into(regex)
-                    .values(&(domain.eq(input_domain), enabled.eq(true)))
-                    .execute(db)
-            }
-        }
-        .context(ErrorKind::GravityDatabase)?;
-
-        Ok(())
-    }
-
-    fn remove(&self, list: List, input_domain: &str) -> Result<(), Error> {
-        let db = &self.db as &SqliteConnection;
-
-        match list {
-            List::White => {
-                use crate::databases::gravity::whitelist::dsl::*;
-                delete(
-                    whitelist
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
-                )
-                .execute(db)
-            }
-            List::Black => {
-                use crate::databases::gravity::blacklist::dsl::*;
-                delete(
-                    blacklist
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
+                update(
+                    regex
+                        .filter(domain.eq(input_domain))
+                        .filter(id.ne_all(wildcard_ids())),
                 )
+                .set((
+                    patch.enabled.map(|value| enabled.eq(value)),
+                    new_comment.map(|value| comment.eq(value)),
+                ))
                 .execute(db)
             }
-            List::Regex => {
+            List::Wildcard => {
                 use crate::databases::gravity::regex::dsl::*;
-                delete(
+                update(
                     regex
-                        .filter(enabled.eq(true))
-                        .filter(domain.eq(input_domain)),
+                        .filter(domain.eq(input_domain))
+                        .filter(id.eq_any(wildcard_ids())),
                 )
+                .set((
+                    patch.enabled.map(|value| enabled.eq(value)),
+                    new_comment.map(|value| comment.eq(value)),
+                ))
                 .execute(db)
             }
         }
         .context(ErrorKind::GravityDatabase)?;
 
+        if updated == 0 {
+            return Err(Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, list: List, domain: &str) -> Result<(), Error> {
+        delete_query(&self.db, list, domain).context(ErrorKind::GravityDatabase)?;
+
         Ok(())
     }
 }
 
+/// Check if the list contains the domain
+fn contains_query(db: &SqliteConnection, list: List, input_domain: &str) -> QueryResult<bool> {
+    match list {
+        List::White => {
+            use crate::databases::gravity::whitelist::dsl::*;
+            select(exists(whitelist.filter(domain.eq(input_domain)))).get_result(db)
+        }
+        List::Black => {
+            use crate::databases::gravity::blacklist::dsl::*;
+            select(exists(blacklist.filter(domain.eq(input_domain)))).get_result(db)
+        }
+        List::Regex => {
+            use crate::databases::gravity::regex::dsl::*;
+            select(exists(
+                regex
+                    .filter(domain.eq(input_domain))
+                    .filter(id.ne_all(wildcard_ids())),
+            ))
+            .get_result(db)
+        }
+        List::Wildcard => {
+            use crate::databases::gravity::regex::dsl::*;
+            select(exists(
+                regex
+                    .filter(domain.eq(input_domain))
+                    .filter(id.eq_any(wildcard_ids())),
+            ))
+            .get_result(db)
+        }
+    }
+}
+
+/// Check if the list's table already has an entry for the domain. Regexes
+/// and wildcards share the regex table, so a regex can only be in one of them.
+fn taken_query(db: &SqliteConnection, list: List, input_domain: &str) -> QueryResult<bool> {
+    match list {
+        List::Regex | List::Wildcard => {
+            use crate::databases::gravity::regex::dsl::*;
+            select(exists(regex.filter(domain.eq(input_domain)))).get_result(db)
+        }
+        _ => contains_query(db, list, input_domain),
+    }
+}
+
+/// The IDs of the regex entries which are wildcards
+fn wildcard_ids() -> Select<regex_wildcard::table, regex_wildcard::regex_id> {
+    regex_wildcard::table.select(regex_wildcard::regex_id)
+}
+
+/// Insert an enabled entry for the domain
+fn insert_query(db: &SqliteConnection, list: List, input_domain: &str) -> QueryResult<usize> {
+    match list {
+        List::White => {
+            use crate::databases::gravity::whitelist::dsl::*;
+            insert_into(whitelist)
+                .values(&(domain.eq(input_domain), enabled.eq(true)))
+                .execute(db)
+        }
+        List::Black => {
+            use crate::databases::gravity::blacklist::dsl::*;
+            insert_into(blacklist)
+                .values(&(domain.eq(input_domain), enabled.eq(true)))
+                .execute(db)
+        }
+        List::Regex => {
+            use crate::databases::gravity::regex::dsl::*;
+            insert_into(regex)
+                .values(&(domain.eq(input_domain), enabled.eq(true)))
+                .execute(db)
+        }
+        List::Wildcard => db.transaction(|| {
+            use crate::databases::gravity::regex::dsl::*;
+            insert_into(regex)
+                .values(&(domain.eq(input_domain), enabled.eq(true)))
+                .execute(db)?;
+
+            let regex_id: i32 = regex.select(id).filter(domain.eq(input_domain)).first(db)?;
+            insert_into(regex_wildcard::table)
+                .values(regex_wildcard::regex_id.eq(regex_id))
+                .execute(db)
+        }),
+    }
+}
+
+/// Delete the domain's entry. The group assignments and wildcard flag
+/// reference the entry, so they are removed first.
+fn delete_query(db: &SqliteConnection, list: List, input_domain: &str) -> QueryResult<usize> {
+    db.transaction(|| match list {
+        List::White => {
+            use crate::databases::gravity::{whitelist::dsl::*, whitelist_by_group};
+            delete(
+                whitelist_by_group::table.filter(
+                    whitelist_by_group::whitelist_id
+                        .eq_any(whitelist.select(id).filter(domain.eq(input_domain))),
+                ),
+            )
+            .execute(db)?;
+            delete(whitelist.filter(domain.eq(input_domain))).execute(db)
+        }
+        List::Black => {
+            use crate::databases::gravity::{blacklist::dsl::*, blacklist_by_group};
+            delete(
+                blacklist_by_group::table.filter(
+                    blacklist_by_group::blacklist_id
+                        .eq_any(blacklist.select(id).filter(domain.eq(input_domain))),
+                ),
+            )
+            .execute(db)?;
+            delete(blacklist.filter(domain.eq(input_domain))).execute(db)
+        }
+        List::Regex => {
+            use crate::databases::gravity::{regex::dsl::*, regex_by_group};
+            let regex_ids: Vec<i32> = regex
+                .select(id)
+                .filter(domain.eq(input_domain))
+                .filter(id.ne_all(wildcard_ids()))
+                .load(db)?;
+
+            delete(regex_by_group::table.filter(regex_by_group::regex_id.eq_any(&regex_ids)))
+                .execute(db)?;
+            delete(regex.filter(id.eq_any(&regex_ids))).execute(db)
+        }
+        List::Wildcard => {
+            use crate::databases::gravity::{regex::dsl::*, regex_by_group};
+            let regex_ids: Vec<i32> = regex
+                .select(id)
+                .filter(domain.eq(input_domain))
+                .filter(id.eq_any(wildcard_ids()))
+                .load(db)?;
+
+            delete(regex_by_group::table.filter(regex_by_group::regex_id.eq_any(&regex_ids)))
+                .execute(db)?;
+            delete(regex_wildcard::table.filter(regex_wildcard::regex_id.eq_any(&regex_ids)))
+                .execute(db)?;
+            delete(regex.filter(id.eq_any(&regex_ids))).execute(db)
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::{ListRepository, ListRepositoryImpl};
-    use crate::{databases::gravity::connect_to_gravity_test_db, services::lists::List};
+    use crate::{
+        databases::gravity::connect_to_gravity_test_db,
+        services::lists::{List, ListEntry, ListEntryPatch},
+        util::ErrorKind,
+    };
+    use diesel::{connection::SimpleConnection, SqliteConnection};
+
+    /// Get the domains of all the entries in the list
+    fn get_domains(repo: &ListRepositoryImpl, list: List) -> Vec<String> {
+        repo.get(list)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.domain)
+            .collect()
+    }
 
     /// Assert that the list of domains retrieved from the database equals the
     /// expected list
@@ -176,7 +422,7 @@ mod tests {
         let db = connect_to_gravity_test_db();
         let repo = ListRepositoryImpl { db };
 
-        let actual_domains = repo.get(list).unwrap();
+        let actual_domains = get_domains(&repo, list);
 
         assert_eq!(actual_domains, expected_domains);
     }
@@ -195,13 +441,13 @@ mod tests {
         let repo = ListRepositoryImpl { db };
 
         // Make sure it doesn't exist already
-        let initial_domains = repo.get(list).unwrap();
+        let initial_domains = get_domains(&repo, list);
         assert!(!initial_domains.contains(&domain.to_owned()));
 
         repo.add(list, domain).unwrap();
 
         // Make sure it was added
-        let domains = repo.get(list).unwrap();
+        let domains = get_domains(&repo, list);
         assert!(domains.contains(&domain.to_owned()));
     }
 
@@ -211,22 +457,35 @@ mod tests {
         let repo = ListRepositoryImpl { db };
 
         // Make sure the domain is on the list
-        let domains = repo.get(list).unwrap();
+        let domains = get_domains(&repo, list);
         assert!(domains.contains(&domain.to_owned()));
 
         repo.remove(list, domain).unwrap();
 
         // Make sure it was removed
-        let domains = repo.get(list).unwrap();
+        let domains = get_domains(&repo, list);
         assert!(!domains.contains(&domain.to_owned()));
     }
 
     /// Getting the lists should return the expected domains
     #[test]
     fn get() {
-        get_test(List::White, vec!["test.com".to_owned()]);
-        get_test(List::Black, vec!["example.com".to_owned()]);
-        get_test(List::Regex, vec!["(^|\\.)example\\.com$".to_owned()]);
+        get_test(
+            List::White,
+            vec!["test.com".to_owned(), "disabled-white.com".to_owned()],
+        );
+        get_test(
+            List::Black,
+            vec!["example.com".to_owned(), "disabled-black.com".to_owned()],
+        );
+        get_test(
+            List::Regex,
+            vec![
+                "(^|\\.)example\\.com$".to_owned(),
+                "disabled\\-regex\\.com".to_owned(),
+            ],
+        );
+        get_test(List::Wildcard, vec!["(^|\\.)wildcard\\.com$".to_owned()]);
     }
 
     /// Assert that checking for an existing domain works
@@ -235,6 +494,7 @@ mod tests {
         contains_test(List::White, "test.com");
         contains_test(List::Black, "example.com");
         contains_test(List::Regex, "(^|\\.)example\\.com$");
+        contains_test(List::Wildcard, "(^|\\.)wildcard\\.com$");
     }
 
     /// Adding new domains to the lists should add the domains
@@ -243,6 +503,7 @@ mod tests {
         add_test(List::White, "whitelist.com");
         add_test(List::Black, "blacklist.com");
         add_test(List::Regex, "regex.com");
+        add_test(List::Wildcard, "(^|\\.)new\\.com$");
     }
 
     /// Deleting existing domains from the lists should work
@@ -251,5 +512,189 @@ mod tests {
         delete_test(List::White, "test.com");
         delete_test(List::Black, "example.com");
         delete_test(List::Regex, "(^|\\.)example\\.com$");
+        delete_test(List::Wildcard, "(^|\\.)wildcard\\.com$");
+    }
+
+    /// Regexes and wildcards share the regex table, but each list only sees
+    /// its own entries
+    #[test]
+    fn regex_and_wildcard_separate() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+        let wildcard = "(^|\\.)wildcard\\.com$";
+
+        assert!(!repo.contains(List::Regex, wildcard).unwrap());
+        assert!(!repo
+            .contains(List::Wildcard, "(^|\\.)example\\.com$")
+            .unwrap());
+        assert_eq!(
+            repo.add(List::Regex, wildcard).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+
+        // Removing the regex form does not remove the wildcard
+        repo.remove(List::Regex, wildcard).unwrap();
+        assert!(repo.contains(List::Wildcard, wildcard).unwrap());
+    }
+
+    /// The entry includes the metadata of the domain
+    #[test]
+    fn get_entry() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+
+        assert_eq!(
+            repo.get_entry(List::White, "disabled-white.com").unwrap(),
+            ListEntry {
+                id: 2,
+                domain: "disabled-white.com".to_owned(),
+                enabled: false,
+                date_added: 1557723854,
+                date_modified: 1557723911,
+                comment: None
+            }
+        );
+        assert_eq!(
+            repo.get_entry(List::White, "not.listed")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Disabled domains are still contained in the list, and can be removed
+    #[test]
+    fn disabled_entries() {
+        contains_test(List::White, "disabled-white.com");
+        contains_test(List::Black, "disabled-black.com");
+        contains_test(List::Regex, "disabled\\-regex\\.com");
+        delete_test(List::Black, "disabled-black.com");
+    }
+
+    /// Only the given properties are changed, and an empty comment removes the
+    /// comment
+    #[test]
+    fn update() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+
+        repo.update(
+            List::Black,
+            "example.com",
+            &ListEntryPatch {
+                enabled: Some(false),
+                comment: Some("Disabled for testing".to_owned()),
+            },
+        )
+        .unwrap();
+        let entry = repo.get_entry(List::Black, "example.com").unwrap();
+        assert!(!entry.enabled);
+        assert_eq!(entry.comment, Some("Disabled for testing".to_owned()));
+
+        repo.update(
+            List::Black,
+            "example.com",
+            &ListEntryPatch {
+                enabled: None,
+                comment: Some(String::new()),
+            },
+        )
+        .unwrap();
+        let entry = repo.get_entry(List::Black, "example.com").unwrap();
+        assert!(!entry.enabled);
+        assert_eq!(entry.comment, None);
+    }
+
+    /// Updating a domain which is not on the list is an error
+    #[test]
+    fn update_missing() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+
+        assert_eq!(
+            repo.update(
+                List::Regex,
+                "not.listed",
+                &ListEntryPatch {
+                    enabled: Some(true),
+                    comment: None
+                }
+            )
+            .unwrap_err()
+            .kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Domains already on the list, or earlier in the input, are skipped, and
+    /// added domains are removed from the other list
+    #[test]
+    fn add_all_duplicates() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+
+        let added = repo
+            .add_all(
+                List::Black,
+                &[
+                    "new.com".to_owned(),
+                    "example.com".to_owned(),
+                    "new.com".to_owned(),
+                    "test.com".to_owned(),
+                ],
+                Some(List::White),
+            )
+            .unwrap();
+
+        assert_eq!(added, vec![true, false, false, true]);
+        assert_eq!(
+            get_domains(&repo, List::Black),
+            vec![
+                "example.com".to_owned(),
+                "disabled-black.com".to_owned(),
+                "new.com".to_owned(),
+                "test.com".to_owned()
+            ]
+        );
+        assert_eq!(
+            get_domains(&repo, List::White),
+            vec!["disabled-white.com".to_owned()]
+        );
+    }
+
+    /// If one of the inserts fails, none of the domains are added
+    #[test]
+    fn add_all_rollback() {
+        let repo = ListRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        };
+
+        // Make the insert of one domain fail
+        (&repo.db as &SqliteConnection)
+            .batch_execute(
+                "CREATE TEMP TRIGGER fail_insert BEFORE INSERT ON blacklist \
+                 WHEN NEW.domain = 'fail.com' \
+                 BEGIN SELECT RAISE(ABORT, 'insert failed'); END;",
+            )
+            .unwrap();
+
+        let error = repo
+            .add_all(
+                List::Black,
+                &["new.com".to_owned(), "fail.com".to_owned()],
+                None,
+            )
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::GravityDatabase);
+        assert_eq!(
+            get_domains(&repo, List::Black),
+            vec!["example.com".to_owned(), "disabled-black.com".to_owned()]
+        );
     }
 }
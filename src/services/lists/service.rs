@@ -3,7 +3,7 @@
 // Network-wide ad blocking via your own hardware.
 //
 // API
-// List Service (Whitelist, Blacklist, Regexlist)
+// List Service (Whitelist, Blacklist, Regexlist, Wildcards)
 //
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
@@ -11,7 +11,9 @@
 use crate::{
     env::Env,
     ftl::FtlConnectionType,
-    services::lists::{List, ListRepository},
+    services::lists::{
+        import_domains, ImportResult, ImportStatus, List, ListEntry, ListEntryPatch, ListRepository,
+    },
     util::{Error, ErrorKind},
 };
 use failure::ResultExt;
@@ -22,18 +24,28 @@ use std::{
 };
 
 /// Describes interactions with the Pi-hole domain lists (whitelist, blacklist,
-/// and regexlist)
+/// regexlist, and wildcards). Wildcards are given and returned in the
+/// `*.example.com` form.
 #[cfg_attr(test, mockall::automock)]
 pub trait ListService: Send {
     /// Add a domain to the list and update FTL and other lists accordingly.
     /// Example: when adding to the whitelist, remove from the blacklist.
     fn add(&self, list: List, domain: &str) -> Result<(), Error>;
 
+    /// Add many domains to the list in a single transaction, and update FTL
+    /// once at the end. Like `add`, whitelisted domains are removed from the
+    /// blacklist and vice versa.
+    fn import(&self, list: List, domains: &[String]) -> Result<Vec<ImportResult>, Error>;
+
     /// Remove a domain from the list and update FTL
     fn remove(&self, list: List, domain: &str) -> Result<(), Error>;
 
-    /// Get all of the domains in the list
-    fn get(&self, list: List) -> Result<Vec<String>, Error>;
+    /// Change the enabled status or comment of a domain, update FTL if
+    /// needed, and return the updated entry
+    fn update(&self, list: List, domain: &str, patch: &ListEntryPatch) -> Result<ListEntry, Error>;
+
+    /// Get all of the entries in the list
+    fn get(&self, list: List) -> Result<Vec<ListEntry>, Error>;
 }
 
 /// The implementation of `ListService`
@@ -69,9 +81,10 @@ impl ListService for ListServiceImpl {
                 // Since we haven't hit an error yet, reload gravity
                 reload_gravity(List::Black, &self.env)
             }
-            List::Regex => {
-                // We only need to add it to the regex list
-                self.add_raw(List::Regex, domain)?;
+            List::Regex | List::Wildcard => {
+                // We only need to add it to the regex list. Wildcards are
+                // added in their canonical form.
+                self.add_raw(list, domain)?;
 
                 // Since we haven't hit an error yet, tell FTL to recompile
                 // regex
@@ -90,20 +103,88 @@ impl ListService for ListServiceImpl {
                 self.remove_raw(List::Black, domain)?;
                 reload_gravity(List::Black, &self.env)
             }
-            List::Regex => {
-                self.remove_raw(List::Regex, domain)?;
+            List::Regex | List::Wildcard => {
+                self.remove_raw(list, domain)?;
                 self.ftl.connect("recompile-regex")?.expect_eom()
             }
         }
     }
 
-    fn get(&self, list: List) -> Result<Vec<String>, Error> {
-        self.repo.get(list)
+    fn import(&self, list: List, domains: &[String]) -> Result<Vec<ImportResult>, Error> {
+        let remove_from = match list {
+            List::White => Some(List::Black),
+            List::Black => Some(List::White),
+            List::Regex | List::Wildcard => None,
+        };
+
+        let results = import_domains(
+            domains,
+            |domain| list.accepts(domain),
+            |valid_domains| {
+                let stored_domains: Vec<String> = valid_domains
+                    .iter()
+                    .map(|domain| list.to_stored(domain))
+                    .collect();
+
+                self.repo.add_all(list, &stored_domains, remove_from)
+            },
+        )?;
+
+        // Only update FTL if something changed
+        if results
+            .iter()
+            .any(|result| result.status == ImportStatus::Added)
+        {
+            self.reload(list)?;
+        }
+
+        Ok(results)
+    }
+
+    fn update(&self, list: List, domain: &str, patch: &ListEntryPatch) -> Result<ListEntry, Error> {
+        let stored_domain = list.to_stored(domain);
+        self.repo.update(list, &stored_domain, patch)?;
+
+        // Only the enabled status affects what FTL blocks
+        if patch.enabled.is_some() {
+            self.reload(list)?;
+        }
+
+        // Return the domain in its canonical form, such as a lowercase
+        // wildcard
+        let mut entry = self.repo.get_entry(list, &stored_domain)?;
+        if let Some(domain) = list.from_stored(&entry.domain) {
+            entry.domain = domain;
+        }
+
+        Ok(entry)
+    }
+
+    fn get(&self, list: List) -> Result<Vec<ListEntry>, Error> {
+        // Return the entries in the form used by the list, leaving out
+        // wildcards which are not in the canonical form
+        Ok(self
+            .repo
+            .get(list)?
+            .into_iter()
+            .filter_map(|mut entry| {
+                entry.domain = list.from_stored(&entry.domain)?;
+                Some(entry)
+            })
+            .collect())
     }
 }
 
 impl ListServiceImpl {
-    /// Simply add a domain to the list
+    /// Update FTL after the list has changed
+    fn reload(&self, list: List) -> Result<(), Error> {
+        match list {
+            List::White | List::Black => reload_gravity(list, &self.env),
+            List::Regex | List::Wildcard => self.ftl.connect("recompile-regex")?.expect_eom(),
+        }
+    }
+
+    /// Simply add a domain to the list, in the form it is stored in
     fn add_raw(&self, list: List, domain: &str) -> Result<(), Error> {
         // Check if it's a valid domain before doing anything
         if !list.accepts(domain) {
@@ -111,11 +192,12 @@ impl ListServiceImpl {
         }
 
         // Check if the domain is already in the list
-        if self.repo.contains(list, domain)? {
+        let domain = list.to_stored(domain);
+        if self.repo.contains(list, &domain)? {
             return Err(Error::from(ErrorKind::AlreadyExists));
         }
 
-        self.repo.add(list, domain)
+        self.repo.add(list, &domain)
     }
 
     /// Try to remove a domain from the list, but it is not an error if the
@@ -135,7 +217,7 @@ impl ListServiceImpl {
         }
     }
 
-    /// Simply remove a domain from the list
+    /// Simply remove a domain from the list, in the form it is stored in
     fn remove_raw(&self, list: List, domain: &str) -> Result<(), Error> {
         // Check if it's a valid domain before doing anything
         if !list.accepts(domain) {
@@ -143,11 +225,12 @@ impl ListServiceImpl {
         }
 
         // Check if the domain is not in the list
-        if !self.repo.contains(list, domain)? {
+        let domain = list.to_stored(domain);
+        if !self.repo.contains(list, &domain)? {
             return Err(Error::from(ErrorKind::NotFound));
         }
 
-        self.repo.remove(list, domain)
+        self.repo.remove(list, &domain)
     }
 }
 
@@ -188,7 +271,10 @@ mod test {
     use super::List;
     use crate::{
         ftl::FtlConnectionType,
-        services::lists::{ListService, ListServiceImpl, MockListRepository},
+        services::lists::{
+            ImportResult, ImportStatus, ListEntry, ListEntryPatch, ListService, ListServiceImpl,
+            MockListRepository,
+        },
         testing::{write_eom, TestEnvBuilder},
     };
     use mockall::predicate::*;
@@ -204,7 +290,19 @@ mod test {
         FtlConnectionType::Test(command_map)
     }
 
-    /// Test getting the domains for a list
+    /// Create an enabled entry for the domain
+    fn entry(domain: &str) -> ListEntry {
+        ListEntry {
+            id: 1,
+            domain: domain.to_owned(),
+            enabled: true,
+            date_added: 1557712172,
+            date_modified: 1557712172,
+            comment: None,
+        }
+    }
+
+    /// Test getting the entries for a list
     fn get_test(list: List, domain: &str) {
         let env = TestEnvBuilder::new().build();
         let ftl = get_ftl();
@@ -212,7 +310,7 @@ mod test {
 
         repo.expect_get()
             .with(eq(list))
-            .return_const(Ok(vec![domain.to_owned()]));
+            .return_const(Ok(vec![entry(domain)]));
 
         let service = ListServiceImpl {
             repo: Box::new(repo),
@@ -220,7 +318,7 @@ mod test {
             ftl: Arc::new(ftl),
         };
 
-        assert_eq!(service.get(list).unwrap(), vec![domain.to_owned()]);
+        assert_eq!(service.get(list).unwrap(), vec![entry(domain)]);
     }
 
     /// Test successfully deleting a domain from a list
@@ -331,6 +429,92 @@ mod test {
         service.add(List::Regex, "example.com").unwrap();
     }
 
+    /// Wildcards are added to the regex list in their canonical form
+    #[test]
+    fn add_wildcard() {
+        let env = TestEnvBuilder::new().build();
+        let ftl = get_ftl();
+        let mut repo = MockListRepository::new();
+
+        repo.expect_contains()
+            .with(eq(List::Wildcard), eq("(^|\\.)example\\.com$"))
+            .return_const(Ok(false));
+        repo.expect_add()
+            .with(eq(List::Wildcard), eq("(^|\\.)example\\.com$"))
+            .return_const(Ok(()));
+
+        let service = ListServiceImpl {
+            repo: Box::new(repo),
+            env: Arc::new(env),
+            ftl: Arc::new(ftl),
+        };
+
+        service.add(List::Wildcard, "*.Example.com").unwrap();
+    }
+
+    /// Wildcards are returned in their friendly form, and wildcards which are
+    /// not in the canonical form are left out
+    #[test]
+    fn get_wildcards() {
+        let env = TestEnvBuilder::new().build();
+        let ftl = get_ftl();
+        let mut repo = MockListRepository::new();
+
+        repo.expect_get()
+            .with(eq(List::Wildcard))
+            .return_const(Ok(vec![
+                entry("(^|\\.)example\\.com$"),
+                entry("^ads?\\.example\\.net$"),
+            ]));
+
+        let service = ListServiceImpl {
+            repo: Box::new(repo),
+            env: Arc::new(env),
+            ftl: Arc::new(ftl),
+        };
+
+        assert_eq!(
+            service.get(List::Wildcard).unwrap(),
+            vec![entry("*.example.com")]
+        );
+    }
+
+    /// Updating a wildcard returns it in its canonical form
+    #[test]
+    fn update_wildcard() {
+        let env = TestEnvBuilder::new().build();
+        let ftl = get_ftl();
+        let mut repo = MockListRepository::new();
+        let patch = ListEntryPatch {
+            enabled: None,
+            comment: Some("Ads".to_owned()),
+        };
+
+        repo.expect_update()
+            .with(
+                eq(List::Wildcard),
+                eq("(^|\\.)example\\.com$"),
+                eq(patch.clone()),
+            )
+            .return_const(Ok(()));
+        repo.expect_get_entry()
+            .with(eq(List::Wildcard), eq("(^|\\.)example\\.com$"))
+            .return_const(Ok(entry("(^|\\.)example\\.com$")));
+
+        let service = ListServiceImpl {
+            repo: Box::new(repo),
+            env: Arc::new(env),
+            ftl: Arc::new(ftl),
+        };
+
+        assert_eq!(
+            service
+                .update(List::Wildcard, "*.Example.COM", &patch)
+                .unwrap(),
+            entry("*.example.com")
+        );
+    }
+
     #[test]
     fn delete_whitelist() {
         delete_test(List::White, "whitelist.com");
@@ -345,4 +529,85 @@ mod test {
     fn delete_regexlist() {
         delete_test(List::Regex, "regex.com");
     }
+
+    /// Updating an entry returns the updated entry
+    #[test]
+    fn update() {
+        let env = TestEnvBuilder::new().build();
+        let ftl = get_ftl();
+        let mut repo = MockListRepository::new();
+        let patch = ListEntryPatch {
+            enabled: Some(true),
+            comment: None,
+        };
+
+        repo.expect_update()
+            .with(eq(List::Regex), eq("regex.com"), eq(patch.clone()))
+            .return_const(Ok(()));
+        repo.expect_get_entry()
+            .with(eq(List::Regex), eq("regex.com"))
+            .return_const(Ok(entry("regex.com")));
+
+        let service = ListServiceImpl {
+            repo: Box::new(repo),
+            env: Arc::new(env),
+            ftl: Arc::new(ftl),
+        };
+
+        assert_eq!(
+            service.update(List::Regex, "regex.com", &patch).unwrap(),
+            entry("regex.com")
+        );
+    }
+
+    /// Importing validates the domains, adds the valid ones in one call, and
+    /// reports the result of each domain
+    #[test]
+    fn import() {
+        let env = TestEnvBuilder::new().build();
+        let ftl = get_ftl();
+        let mut repo = MockListRepository::new();
+
+        repo.expect_add_all()
+            .withf(|list, domains, remove_from| {
+                *list == List::Black
+                    && domains == ["example.com".to_owned(), "example.net".to_owned()]
+                    && *remove_from == Some(List::White)
+            })
+            .times(1)
+            .return_const(Ok(vec![true, false]));
+
+        let service = ListServiceImpl {
+            repo: Box::new(repo),
+            env: Arc::new(env),
+            ftl: Arc::new(ftl),
+        };
+
+        assert_eq!(
+            service
+                .import(
+                    List::Black,
+                    &[
+                        "example.com".to_owned(),
+                        "in valid".to_owned(),
+                        "example.net".to_owned()
+                    ]
+                )
+                .unwrap(),
+            vec![
+                ImportResult {
+                    domain: "example.com".to_owned(),
+                    status: ImportStatus::Added
+                },
+                ImportResult {
+                    domain: "in valid".to_owned(),
+                    status: ImportStatus::Invalid
+                },
+                ImportResult {
+                    domain: "example.net".to_owned(),
+                    status: ImportStatus::Duplicate
+                }
+            ]
+        );
+    }
 }
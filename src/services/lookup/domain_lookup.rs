@@ -0,0 +1,199 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Domain Lookup Results
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+/// A whitelist, blacklist, or regex list entry which matches the domain
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LookupEntry {
+    pub id: i32,
+    pub domain: String,
+    pub enabled: bool,
+    pub comment: Option<String>,
+    pub groups: Vec<i32>,
+}
+
+impl LookupEntry {
+    /// Check if the entry is used by FTL. Disabled entries are ignored, as are
+    /// entries whose groups are all disabled. Entries without a group are
+    /// always used.
+    pub fn is_active(&self, enabled_groups: &[i32]) -> bool {
+        self.enabled
+            && (self.groups.is_empty()
+                || self
+                    .groups
+                    .iter()
+                    .any(|group_id| enabled_groups.contains(group_id)))
+    }
+}
+
+/// Where the domain was found in the gravity table
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GravityMatch {
+    /// If the domain is in the gravity table
+    pub found: bool,
+    /// The enabled adlists which contained the domain at the last gravity
+    /// update. Whitelisted domains are left out of gravity, but are still
+    /// reported here.
+    pub adlists: Vec<i32>,
+}
+
+/// The decision FTL would make for the domain
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Allowed by an exact whitelist entry, even if it is blocked elsewhere
+    Whitelisted,
+    /// Blocked by an exact blacklist entry
+    Blacklisted,
+    /// Blocked because it is in the gravity table
+    Gravity,
+    /// Blocked by a regex list entry
+    Regex,
+    /// Not found on any list, so it is allowed
+    NotListed,
+}
+
+/// Everything known about why a domain is blocked or allowed
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DomainLookup {
+    pub domain: String,
+    pub whitelist: Option<LookupEntry>,
+    pub blacklist: Option<LookupEntry>,
+    pub regex: Vec<LookupEntry>,
+    pub gravity: GravityMatch,
+    pub blocked: bool,
+    pub verdict: Verdict,
+}
+
+impl DomainLookup {
+    /// Combine the matches of a domain and decide what FTL would do with it.
+    /// FTL checks the whitelist first, then the blacklist, gravity, and
+    /// finally the regex list.
+    pub fn new(
+        domain: String,
+        whitelist: Option<LookupEntry>,
+        blacklist: Option<LookupEntry>,
+        regex: Vec<LookupEntry>,
+        gravity: GravityMatch,
+        enabled_groups: &[i32],
+    ) -> Self {
+        let is_active = |entry: &Option<LookupEntry>| {
+            entry
+                .as_ref()
+                .map_or(false, |entry| entry.is_active(enabled_groups))
+        };
+
+        let verdict = if is_active(&whitelist) {
+            Verdict::Whitelisted
+        } else if is_active(&blacklist) {
+            Verdict::Blacklisted
+        } else if gravity.found {
+            Verdict::Gravity
+        } else if regex.iter().any(|entry| entry.is_active(enabled_groups)) {
+            Verdict::Regex
+        } else {
+            Verdict::NotListed
+        };
+
+        DomainLookup {
+            domain,
+            whitelist,
+            blacklist,
+            regex,
+            gravity,
+            blocked: verdict != Verdict::Whitelisted && verdict != Verdict::NotListed,
+            verdict,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DomainLookup, GravityMatch, LookupEntry, Verdict};
+
+    fn entry(enabled: bool, groups: Vec<i32>) -> LookupEntry {
+        LookupEntry {
+            id: 1,
+            domain: "example.com".to_owned(),
+            enabled,
+            comment: None,
+            groups,
+        }
+    }
+
+    fn gravity(found: bool) -> GravityMatch {
+        GravityMatch {
+            found,
+            adlists: Vec::new(),
+        }
+    }
+
+    /// The whitelist takes priority over every other list
+    #[test]
+    fn whitelist_wins() {
+        let lookup = DomainLookup::new(
+            "example.com".to_owned(),
+            Some(entry(true, vec![])),
+            Some(entry(true, vec![])),
+            vec![entry(true, vec![])],
+            gravity(true),
+            &[],
+        );
+
+        assert_eq!(lookup.verdict, Verdict::Whitelisted);
+        assert!(!lookup.blocked);
+    }
+
+    /// Disabled entries and entries in disabled groups are ignored
+    #[test]
+    fn inactive_entries_ignored() {
+        let lookup = DomainLookup::new(
+            "example.com".to_owned(),
+            Some(entry(false, vec![])),
+            Some(entry(true, vec![2])),
+            vec![entry(true, vec![1])],
+            gravity(false),
+            &[1],
+        );
+
+        assert_eq!(lookup.verdict, Verdict::Regex);
+        assert!(lookup.blocked);
+    }
+
+    /// Gravity is checked before the regex list
+    #[test]
+    fn gravity_before_regex() {
+        let lookup = DomainLookup::new(
+            "example.com".to_owned(),
+            None,
+            None,
+            vec![entry(true, vec![])],
+            gravity(true),
+            &[],
+        );
+
+        assert_eq!(lookup.verdict, Verdict::Gravity);
+    }
+
+    /// A domain which is not on any list is not blocked
+    #[test]
+    fn not_listed() {
+        let lookup = DomainLookup::new(
+            "example.com".to_owned(),
+            None,
+            None,
+            vec![],
+            gravity(false),
+            &[],
+        );
+
+        assert_eq!(lookup.verdict, Verdict::NotListed);
+        assert!(!lookup.blocked);
+    }
+}
@@ -0,0 +1,244 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Domain Lookup Database Repository
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    databases::gravity::{
+        adlist, adlist_domain, blacklist, blacklist_by_group, gravity, group, regex,
+        regex_by_group, whitelist, whitelist_by_group, GravityDatabase,
+    },
+    services::{
+        lists::List,
+        lookup::{DomainLookup, GravityMatch, LookupEntry},
+    },
+    util::{Error, ErrorKind},
+};
+use diesel::{dsl::exists, prelude::*, select};
+use failure::ResultExt;
+use shaku::Provider;
+
+/// A list entry as it is stored in the database, without its groups
+type EntryRecord = (i32, String, bool, Option<String>);
+
+/// Describes how to find everything which matches a domain
+#[cfg_attr(test, mockall::automock)]
+pub trait LookupRepository: Send {
+    /// Find the list entries, regex entries, and gravity data which match the
+    /// domain, and decide what FTL would do with it
+    fn lookup(&self, domain: &str) -> Result<DomainLookup, Error>;
+}
+
+/// The implementation of `LookupRepository`
+#[derive(Provider)]
+#[shaku(interface = LookupRepository)]
+pub struct LookupRepositoryImpl {
+    #[shaku(provide)]
+    db: Box<GravityDatabase>,
+}
+
+impl LookupRepository for LookupRepositoryImpl {
+    fn lookup(&self, domain: &str) -> Result<DomainLookup, Error> {
+        let db = &self.db as &SqliteConnection;
+
+        let whitelist_entry = find_exact(db, List::White, domain)?;
+        let blacklist_entry = find_exact(db, List::Black, domain)?;
+
+        // Regex entries are matched in Rust, since SQLite can not evaluate
+        // them. Invalid patterns never match.
+        let regex_records: Vec<EntryRecord> = regex::table
+            .select((regex::id, regex::domain, regex::enabled, regex::comment))
+            .order(regex::id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+        let mut regex_entries = Vec::new();
+        for record in regex_records {
+            let is_match = ::regex::Regex::new(&record.1)
+                .map(|pattern| pattern.is_match(domain))
+                .unwrap_or(false);
+
+            if is_match {
+                regex_entries.push(into_entry(db, List::Regex, record)?);
+            }
+        }
+
+        let found: bool = select(exists(gravity::table.filter(gravity::domain.eq(domain))))
+            .get_result(db)
+            .context(ErrorKind::GravityDatabase)?;
+        let adlists = adlist_domain::table
+            .inner_join(adlist::table)
+            .filter(adlist_domain::domain.eq(domain))
+            .filter(adlist::enabled.eq(true))
+            .select(adlist::id)
+            .order(adlist::id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        let enabled_groups: Vec<i32> = group::table
+            .filter(group::enabled.eq(true))
+            .select(group::id)
+            .load(db)
+            .context(ErrorKind::GravityDatabase)?;
+
+        Ok(DomainLookup::new(
+            domain.to_owned(),
+            whitelist_entry,
+            blacklist_entry,
+            regex_entries,
+            GravityMatch { found, adlists },
+            &enabled_groups,
+        ))
+    }
+}
+
+/// Find the whitelist or blacklist entry which exactly matches the domain
+fn find_exact(
+    db: &SqliteConnection,
+    list: List,
+    input_domain: &str,
+) -> Result<Option<LookupEntry>, Error> {
+    let record: Option<EntryRecord> = match list {
+        List::White => whitelist::table
+            .filter(whitelist::domain.eq(input_domain))
+            .select((
+                whitelist::id,
+                whitelist::domain,
+                whitelist::enabled,
+                whitelist::comment,
+            ))
+            .first(db)
+            .optional(),
+        List::Black => blacklist::table
+            .filter(blacklist::domain.eq(input_domain))
+            .select((
+                blacklist::id,
+                blacklist::domain,
+                blacklist::enabled,
+                blacklist::comment,
+            ))
+            .first(db)
+            .optional(),
+        List::Regex | List::Wildcard => regex::table
+            .filter(regex::domain.eq(input_domain))
+            .select((regex::id, regex::domain, regex::enabled, regex::comment))
+            .first(db)
+            .optional(),
+    }
+    .context(ErrorKind::GravityDatabase)?;
+
+    record
+        .map(|record| into_entry(db, list, record))
+        .transpose()
+}
+
+/// Load the groups of a list entry and combine them with the entry
+fn into_entry(
+    db: &SqliteConnection,
+    list: List,
+    (id, domain, enabled, comment): EntryRecord,
+) -> Result<LookupEntry, Error> {
+    let groups = match list {
+        List::White => whitelist_by_group::table
+            .filter(whitelist_by_group::whitelist_id.eq(id))
+            .select(whitelist_by_group::group_id)
+            .order(whitelist_by_group::group_id)
+            .load(db),
+        List::Black => blacklist_by_group::table
+            .filter(blacklist_by_group::blacklist_id.eq(id))
+            .select(blacklist_by_group::group_id)
+            .order(blacklist_by_group::group_id)
+            .load(db),
+        List::Regex | List::Wildcard => regex_by_group::table
+            .filter(regex_by_group::regex_id.eq(id))
+            .select(regex_by_group::group_id)
+            .order(regex_by_group::group_id)
+            .load(db),
+    }
+    .context(ErrorKind::GravityDatabase)?;
+
+    Ok(LookupEntry {
+        id,
+        domain,
+        enabled,
+        comment,
+        groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LookupRepository, LookupRepositoryImpl};
+    use crate::{databases::gravity::connect_to_gravity_test_db, services::lookup::Verdict};
+
+    fn repo() -> LookupRepositoryImpl {
+        LookupRepositoryImpl {
+            db: connect_to_gravity_test_db(),
+        }
+    }
+
+    /// A whitelisted domain is allowed even though it is in gravity
+    #[test]
+    fn whitelisted() {
+        let lookup = repo().lookup("test.com").unwrap();
+
+        assert_eq!(lookup.verdict, Verdict::Whitelisted);
+        assert!(!lookup.blocked);
+        assert_eq!(lookup.whitelist.unwrap().groups, vec![1]);
+        assert!(lookup.gravity.found);
+        assert_eq!(lookup.gravity.adlists, vec![1]);
+    }
+
+    /// Only the adlists which contain the domain are reported
+    #[test]
+    fn gravity_adlists() {
+        let lookup = repo().lookup("vriaj.com").unwrap();
+
+        assert_eq!(lookup.verdict, Verdict::Gravity);
+        assert!(lookup.blocked);
+        assert_eq!(lookup.gravity.adlists, vec![1, 2]);
+        assert_eq!(repo().lookup("vra4.com").unwrap().gravity.adlists, vec![2]);
+    }
+
+    /// Both the exact blacklist entry and the matching regex are reported
+    #[test]
+    fn blacklisted() {
+        let lookup = repo().lookup("example.com").unwrap();
+
+        assert_eq!(lookup.verdict, Verdict::Blacklisted);
+        assert!(lookup.blocked);
+        assert_eq!(lookup.blacklist.unwrap().id, 1);
+        assert_eq!(
+            lookup
+                .regex
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<i32>>(),
+            vec![1]
+        );
+        assert!(!lookup.gravity.found);
+    }
+
+    /// A domain which only matches a regex is blocked by the regex
+    #[test]
+    fn regex() {
+        let lookup = repo().lookup("ads.example.com").unwrap();
+
+        assert_eq!(lookup.verdict, Verdict::Regex);
+        assert_eq!(lookup.regex.len(), 1);
+        assert_eq!(lookup.regex[0].domain, "(^|\\.)example\\.com$");
+    }
+
+    /// Disabled regex entries are reported, but do not block the domain
+    #[test]
+    fn disabled_regex() {
+        let lookup = repo().lookup("disabled-regex.com").unwrap();
+
+        assert_eq!(lookup.verdict, Verdict::NotListed);
+        assert!(!lookup.regex[0].enabled);
+    }
+}
@@ -8,8 +8,15 @@
 // This file is copyright under the latest version of the EUPL.
 // Please see LICENSE file for your rights under this license.
 
+pub mod accounts;
+pub mod adlists;
+pub mod api_tokens;
 pub mod domain_audit;
+pub mod gravity;
+pub mod groups;
 pub mod lists;
+pub mod lookup;
+pub mod totp;
 
 use crate::{
     databases::{
@@ -19,9 +26,16 @@ use crate::{
     env::Env,
     ftl::FtlConnectionType,
 };
+use accounts::AccountServiceImpl;
+use adlists::{AdlistRepositoryImpl, AdlistStatusRepositoryImpl};
+use api_tokens::ApiTokenServiceImpl;
 use domain_audit::DomainAuditRepositoryImpl;
+use gravity::{GravityRepositoryImpl, GravityServiceImpl};
+use groups::GroupRepositoryImpl;
 use lists::{ListRepositoryImpl, ListServiceImpl};
+use lookup::LookupRepositoryImpl;
 use shaku::module;
+use totp::TotpServiceImpl;
 
 module! {
     pub PiholeModule {
@@ -35,6 +49,15 @@ module! {
             ListRepositoryImpl,
             ListServiceImpl,
             DomainAuditRepositoryImpl,
+            GroupRepositoryImpl,
+            AdlistRepositoryImpl,
+            AdlistStatusRepositoryImpl,
+            GravityRepositoryImpl,
+            GravityServiceImpl,
+            LookupRepositoryImpl,
+            ApiTokenServiceImpl,
+            TotpServiceImpl,
+            AccountServiceImpl,
             GravityDatabase,
             FtlDatabase
         ]
@@ -0,0 +1,521 @@
+// Pi-hole: A black hole for Internet advertisements
+// (c) 2019 Pi-hole, LLC (https://pi-hole.net)
+// Network-wide ad blocking via your own hardware.
+//
+// API
+// Two-Factor Authentication Service
+//
+// This file is copyright under the latest version of the EUPL.
+// Please see LICENSE file for your rights under this license.
+
+use crate::{
+    services::{
+        accounts::AccountService,
+        api_tokens::hash_secret,
+        totp::otp::{
+            base32_decode, base32_encode, generate_code, percent_encode, time_step, DIGITS,
+            TIME_STEP,
+        },
+    },
+    util::{Error, ErrorKind},
+};
+use rand::{rngs::OsRng, RngCore};
+use shaku::Provider;
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use subtle::ConstantTimeEq;
+
+/// The number of random bytes in a TOTP secret (160 bits, as recommended by
+/// RFC 4226)
+const SECRET_BYTES: usize = 20;
+
+/// The number of recovery codes generated during enrollment
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// The number of random bytes in a recovery code
+const RECOVERY_CODE_BYTES: usize = 5;
+
+/// The number of time steps before and after the current one which are also
+/// accepted, to allow for clock drift
+const ALLOWED_DRIFT: u64 = 1;
+
+/// Held while an account's data is read, checked, and written, so concurrent
+/// requests do not overwrite each other's changes or reuse a code
+static TOTP_LOCK: Mutex<()> = Mutex::new(());
+
+/// The data returned when enrolling in two-factor authentication. This is the
+/// only time the secret and recovery codes are available. The secret is not
+/// used until it is confirmed with a code.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+/// The two-factor authentication data of an account, as it is stored in the
+/// accounts file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TotpRecord {
+    /// The base32 encoded secret
+    secret: String,
+    /// If a code has been entered for the secret. Unconfirmed secrets are not
+    /// used to log in.
+    confirmed: bool,
+    /// The last time step which a code was used for. Codes from this step or
+    /// earlier are rejected to prevent replay.
+    last_step: u64,
+    /// The hex-encoded SHA-256 hashes of the unused recovery codes
+    recovery_codes: Vec<String>,
+}
+
+/// Describes interactions with time-based one-time password (TOTP) two-factor
+/// authentication. Each account enrolls separately.
+#[cfg_attr(test, mockall::automock)]
+pub trait TotpService: Send {
+    /// Check if the account has enrolled and confirmed two-factor
+    /// authentication
+    fn is_enrolled(&self, account: &str) -> Result<bool, Error>;
+
+    /// Start enrolling the account in two-factor authentication. The label
+    /// identifies the account in authenticator apps. An earlier unconfirmed
+    /// enrollment is replaced.
+    fn enroll(&self, account: &str, label: &str) -> Result<TotpEnrollment, Error>;
+
+    /// Finish enrolling the account by checking a code for the new secret
+    fn confirm(&self, account: &str, code: &str) -> Result<(), Error>;
+
+    /// Disable two-factor authentication for the account. A valid code or
+    /// recovery code is required.
+    fn disable(&self, account: &str, code: &str) -> Result<(), Error>;
+
+    /// Check a code or recovery code of the account. Codes can only be used
+    /// once.
+    fn verify(&self, account: &str, code: &str) -> Result<bool, Error>;
+}
+
+/// The implementation of `TotpService`, which stores the data of each account
+/// in the accounts file
+#[derive(Provider)]
+#[shaku(interface = TotpService)]
+pub struct TotpServiceImpl {
+    #[shaku(provide)]
+    account_service: Box<dyn AccountService>,
+}
+
+impl TotpService for TotpServiceImpl {
+    fn is_enrolled(&self, account: &str) -> Result<bool, Error> {
+        Ok(self
+            .account_service
+            .get_totp(account)?
+            .map_or(false, |record| record.confirmed))
+    }
+
+    fn enroll(&self, account: &str, label: &str) -> Result<TotpEnrollment, Error> {
+        let _lock = lock();
+
+        if self.is_enrolled(account)? {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+
+        let secret = base32_encode(&random_bytes(SECRET_BYTES));
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+
+        self.account_service.set_totp(
+            account,
+            Some(TotpRecord {
+                secret: secret.clone(),
+                confirmed: false,
+                last_step: 0,
+                recovery_codes: recovery_codes
+                    .iter()
+                    .map(|code| hash_secret(code))
+                    .collect(),
+            }),
+        )?;
+
+        Ok(TotpEnrollment {
+            uri: format!(
+                "otpauth://totp/Pi-hole:{}?secret={}&issuer=Pi-hole&algorithm=SHA1&digits={}&period={}",
+                percent_encode(label),
+                secret,
+                DIGITS,
+                TIME_STEP
+            ),
+            secret,
+            recovery_codes,
+        })
+    }
+
+    fn confirm(&self, account: &str, code: &str) -> Result<(), Error> {
+        self.confirm_at(account, code, now())
+    }
+
+    fn disable(&self, account: &str, code: &str) -> Result<(), Error> {
+        let _lock = lock();
+
+        let mut record = match self.account_service.get_totp(account)? {
+            Some(record) if record.confirmed => record,
+            _ => return Err(Error::from(ErrorKind::NotFound)),
+        };
+
+        if !check_code(&mut record, code, now(), true)? {
+            return Err(Error::from(ErrorKind::Unauthorized));
+        }
+
+        self.account_service.set_totp(account, None)
+    }
+
+    fn verify(&self, account: &str, code: &str) -> Result<bool, Error> {
+        self.verify_at(account, code, now())
+    }
+}
+
+impl TotpServiceImpl {
+    fn confirm_at(&self, account: &str, code: &str, now: u64) -> Result<(), Error> {
+        let _lock = lock();
+
+        let mut record = match self.account_service.get_totp(account)? {
+            Some(record) if !record.confirmed => record,
+            _ => return Err(Error::from(ErrorKind::NotFound)),
+        };
+
+        // Recovery codes can not confirm the secret, since they do not show
+        // that the authenticator app was set up
+        if !check_code(&mut record, code, now, false)? {
+            return Err(Error::from(ErrorKind::Unauthorized));
+        }
+
+        record.confirmed = true;
+        self.account_service.set_totp(account, Some(record))
+    }
+
+    fn verify_at(&self, account: &str, code: &str, now: u64) -> Result<bool, Error> {
+        let _lock = lock();
+
+        let mut record = match self.account_service.get_totp(account)? {
+            Some(record) if record.confirmed => record,
+            _ => return Ok(false),
+        };
+
+        if !check_code(&mut record, code, now, true)? {
+            return Ok(false);
+        }
+
+        self.account_service.set_totp(account, Some(record))?;
+        Ok(true)
+    }
+}
+
+/// Check a code, or a recovery code if they are allowed. The used time step is
+/// recorded and a used recovery code is removed, but the record is not saved.
+fn check_code(
+    record: &mut TotpRecord,
+    code: &str,
+    now: u64,
+    allow_recovery: bool,
+) -> Result<bool, Error> {
+    let secret = base32_decode(&record.secret).ok_or(ErrorKind::Unknown)?;
+    let code = code.trim();
+
+    // Check the code against the current time step and its neighbors. Steps
+    // which have already been used are skipped.
+    let current_step = time_step(now);
+    let first_step = current_step
+        .saturating_sub(ALLOWED_DRIFT)
+        .max(record.last_step + 1);
+
+    for step in first_step..=current_step + ALLOWED_DRIFT {
+        let expected = generate_code(&secret, step);
+
+        if bool::from(expected.as_bytes().ct_eq(code.as_bytes())) {
+            record.last_step = step;
+            return Ok(true);
+        }
+    }
+
+    if !allow_recovery {
+        return Ok(false);
+    }
+
+    // Check the recovery codes, removing the code if it matches
+    let hash = hash_secret(code);
+    let len_before = record.recovery_codes.len();
+    record
+        .recovery_codes
+        .retain(|recovery_code| recovery_code != &hash);
+
+    Ok(record.recovery_codes.len() != len_before)
+}
+
+/// Take the lock on the stored data. The data is still usable if another
+/// request panicked while holding the lock.
+fn lock() -> MutexGuard<'static, ()> {
+    TOTP_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Generate random bytes
+fn random_bytes(count: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; count];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes
+}
+
+/// Generate a random recovery code, formatted as hex
+fn generate_recovery_code() -> String {
+    random_bytes(RECOVERY_CODE_BYTES)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Get the current Unix timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Current time is older than epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TotpRecord, TotpService, TotpServiceImpl};
+    use crate::{
+        services::{
+            accounts::MockAccountService,
+            api_tokens::hash_secret,
+            totp::otp::{base32_decode, base32_encode, generate_code, time_step},
+        },
+        util::ErrorKind,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    /// Create a service where the admin account has an enrolled secret and a
+    /// single recovery code
+    fn enrolled_service() -> TotpServiceImpl {
+        let service = service();
+
+        service
+            .account_service
+            .set_totp(
+                "admin",
+                Some(TotpRecord {
+                    secret: base32_encode(SECRET),
+                    confirmed: true,
+                    last_step: 0,
+                    recovery_codes: vec![hash_secret("0123456789")],
+                }),
+            )
+            .unwrap();
+
+        service
+    }
+
+    /// Create a service which stores the data of each account in memory
+    fn service() -> TotpServiceImpl {
+        let records = Arc::new(Mutex::new(HashMap::<String, TotpRecord>::new()));
+        let mut account_service = MockAccountService::new();
+
+        let get_records = Arc::clone(&records);
+        account_service
+            .expect_get_totp()
+            .returning(move |name| Ok(get_records.lock().unwrap().get(name).cloned()));
+        account_service
+            .expect_set_totp()
+            .returning(move |name, totp| {
+                let mut records = records.lock().unwrap();
+
+                match totp {
+                    Some(totp) => records.insert(name.to_owned(), totp),
+                    None => records.remove(name),
+                };
+
+                Ok(())
+            });
+
+        TotpServiceImpl {
+            account_service: Box::new(account_service),
+        }
+    }
+
+    /// Without a stored secret, two-factor authentication is not enrolled
+    #[test]
+    fn not_enrolled() {
+        assert!(!service().is_enrolled("admin").unwrap());
+        assert!(enrolled_service().is_enrolled("admin").unwrap());
+    }
+
+    /// Enrolling produces an otpauth URI and recovery codes, which only work
+    /// once the secret is confirmed with a code
+    #[test]
+    fn enroll() {
+        let now = 1_000_000;
+        let service = service();
+        let enrollment = service.enroll("admin", "pi.hole").unwrap();
+        let secret = base32_decode(&enrollment.secret).unwrap();
+
+        assert!(enrollment.uri.starts_with(&format!(
+            "otpauth://totp/Pi-hole:pi.hole?secret={}&",
+            enrollment.secret
+        )));
+        assert_eq!(enrollment.recovery_codes.len(), 10);
+        assert!(!service.is_enrolled("admin").unwrap());
+        assert!(!service
+            .verify("admin", &enrollment.recovery_codes[0])
+            .unwrap());
+
+        assert_eq!(
+            service
+                .confirm_at("admin", &enrollment.recovery_codes[0], now)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Unauthorized
+        );
+        service
+            .confirm_at("admin", &generate_code(&secret, time_step(now)), now)
+            .unwrap();
+
+        assert!(service.is_enrolled("admin").unwrap());
+        assert!(service
+            .verify("admin", &enrollment.recovery_codes[0])
+            .unwrap());
+        assert_eq!(
+            service.confirm("admin", "000000").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    /// Enrolling again before confirming replaces the unconfirmed secret
+    #[test]
+    fn enroll_unconfirmed() {
+        let now = 1_000_000;
+        let service = service();
+        let first = service.enroll("admin", "pi.hole").unwrap();
+        let second = service.enroll("admin", "pi.hole").unwrap();
+        let first_secret = base32_decode(&first.secret).unwrap();
+        let second_secret = base32_decode(&second.secret).unwrap();
+
+        assert_ne!(first.secret, second.secret);
+        assert_eq!(
+            service
+                .confirm_at("admin", &generate_code(&first_secret, time_step(now)), now)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Unauthorized
+        );
+        service
+            .confirm_at("admin", &generate_code(&second_secret, time_step(now)), now)
+            .unwrap();
+    }
+
+    /// The label is percent-encoded in the otpauth URI
+    #[test]
+    fn enroll_label_encoding() {
+        let enrollment = service().enroll("admin", "my pi:hole").unwrap();
+
+        assert!(enrollment
+            .uri
+            .starts_with("otpauth://totp/Pi-hole:my%20pi%3Ahole?secret="));
+    }
+
+    /// Each account has its own secret
+    #[test]
+    fn per_account() {
+        let now = 1_000_000;
+        let service = enrolled_service();
+        let code = generate_code(SECRET, time_step(now));
+
+        assert!(!service.is_enrolled("viewer").unwrap());
+        assert!(!service.verify_at("viewer", &code, now).unwrap());
+        assert!(!service.verify("viewer", "0123456789").unwrap());
+
+        service.enroll("viewer", "pi.hole").unwrap();
+
+        assert!(!service.is_enrolled("viewer").unwrap());
+        assert!(service.is_enrolled("admin").unwrap());
+        assert!(service.verify_at("admin", &code, now).unwrap());
+    }
+
+    /// Enrolling twice is an error
+    #[test]
+    fn enroll_twice() {
+        assert_eq!(
+            enrolled_service()
+                .enroll("admin", "pi.hole")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    /// The code for the current time step is accepted, along with codes from
+    /// neighboring steps to allow for clock drift
+    #[test]
+    fn verify_code() {
+        let now = 1_000_000;
+        let step = time_step(now);
+
+        assert!(enrolled_service()
+            .verify_at("admin", &generate_code(SECRET, step), now)
+            .unwrap());
+        assert!(enrolled_service()
+            .verify_at("admin", &generate_code(SECRET, step - 1), now)
+            .unwrap());
+        assert!(enrolled_service()
+            .verify_at("admin", &generate_code(SECRET, step + 1), now)
+            .unwrap());
+        assert!(!enrolled_service()
+            .verify_at("admin", &generate_code(SECRET, step - 2), now)
+            .unwrap());
+        assert!(!enrolled_service()
+            .verify_at("admin", "000000", now)
+            .unwrap());
+    }
+
+    /// A code can not be used twice
+    #[test]
+    fn code_replay() {
+        let now = 1_000_000;
+        let service = enrolled_service();
+        let code = generate_code(SECRET, time_step(now));
+
+        assert!(service.verify_at("admin", &code, now).unwrap());
+        assert!(!service.verify_at("admin", &code, now).unwrap());
+    }
+
+    /// A recovery code can only be used once
+    #[test]
+    fn recovery_code() {
+        let service = enrolled_service();
+
+        assert!(service.verify_at("admin", "0123456789", 0).unwrap());
+        assert!(!service.verify_at("admin", "0123456789", 0).unwrap());
+    }
+
+    /// Disabling requires a valid code, and removes the secret
+    #[test]
+    fn disable() {
+        let service = enrolled_service();
+
+        assert_eq!(
+            service.disable("admin", "wrong").unwrap_err().kind(),
+            ErrorKind::Unauthorized
+        );
+        service.disable("admin", "0123456789").unwrap();
+        assert!(!service.is_enrolled("admin").unwrap());
+        assert_eq!(
+            service.disable("admin", "0123456789").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
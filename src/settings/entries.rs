@@ -101,6 +101,28 @@ pub trait ConfigEntry {
                 continue;
             }
 
+            // The password hash contains `=` and is quoted (see `write`), so
+            // it is read differently from the other entries
+            if let ValueType::WebPassword = self.value_type() {
+                if let Some(item) = line
+                    .strip_prefix(key.as_ref())
+                    .and_then(|rest| rest.strip_prefix('='))
+                {
+                    let item = item
+                        .strip_prefix('\'')
+                        .and_then(|item| item.strip_suffix('\''))
+                        .unwrap_or(item);
+
+                    return Ok(if item.is_empty() {
+                        self.get_default().to_owned()
+                    } else {
+                        item.to_owned()
+                    });
+                }
+
+                continue;
+            }
+
             let mut split = line.split('=');
 
             // Check if we found the key by checking if the line starts with `entry=`
@@ -143,7 +165,12 @@ pub trait ConfigEntry {
 
         // Append entry to working copy if not empty
         if !value.is_empty() {
-            let new_entry = format!("{}={}", key, value);
+            let new_entry = match self.value_type() {
+                // Password hashes contain `$`, which bash would expand when
+                // sourcing setupVars, so they are quoted
+                ValueType::WebPassword => format!("{}='{}'", key, value),
+                _ => format!("{}={}", key, value),
+            };
             entries.push(new_entry);
         }
 
@@ -547,6 +574,24 @@ mod tests {
         });
     }
 
+    /// Password hashes are quoted so bash does not expand them, and the quotes
+    /// are removed when reading
+    #[test]
+    fn write_web_password() {
+        let hash = "$argon2id$v=19$m=4096,t=3,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNo";
+
+        test_with_file(
+            PiholeFile::SetupVars,
+            "WEBPASSWORD=test_key\n",
+            &format!("WEBPASSWORD='{}'\n", hash),
+            |env| {
+                SetupVarsEntry::WebPassword.write(hash, &env).unwrap();
+
+                assert_eq!(SetupVarsEntry::WebPassword.read(&env).unwrap(), hash);
+            },
+        );
+    }
+
     /// Reading from a missing file returns the default value
     #[test]
     fn read_from_missing_file() {
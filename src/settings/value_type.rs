@@ -16,6 +16,10 @@ use std::{
     str::FromStr,
 };
 
+/// The prefix of a password hashed with Argon2. Only hashed web passwords can
+/// be written.
+pub const ARGON2_PREFIX: &str = "$argon2";
+
 /// Categories of allowable values, shared across settings files
 #[cfg_attr(test, derive(Debug))]
 pub enum ValueType {
@@ -42,6 +46,8 @@ pub enum ValueType {
     Path,
     PortNumber,
     Regex,
+    Url,
+    Wildcard,
     YesNo,
     WebPassword,
     String(&'static [&'static str]),
@@ -181,10 +187,19 @@ impl ValueType {
                 }
             }
             ValueType::Regex => Regex::new(value).is_ok(),
+            ValueType::Url => is_url_valid(value),
+            ValueType::Wildcard => {
+                // A hostname with all of its subdomains, such as *.example.com
+                value
+                    .strip_prefix("*.")
+                    .map_or(false, |hostname| ValueType::Hostname.is_valid(hostname))
+            }
             ValueType::YesNo => matches!(value, "yes" | "no"),
             ValueType::WebPassword => {
-                // Web password is a valid key, but altering it is disallowed
-                false
+                // Only hashed passwords can be written. Legacy values are
+                // still read, but never written. The hash is written in single
+                // quotes, so it can not contain any.
+                value.starts_with(ARGON2_PREFIX) && !value.contains('\'')
             }
             ValueType::String(strings) => strings.contains(&value),
             ValueType::LanguageCode => Regex::new("^[a-zA-Z]+(-[a-zA-Z]+)*$")
@@ -223,6 +238,38 @@ fn is_ipv6_valid(value: &str) -> bool {
     }
 }
 
+/// URL - Check that the value is an HTTP(S) URL with a valid host and
+/// optional port. These are the addresses which adlists can be downloaded
+/// from.
+fn is_url_valid(value: &str) -> bool {
+    let url_re = Regex::new(r"^(?i:https?)://([^/?#\s@]+)([/?#]\S*)?$").unwrap();
+
+    let authority = match url_re.captures(value) {
+        Some(captures) => captures.get(1).unwrap().as_str(),
+        None => return false,
+    };
+
+    // Split off the port, taking care not to split an IPv6 address
+    let (host, port) = match authority.rfind(':') {
+        Some(index) if !authority.ends_with(']') => {
+            (&authority[..index], Some(&authority[index + 1..]))
+        }
+        _ => (authority, None),
+    };
+
+    if let Some(port) = port {
+        if port.is_empty() || !ValueType::PortNumber.is_valid(port) {
+            return false;
+        }
+    }
+
+    if host.starts_with('[') && host.ends_with(']') {
+        is_ipv6_valid(&host[1..host.len() - 1])
+    } else {
+        ValueType::Hostname.is_valid(host) || is_ipv4_valid(host)
+    }
+}
+
 /// Get the address and port of an string representing an IPv6 address with or
 /// without a port.
 ///
@@ -298,7 +345,18 @@ mod tests {
             (ValueType::Path, "/tmp/directory/file.ext"),
             (ValueType::PortNumber, "9000"),
             (ValueType::Regex, "^.*example$"),
+            (
+                ValueType::Url,
+                "https://raw.githubusercontent.com/StevenBlack/hosts/master/hosts",
+            ),
+            (ValueType::Url, "http://192.168.1.10:8080/list.txt"),
+            (ValueType::Url, "http://[1fff:0:a88:85a3::ac1f]/list.txt"),
+            (ValueType::Wildcard, "*.example.com"),
             (ValueType::YesNo, "yes"),
+            (
+                ValueType::WebPassword,
+                "$argon2id$v=19$m=4096,t=3,p=1$c2FsdHNhbHQ$aGFzaGhhc2hoYXNo",
+            ),
             (ValueType::String(&["boxed", ""]), "boxed"),
         ];
 
@@ -357,7 +415,16 @@ mod tests {
             (ValueType::Path, "~/tmp/directory/file.ext"),
             (ValueType::PortNumber, "65536"),
             (ValueType::Regex, "example\\"),
+            (ValueType::Url, "example.com/hosts"),
+            (ValueType::Url, "file:///etc/hosts"),
+            (ValueType::Url, "ftp://example.com/hosts"),
+            (ValueType::Url, "https://exa$mple.com/hosts"),
+            (ValueType::Url, "https://example.com:99999/hosts"),
+            (ValueType::Wildcard, "example.com"),
+            (ValueType::Wildcard, "*.*.example.com"),
+            (ValueType::Wildcard, "*example.com"),
             (ValueType::YesNo, "true"),
+            (ValueType::WebPassword, "hunter2"),
             (ValueType::String(&["boxed", ""]), "lan"),
         ];
 
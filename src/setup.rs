@@ -12,21 +12,21 @@ use crate::{
     databases::{
         custom_connection::CustomSqliteConnection,
         ftl::{FtlDatabasePool, FtlDatabasePoolParameters},
-        gravity::{GravityDatabasePool, GravityDatabasePoolParameters},
+        gravity::{create_api_tables, GravityDatabasePool, GravityDatabasePoolParameters},
         load_ftl_db_config, load_gravity_db_config,
     },
     env::{Config, Env},
     ftl::FtlMemory,
     routes::{
-        auth::{self, AuthData},
+        auth::{self, AuthData, RetryAfter, TotpRequired},
         dns, settings, stats, version, web,
     },
-    services::PiholeModule,
+    services::{gravity::GravityJobs, PiholeModule},
     settings::{ConfigEntry, SetupVarsEntry},
     util::{Error, ErrorKind},
 };
 use failure::ResultExt;
-use rocket::{Build, Rocket};
+use rocket::{Build, Request, Rocket};
 use rocket_cors::CorsOptions;
 
 #[cfg(test)]
@@ -39,8 +39,25 @@ fn not_found() -> Error {
 }
 
 #[catch(401)]
-fn unauthorized() -> Error {
-    Error::from(ErrorKind::Unauthorized)
+fn unauthorized(request: &Request) -> Error {
+    let TotpRequired(totp_required) = request.local_cache(|| TotpRequired(false));
+
+    if *totp_required {
+        Error::from(ErrorKind::TotpRequired)
+    } else {
+        Error::from(ErrorKind::Unauthorized)
+    }
+}
+
+#[catch(403)]
+fn forbidden() -> Error {
+    Error::from(ErrorKind::Forbidden)
+}
+
+#[catch(429)]
+fn too_many_requests(request: &Request) -> Error {
+    let RetryAfter(retry_after) = request.local_cache(|| RetryAfter(0));
+    Error::from(ErrorKind::TooManyRequests(*retry_after))
 }
 
 /// Run the API normally (connect to FTL over the socket)
@@ -51,10 +68,13 @@ pub async fn start(config_location: &Path) -> Result<(), Error> {
 
     println!("{:#?}", env.config());
 
+    let gravity_pool = CustomSqliteConnection::pool(load_gravity_db_config(&env)?)
+        .context(ErrorKind::GravityDatabase)?;
+    create_api_tables(&gravity_pool.get().context(ErrorKind::GravityDatabase)?)?;
+
     let module = PiholeModule::builder()
         .with_component_parameters::<GravityDatabasePool>(GravityDatabasePoolParameters {
-            pool: CustomSqliteConnection::pool(load_gravity_db_config(&env)?)
-                .context(ErrorKind::GravityDatabase)?,
+            pool: gravity_pool,
         })
         .with_component_parameters::<FtlDatabasePool>(FtlDatabasePoolParameters {
             pool: CustomSqliteConnection::pool(load_ftl_db_config(&env)?)
@@ -151,13 +171,20 @@ fn setup(
         // Attach CORS handler
         .attach(cors)
         // Add custom error handlers
-        .register("/", catchers![not_found, unauthorized])
+        .register("/", catchers![
+            not_found,
+            unauthorized,
+            forbidden,
+            too_many_requests
+        ])
         // Manage the FTL shared memory configuration
         .manage(ftl_memory)
         // Manage the API key
-        .manage(AuthData::new(api_key))
+        .manage(AuthData::new(api_key, &config.auth))
         // Manage the scheduler
         .manage(scheduler)
+        // Manage the gravity update jobs
+        .manage(GravityJobs::default())
         // Manage the dependency injection module
         .manage(Box::new(module))
         // Mount the API
@@ -165,14 +192,33 @@ fn setup(
             version::version,
             auth::check,
             auth::logout,
+            auth::change_password,
+            auth::get_sessions,
+            auth::delete_session,
+            auth::get_lockouts,
+            auth::get_totp,
+            auth::enroll_totp,
+            auth::confirm_totp,
+            auth::disable_totp,
+            auth::get_accounts,
+            auth::create_account,
+            auth::delete_account,
+            auth::get_tokens,
+            auth::create_token,
+            auth::delete_token,
             stats::summary::get_summary,
             stats::top_domains::route,
             stats::top_clients::route,
             stats::upstreams::route,
+            stats::upstream_latency::route,
             stats::query_types::route,
             stats::history::route,
+            stats::history::history_stream,
+            stats::history::history_export,
             stats::recent_blocked::route,
             stats::clients::route,
+            stats::client_details::route,
+            stats::domain_details::route,
             stats::over_time_history::route,
             stats::over_time_clients::route,
             stats::database::summary_db::get_summary_db,
@@ -185,14 +231,44 @@ fn setup(
             dns::get_whitelist,
             dns::get_blacklist,
             dns::get_regexlist,
+            dns::get_wildcardlist,
             dns::get_status,
             dns::change_status,
             dns::add_whitelist,
             dns::add_blacklist,
             dns::add_regexlist,
+            dns::add_wildcardlist,
             dns::delete_whitelist,
             dns::delete_blacklist,
             dns::delete_regexlist,
+            dns::delete_wildcardlist,
+            dns::update_whitelist,
+            dns::update_blacklist,
+            dns::update_regexlist,
+            dns::update_wildcardlist,
+            dns::import_list,
+            dns::export_list,
+            dns::get_adlists,
+            dns::add_adlist,
+            dns::update_adlist,
+            dns::delete_adlist,
+            dns::update_gravity,
+            dns::get_audit,
+            dns::add_audit,
+            dns::delete_audit,
+            dns::get_gravity_job,
+            dns::get_gravity_job_events,
+            dns::get_groups,
+            dns::add_group,
+            dns::update_group,
+            dns::delete_group,
+            dns::get_group_members,
+            dns::add_group_domain,
+            dns::delete_group_domain,
+            dns::add_group_adlist,
+            dns::delete_group_adlist,
+            dns::lookup_domain,
+            dns::test_regex,
             settings::get_dhcp,
             settings::put_dhcp,
             settings::get_dns,
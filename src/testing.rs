@@ -156,10 +156,12 @@ pub struct TestBuilder {
     should_auth: bool,
     auth_required: bool,
     body_data: Option<serde_json::Value>,
+    raw_body_data: Option<(ContentType, String)>,
     ftl_data: HashMap<String, Vec<u8>>,
     ftl_memory: FtlMemory,
     test_env_builder: TestEnvBuilder,
     expected_json: serde_json::Value,
+    expected_raw: Option<String>,
     expected_status: Status,
     needs_database: bool,
     module_builder: ModuleBuilder<PiholeModule>,
@@ -174,6 +176,7 @@ impl TestBuilder {
             should_auth: true,
             auth_required: true,
             body_data: None,
+            raw_body_data: None,
             ftl_data: HashMap::new(),
             ftl_memory: FtlMemory::Test {
                 clients: Vec::new(),
@@ -190,6 +193,7 @@ impl TestBuilder {
                 "data": [],
                 "errors": []
             }),
+            expected_raw: None,
             expected_status: Status::Ok,
             needs_database: false,
             module_builder: PiholeModule::builder(),
@@ -227,6 +231,12 @@ impl TestBuilder {
         self
     }
 
+    /// Send a body which is not JSON, such as plain text
+    pub fn raw_body(mut self, content_type: ContentType, body: &str) -> Self {
+        self.raw_body_data = Some((content_type, body.to_owned()));
+        self
+    }
+
     pub fn ftl(mut self, command: &str, data: Vec<u8>) -> Self {
         self.ftl_data.insert(command.to_owned(), data);
         self
@@ -259,6 +269,12 @@ impl TestBuilder {
         self
     }
 
+    /// Expect a response body which is not JSON, such as plain text
+    pub fn expect_raw(mut self, expected_raw: &str) -> Self {
+        self.expected_raw = Some(expected_raw.to_owned());
+        self
+    }
+
     pub fn expect_status(mut self, status: Status) -> Self {
         self.expected_status = status;
         self
@@ -359,6 +375,11 @@ impl TestBuilder {
             request.set_body(serde_json::to_vec(&data).unwrap());
         }
 
+        if let Some((content_type, data)) = self.raw_body_data {
+            request.add_header(content_type);
+            request.set_body(data);
+        }
+
         // Dispatch the request
         println!("{:#?}", request);
         let response = request.dispatch();
@@ -374,11 +395,16 @@ impl TestBuilder {
         let body_str = body.unwrap();
         println!("Body:\n{}", body_str);
 
-        // Check that it is correct JSON
-        let parsed: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        if let Some(expected_raw) = self.expected_raw {
+            // Check that the body is exactly as expected
+            assert_eq!(expected_raw, body_str);
+        } else {
+            // Check that it is correct JSON
+            let parsed: serde_json::Value = serde_json::from_str(&body_str).unwrap();
 
-        // Check that is is the same as the expected JSON
-        assert_eq!(self.expected_json, parsed);
+            // Check that is is the same as the expected JSON
+            assert_eq!(self.expected_json, parsed);
+        }
 
         // Check the files against the expected data
         let mut buffer = String::new();
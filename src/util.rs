@@ -35,7 +35,11 @@ pub fn reply<D: Serialize>(data: Result<D, Error>, status: Status) -> Reply {
         Err(e) => {
             // Only print out the error if it's not a common error
             match e.kind() {
-                ErrorKind::Unauthorized | ErrorKind::NotFound => (),
+                ErrorKind::Unauthorized
+                | ErrorKind::TotpRequired
+                | ErrorKind::Forbidden
+                | ErrorKind::TooManyRequests(_)
+                | ErrorKind::NotFound => (),
                 _ => e.print_stacktrace(),
             }
 
@@ -103,6 +107,8 @@ pub enum ErrorKind {
     Unknown,
     #[fail(display = "Failed to create the blocklist")]
     GravityError,
+    #[fail(display = "Failed to download the adlist: {}", _0)]
+    AdlistDownload(String),
     #[fail(display = "Failed to connect to FTL")]
     FtlConnectionFail,
     #[fail(display = "Error reading from FTL")]
@@ -115,10 +121,18 @@ pub enum ErrorKind {
     AlreadyExists,
     #[fail(display = "Invalid domain")]
     InvalidDomain,
+    #[fail(display = "Invalid URL")]
+    InvalidUrl,
     #[fail(display = "Bad request")]
     BadRequest,
     #[fail(display = "Unauthorized")]
     Unauthorized,
+    #[fail(display = "A two-factor authentication code is required")]
+    TotpRequired,
+    #[fail(display = "Forbidden")]
+    Forbidden,
+    #[fail(display = "Too many failed attempts, retry after {} seconds", _0)]
+    TooManyRequests(u64),
     #[fail(display = "Error reading from {}", _0)]
     FileRead(String),
     #[fail(display = "Error writing to {}", _0)]
@@ -219,14 +233,19 @@ impl ErrorKind {
         match self {
             ErrorKind::Unknown => "unknown",
             ErrorKind::GravityError => "gravity_error",
+            ErrorKind::AdlistDownload(_) => "adlist_download",
             ErrorKind::FtlConnectionFail => "ftl_connection_fail",
             ErrorKind::FtlReadError => "ftl_read_error",
             ErrorKind::FtlEomError => "ftl_eom_error",
             ErrorKind::NotFound => "not_found",
             ErrorKind::AlreadyExists => "already_exists",
             ErrorKind::InvalidDomain => "invalid_domain",
+            ErrorKind::InvalidUrl => "invalid_url",
             ErrorKind::BadRequest => "bad_request",
             ErrorKind::Unauthorized => "unauthorized",
+            ErrorKind::TotpRequired => "totp_required",
+            ErrorKind::Forbidden => "forbidden",
+            ErrorKind::TooManyRequests(_) => "too_many_requests",
             ErrorKind::FileRead(_) => "file_read",
             ErrorKind::FileWrite(_) => "file_write",
             ErrorKind::ConfigParsingError => "config_parsing_error",
@@ -248,10 +267,14 @@ impl ErrorKind {
         match self {
             ErrorKind::NotFound => Status::NotFound,
             ErrorKind::AlreadyExists => Status::Conflict,
-            ErrorKind::InvalidDomain | ErrorKind::BadRequest | ErrorKind::InvalidSettingValue => {
-                Status::BadRequest
-            }
-            ErrorKind::Unauthorized => Status::Unauthorized,
+            ErrorKind::InvalidDomain
+            | ErrorKind::InvalidUrl
+            | ErrorKind::BadRequest
+            | ErrorKind::InvalidSettingValue => Status::BadRequest,
+            ErrorKind::Unauthorized | ErrorKind::TotpRequired => Status::Unauthorized,
+            ErrorKind::Forbidden => Status::Forbidden,
+            ErrorKind::TooManyRequests(_) => Status::TooManyRequests,
+            ErrorKind::AdlistDownload(_) => Status::BadGateway,
             ErrorKind::Unknown
             | ErrorKind::GravityError
             | ErrorKind::FtlConnectionFail
@@ -277,6 +300,7 @@ impl ErrorKind {
         match self {
             ErrorKind::FileRead(file) => Some(json!({ "file": file })),
             ErrorKind::FileWrite(file) => Some(json!({ "file": file })),
+            ErrorKind::TooManyRequests(retry_after) => Some(json!({ "retry_after": retry_after })),
             _ => None,
         }
     }
@@ -328,9 +352,21 @@ impl From<shmem::Error> for Error {
 
 impl<'r> Responder<'r, 'static> for Error {
     fn respond_to(self, request: &Request) -> response::Result<'static> {
+        // Tell the client when it can retry
+        let retry_after = match self.kind() {
+            ErrorKind::TooManyRequests(retry_after) => Some(retry_after),
+            _ => None,
+        };
+
         // This allows us to automatically use `reply_error` when we return an Error in
         // the API
-        reply_error(self).unwrap().respond_to(request)
+        let mut response = reply_error(self).unwrap().respond_to(request)?;
+
+        if let Some(retry_after) = retry_after {
+            response.set_raw_header("Retry-After", retry_after.to_string());
+        }
+
+        Ok(response)
     }
 }
 
@@ -174,6 +174,22 @@ UPDATE info
 SET value = 2
 WHERE property = 'version';
 
+-- Tables added by the API
+
+CREATE TABLE IF NOT EXISTS adlist_domain
+(
+    domain    TEXT    NOT NULL,
+    adlist_id INTEGER NOT NULL,
+    PRIMARY KEY (domain, adlist_id)
+);
+
+CREATE INDEX IF NOT EXISTS adlist_domain_adlist_id ON adlist_domain (adlist_id);
+
+CREATE TABLE IF NOT EXISTS regex_wildcard
+(
+    regex_id INTEGER PRIMARY KEY REFERENCES regex (id)
+);
+
 -- BEGIN TEST DATA
 
 INSERT INTO whitelist
@@ -186,7 +202,11 @@ VALUES (1, 'example.com', 1, 1557712177, 1557712177, NULL),
 
 INSERT INTO regex
 VALUES (1, '(^|\.)example\.com$', 1, 1557712181, 1557712181, NULL),
-       (2, 'disabled\-regex\.com', 0, 1557723854, 1557723872, NULL);
+       (2, 'disabled\-regex\.com', 0, 1557723854, 1557723872, NULL),
+       (3, '(^|\.)wildcard\.com$', 1, 1557723880, 1557723880, NULL);
+
+INSERT INTO regex_wildcard
+VALUES (3);
 
 INSERT INTO adlist
 VALUES (1, 'https://raw.githubusercontent.com/StevenBlack/hosts/master/hosts', 1, 1557712118, 1557712118,
@@ -194,6 +214,15 @@ VALUES (1, 'https://raw.githubusercontent.com/StevenBlack/hosts/master/hosts', 1
        (2, 'https://mirror1.malwaredomains.com/files/justdomains', 1, 1557712118, 1557712118,
         'Migrated from /etc/pihole/adlists.list');
 
+INSERT INTO "group"
+VALUES (1, 1, 'Test Group', 'A group for testing');
+
+INSERT INTO whitelist_by_group
+VALUES (1, 1);
+
+INSERT INTO adlist_by_group
+VALUES (1, 1);
+
 INSERT INTO gravity
 VALUES ('test.com'),
        ('vqubwduhbsd.com'),
@@ -206,5 +235,18 @@ VALUES ('test.com'),
        ('vra4.com'),
        ('vriaj.com');
 
+INSERT INTO adlist_domain
+VALUES ('test.com', 1),
+       ('vqubwduhbsd.com', 1),
+       ('vquf4tcdpt22px9l2jqqq.science', 1),
+       ('vqwdsvjygnah.com', 1),
+       ('vqxzysmhsvloijm12fsuswlu.download', 1),
+       ('vr-private-kunden-de.tk', 2),
+       ('vr-private-kundes-de.tk', 2),
+       ('vra.outbrain.com', 2),
+       ('vra4.com', 2),
+       ('vriaj.com', 1),
+       ('vriaj.com', 2);
+
 INSERT INTO domain_audit (domain)
 VALUES ('audited.domain');